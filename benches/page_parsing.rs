@@ -0,0 +1,65 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use sqlite_database_file_dissect::components::database::Database;
+
+const TEMPLATE_DATABASE: &str = "test-data/Chinook.db.4.analyze";
+const PAGE_SIZE: usize = 4096;
+// SQLITE_DISSECT_BENCH_BYTES overrides the size of the generated database
+const DEFAULT_BENCH_BYTES: usize = 2 << 30;
+
+// Chinook followed by copies of its btree pages until the file has `length` bytes, every page
+// parses on its own. The file is kept in the temp directory and reused by the next runs.
+fn generated_database(length: usize) -> PathBuf {
+    let path = env::temp_dir().join(format!("sqlite-dissect-bench-{}.db", length));
+    if fs::metadata(&path).map(|metadata| metadata.len() as usize == length).unwrap_or(false) {
+        return path;
+    }
+
+    let template = fs::read(TEMPLATE_DATABASE).unwrap();
+    let page_count = length / PAGE_SIZE;
+    let mut first_page = template[..PAGE_SIZE].to_vec();
+    // the in-header database size
+    first_page[28..32].copy_from_slice(&(page_count as u32).to_be_bytes());
+
+    let mut out = BufWriter::new(File::create(&path).unwrap());
+    out.write_all(&first_page).unwrap();
+    out.write_all(&template[PAGE_SIZE..]).unwrap();
+    let btree_pages: Vec<&[u8]> = template.chunks(PAGE_SIZE).skip(1).collect();
+    for page in btree_pages.iter().cycle().take(page_count - template.len() / PAGE_SIZE) {
+        out.write_all(page).unwrap();
+    }
+    out.flush().unwrap();
+    path
+}
+
+fn page_parsing(c: &mut Criterion) {
+    let length = env::var("SQLITE_DISSECT_BENCH_BYTES").ok()
+        .and_then(|bytes| bytes.parse().ok())
+        .unwrap_or(DEFAULT_BENCH_BYTES);
+    let path = generated_database(length);
+    let db = Database::open(path.to_str().unwrap()).unwrap();
+
+    let mut group = c.benchmark_group("page_parsing");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(length as u64));
+    group.bench_function("sequential", |b| b.iter(|| {
+        db.pages().filter(|(_, page)| page.is_ok()).count()
+    }));
+    group.bench_function("parallel", |b| b.iter(|| {
+        let mut count = 0;
+        db.for_each_page_parallel(|_, page| {
+            count += page.is_ok() as usize;
+            Ok(())
+        }).unwrap();
+        count
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, page_parsing);
+criterion_main!(benches);
@@ -6,3 +6,17 @@ pub mod page_header;
 pub mod cell_pointer;
 pub mod cell;
 pub mod record;
+pub mod btree;
+pub mod schema;
+pub mod statistics;
+pub mod space_usage;
+pub mod check;
+pub mod diff;
+pub mod table;
+pub mod row_diff;
+pub mod export;
+pub mod sqldump;
+pub mod graph;
+pub mod page_stream;
+pub mod writer;
+pub mod repair;
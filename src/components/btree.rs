@@ -0,0 +1,452 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde_derive::Serialize;
+
+use crate::components::cell::{Cell, CellRef};
+use crate::components::database::Database;
+use crate::components::page::Page;
+use crate::components::page_header::PageType;
+use crate::components::record::{Record, RecordRef};
+use crate::components::schema::SQLITE_SCHEMA_ROOT_PAGE;
+use crate::utils::error::{ErrorKind, MyError};
+use crate::utils::json::serialize_i64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableRow {
+    #[serde(serialize_with = "serialize_i64")]
+    pub row_id: i64,
+    pub record: Record,
+}
+
+enum WalkStep<'a> {
+    Page(u32),
+    Cell(CellRef<'a>),
+}
+
+/// The cells of a btree in key order, the walk ends at the first error.
+pub struct BtreeCells<'a> {
+    db: &'a Database,
+    traversal: BtreeTraversal,
+    stack: Vec<WalkStep<'a>>,
+    error: Option<MyError>,
+}
+
+impl<'a> BtreeCells<'a> {
+    // Stack the children and cells of a page, the cells of a table interior page only point
+    // to their children.
+    fn push_page(&mut self, page_number: u32) -> Result<(), MyError> {
+        let page = self.db.page_ref(page_number)?;
+        let is_index_interior = page.header.page_type == PageType::IndexInteriorBtreePage;
+
+        if let Some(right_most_pointer) = page.header.right_most_pointer {
+            if !self.traversal.claim(right_most_pointer, page_number) {
+                return Err(self.traversal.corrupted());
+            }
+            self.stack.push(WalkStep::Page(right_most_pointer));
+        }
+
+        for index in (0..page.cell_count()).rev() {
+            let cell = page.cell(index)?;
+            match cell.left_child_page_number()? {
+                Some(left_child_page_number) => {
+                    if !self.traversal.claim(left_child_page_number, page_number) {
+                        return Err(self.traversal.corrupted());
+                    }
+                    if is_index_interior {
+                        self.stack.push(WalkStep::Cell(cell));
+                    }
+                    self.stack.push(WalkStep::Page(left_child_page_number));
+                },
+                None => self.stack.push(WalkStep::Cell(cell)),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for BtreeCells<'a> {
+    type Item = Result<CellRef<'a>, MyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            self.stack.clear();
+            return Some(Err(error));
+        }
+        while let Some(step) = self.stack.pop() {
+            match step {
+                WalkStep::Cell(cell) => return Some(Ok(cell)),
+                WalkStep::Page(page_number) => {
+                    if let Err(error) = self.push_page(page_number) {
+                        self.stack.clear();
+                        return Some(Err(error));
+                    }
+                },
+            }
+        }
+        None
+    }
+}
+
+/// The rows of a table btree in rowid order, see `Database::table_row_iter`.
+pub struct TableRows<'a> {
+    db: &'a Database,
+    cells: BtreeCells<'a>,
+}
+
+impl<'a> Iterator for TableRows<'a> {
+    type Item = Result<TableRow, MyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let db = self.db;
+        for cell in &mut self.cells {
+            let row = cell.and_then(|cell| cell.to_cell()).and_then(|cell| match (cell.row_id, db.read_record(&cell)?) {
+                (Some(row_id), Some(record)) => Ok(Some(TableRow { row_id, record })),
+                _ => Ok(None),
+            });
+            match row {
+                Ok(Some(row)) => return Some(Ok(row)),
+                Ok(None) => continue,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        None
+    }
+}
+
+impl Database {
+    /// Visit every cell carrying a payload in key order. For index btrees the interior
+    /// cells are visited between their left child and the next subtree. A cycle or a bad
+    /// child pointer ends the walk with `BtreeCorrupted`.
+    pub fn walk_btree<F>(&self, root_page: u32, mut visit: F) -> Result<(), MyError>
+    where F: FnMut(&Cell) -> Result<(), MyError> {
+        self.walk_btree_refs(root_page, |cell| visit(&cell.to_cell()?))
+    }
+
+    /// `walk_btree` over cells borrowed from the pages, a cell is only decoded as far as
+    /// `visit` reads it.
+    pub fn walk_btree_refs<'a, F>(&'a self, root_page: u32, mut visit: F) -> Result<(), MyError>
+    where F: FnMut(&CellRef<'a>) -> Result<(), MyError> {
+        for cell in self.btree_cells(root_page) {
+            visit(&cell?)?;
+        }
+        Ok(())
+    }
+
+    /// The cells `walk_btree_refs` visits, pulled one at a time.
+    pub fn btree_cells(&self, root_page: u32) -> BtreeCells<'_> {
+        let mut traversal = BtreeTraversal::new(self.page_count());
+        let error = if traversal.claim(root_page, 0) { None } else { Some(traversal.corrupted()) };
+        BtreeCells {
+            db: self,
+            traversal,
+            stack: vec![WalkStep::Page(root_page)],
+            error,
+        }
+    }
+
+    /// The rows of a table btree in rowid order, read one at a time.
+    pub fn table_row_iter(&self, root_page: u32) -> TableRows<'_> {
+        TableRows {
+            db: self,
+            cells: self.btree_cells(root_page),
+        }
+    }
+
+    /// Like `for_each_table_row` without copying the rows out of the pages, only the
+    /// records of rows spilling into overflow pages are assembled in memory.
+    pub fn for_each_table_row_ref<F>(&self, root_page: u32, mut visit: F) -> Result<usize, MyError>
+    where F: FnMut(i64, &RecordRef<'_>) -> Result<(), MyError> {
+        let mut count = 0;
+        self.walk_btree_refs(root_page, |cell| {
+            if let (Some(row_id), Some(payload)) = (cell.row_id()?, self.payload_of(cell)?) {
+                visit(row_id, &RecordRef::try_from_be_bytes_encoded(&payload, &self.header().text_encoding)?)?;
+                count += 1;
+            }
+            Ok(())
+        })?;
+        Ok(count)
+    }
+
+    /// Visit the rows of a table btree in rowid order, one at a time, returns the number of rows.
+    pub fn for_each_table_row<F>(&self, root_page: u32, mut visit: F) -> Result<usize, MyError>
+    where F: FnMut(&TableRow) -> Result<(), MyError> {
+        let mut count = 0;
+        for row in self.table_row_iter(root_page) {
+            visit(&row?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    pub fn table_rows(&self, root_page: u32) -> Result<Vec<TableRow>, MyError> {
+        let mut rows = Vec::new();
+        self.for_each_table_row(root_page, |row| {
+            rows.push(row.clone());
+            Ok(())
+        })?;
+        Ok(rows)
+    }
+
+    pub fn index_entries(&self, root_page: u32) -> Result<Vec<Record>, MyError> {
+        let mut entries = Vec::new();
+        self.walk_btree(root_page, |cell| {
+            if let Some(record) = self.read_record(cell)? {
+                entries.push(record);
+            }
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    /// Number of rows of a table btree, or keys of an index btree.
+    pub fn count_entries(&self, root_page: u32) -> Result<usize, MyError> {
+        let mut count = 0;
+        self.walk_btree(root_page, |_| {
+            count += 1;
+            Ok(())
+        })?;
+        Ok(count)
+    }
+}
+
+pub const PAGE_NOT_TRAVELED: i32 = -100;
+pub const PAGE_IS_ROOT: i32 = -1;
+
+// deepest btree sqlite itself opens, BTCURSOR_MAX_DEPTH
+pub const MAX_BTREE_DEPTH: usize = 20;
+
+/// A child pointer which doesn't lead to a page of its own.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum TraversalProblem {
+    // the page is an ancestor of the page pointing at it
+    Cycle { page_number: u32, parent: u32 },
+    // the pointer is 0 or past the last page
+    DanglingPointer { page_number: u32, parent: u32 },
+    // a parent of 0 stands for a btree root
+    MultipleParents { page_number: u32, first_parent: u32, parent: u32 },
+    TooDeep { page_number: u32, depth: usize },
+    Unreadable { page_number: u32, error: String },
+}
+
+impl fmt::Display for TraversalProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parent_to_text = |parent: &u32| if *parent == 0 { "the schema".to_string() } else { format!("page {}", parent) };
+        match self {
+            TraversalProblem::Cycle { page_number, parent } =>
+                write!(f, "page {} points back to its ancestor page {}", parent, page_number),
+            TraversalProblem::DanglingPointer { page_number, parent } =>
+                write!(f, "page {} points to page {} which is out of range", parent, page_number),
+            TraversalProblem::MultipleParents { page_number, first_parent, parent } =>
+                write!(f, "page {} is referenced by both {} and {}", page_number, parent_to_text(first_parent), parent_to_text(parent)),
+            TraversalProblem::TooDeep { page_number, depth } =>
+                write!(f, "the children of page {} at depth {} are deeper than {} levels", page_number, depth, MAX_BTREE_DEPTH),
+            TraversalProblem::Unreadable { page_number, error } =>
+                write!(f, "page {} can't be parsed: {}", page_number, error),
+        }
+    }
+}
+
+/// The pages visited by the traversals of the btrees of one file, each page is visited once.
+pub struct BtreeTraversal {
+    // parent page number by page number, 0 for roots
+    parents: Vec<Option<u32>>,
+    // roots guessed from pages no btree of the schema reached
+    implicit_roots: HashSet<u32>,
+    pub problems: Vec<TraversalProblem>,
+}
+
+impl BtreeTraversal {
+    pub fn new(page_count: u32) -> Self {
+        BtreeTraversal {
+            parents: vec![None; page_count as usize + 1],
+            implicit_roots: HashSet::new(),
+            problems: Vec::new(),
+        }
+    }
+
+    pub fn is_visited(&self, page_number: u32) -> bool {
+        matches!(self.parents.get(page_number as usize), Some(Some(_)))
+    }
+
+    /// The page a visited page was reached from, 0 for roots.
+    pub fn parent(&self, page_number: u32) -> Option<u32> {
+        self.parents.get(page_number as usize).copied().flatten()
+    }
+
+    fn is_ancestor(&self, ancestor: u32, mut page_number: u32) -> bool {
+        // the parents never form a cycle, the bound only guards against mistakes
+        for _ in 0..self.parents.len() {
+            if page_number == ancestor {
+                return true;
+            }
+            match self.parents[page_number as usize] {
+                Some(parent) if parent != 0 => page_number = parent,
+                _ => return false,
+            }
+        }
+        false
+    }
+
+    /// Record `parent` (0 for a root) as the parent of `page_number`, false when the page must not be
+    /// visited: out of range, already visited or pointing into a cycle.
+    pub fn claim(&mut self, page_number: u32, parent: u32) -> bool {
+        if page_number == 0 || page_number as usize >= self.parents.len() {
+            self.problems.push(TraversalProblem::DanglingPointer { page_number, parent });
+            return false;
+        }
+        let first_parent = match self.parents[page_number as usize] {
+            None => {
+                self.parents[page_number as usize] = Some(parent);
+                return true;
+            },
+            Some(first_parent) => first_parent,
+        };
+
+        if parent != 0 && self.is_ancestor(page_number, parent) {
+            self.problems.push(TraversalProblem::Cycle { page_number, parent });
+        } else if parent != 0 && self.implicit_roots.remove(&page_number) {
+            // the guessed root was only the child of a page found later
+            self.parents[page_number as usize] = Some(parent);
+        } else {
+            self.problems.push(TraversalProblem::MultipleParents { page_number, first_parent, parent });
+        }
+        false
+    }
+
+    // The error for the last problem found.
+    fn corrupted(&self) -> MyError {
+        let problem = self.problems.last().map_or_else(String::new, |problem| problem.to_string());
+        MyError::new(ErrorKind::BtreeCorrupted(problem))
+    }
+
+    /// The parent page index of every page (indexes start from 0), `PAGE_IS_ROOT` for btree roots
+    /// and `PAGE_NOT_TRAVELED` for the pages no btree uses.
+    pub fn page_parents(&self) -> Vec<i32> {
+        self.parents.iter()
+            .skip(1)
+            .map(|parent| match parent {
+                None => PAGE_NOT_TRAVELED,
+                Some(0) => PAGE_IS_ROOT,
+                Some(parent) => *parent as i32 - 1,
+            })
+            .collect()
+    }
+}
+
+/// A btree page and its children, as shown by the tree view.
+#[derive(Debug, Serialize)]
+pub struct BtreeNode {
+    pub page_number: u32,
+    pub page_type: PageType,
+    pub cell_number: u16,
+    pub children: Vec<BtreeNode>,
+}
+
+impl Database {
+    /// Visit the pages of the btree rooted at `root_page` depth first, children in key order, with
+    /// their parent (0 for the root) and depth. Pages already visited by `traversal` are skipped,
+    /// bad pointers and unreadable pages are recorded in `traversal.problems`.
+    pub fn traverse_btree<F>(&self, root_page: u32, traversal: &mut BtreeTraversal, visit: F) -> Result<(), MyError>
+    where F: FnMut(&Page, u32, u32, usize) -> Result<(), MyError> {
+        if !traversal.claim(root_page, 0) {
+            return Ok(());
+        }
+        self.traverse_claimed_btree(root_page, None, traversal, visit)
+    }
+
+    // the traversal of a root page already claimed, `root` is its page when the caller parsed it
+    fn traverse_claimed_btree<F>(&self, root_page: u32, mut root: Option<Page>, traversal: &mut BtreeTraversal, mut visit: F) -> Result<(), MyError>
+    where F: FnMut(&Page, u32, u32, usize) -> Result<(), MyError> {
+        let mut stack: Vec<(u32, u32, usize)> = vec![(root_page, 0, 0)];
+        while let Some((page_number, parent, depth)) = stack.pop() {
+            let page = match root.take().map_or_else(|| self.read_page(page_number), Ok) {
+                Ok(page) => page,
+                Err(e) => {
+                    traversal.problems.push(TraversalProblem::Unreadable { page_number, error: e.to_string() });
+                    continue;
+                },
+            };
+            visit(&page, page_number, parent, depth)?;
+
+            let children: Vec<u32> = page.cells.iter()
+                .filter_map(|cell| cell.left_child_page_number)
+                .chain(page.header.right_most_pointer)
+                .collect();
+            if !children.is_empty() && depth + 1 >= MAX_BTREE_DEPTH {
+                traversal.problems.push(TraversalProblem::TooDeep { page_number, depth });
+                continue;
+            }
+            for child in children.into_iter().rev() {
+                if traversal.claim(child, page_number) {
+                    stack.push((child, page_number, depth + 1));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Traverse sqlite_schema and the btrees it lists, then every other page which parses as a
+    /// btree page, the way the tree view shows orphaned pages as roots.
+    pub fn btree_traversal(&self) -> Result<BtreeTraversal, MyError> {
+        let mut traversal = BtreeTraversal::new(self.page_count());
+        let mut roots = vec![SQLITE_SCHEMA_ROOT_PAGE];
+        if let Ok(schema) = self.schema() {
+            roots.extend(schema.iter().filter(|entry| entry.root_page > 0).map(|entry| entry.root_page));
+        }
+        for root_page in roots {
+            self.traverse_btree(root_page, &mut traversal, |_, _, _, _| Ok(()))?;
+        }
+
+        for page_number in 1..=self.page_count() {
+            // overflow, freelist and the lock-byte pages don't parse and stay untraveled
+            if traversal.is_visited(page_number) || self.is_lock_byte_page(page_number) {
+                continue;
+            }
+            let page = match self.read_page(page_number) {
+                Ok(page) => page,
+                Err(_) => continue,
+            };
+            traversal.claim(page_number, 0);
+            traversal.implicit_roots.insert(page_number);
+            self.traverse_claimed_btree(page_number, Some(page), &mut traversal, |_, _, _, _| Ok(()))?;
+        }
+        Ok(traversal)
+    }
+
+    /// The parent page index of every page (indexes start from 0), `PAGE_IS_ROOT` for btree roots.
+    pub fn page_parents(&self) -> Result<Vec<i32>, MyError> {
+        Ok(self.btree_traversal()?.page_parents())
+    }
+
+    /// The pages of a btree as a tree, bad child pointers are left out, see `btree_traversal` for them.
+    pub fn btree_node(&self, page_number: u32) -> Result<BtreeNode, MyError> {
+        let mut pages: Vec<(u32, BtreeNode)> = Vec::new();
+        let mut traversal = BtreeTraversal::new(self.page_count());
+        self.traverse_btree(page_number, &mut traversal, |page, page_number, parent, _| {
+            pages.push((parent, BtreeNode {
+                page_number,
+                page_type: page.header.page_type,
+                cell_number: page.header.cell_number,
+                children: Vec::new(),
+            }));
+            Ok(())
+        })?;
+
+        // pages come in depth first order, a node is complete once the pages after it are attached
+        let mut children: HashMap<u32, Vec<BtreeNode>> = HashMap::new();
+        let mut root = None;
+        while let Some((parent, mut node)) = pages.pop() {
+            if let Some(mut node_children) = children.remove(&node.page_number) {
+                node_children.reverse();
+                node.children = node_children;
+            }
+            if parent == 0 {
+                root = Some(node);
+            } else {
+                children.entry(parent).or_default().push(node);
+            }
+        }
+        root.ok_or_else(|| traversal.corrupted())
+    }
+}
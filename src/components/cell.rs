@@ -1,23 +1,38 @@
+use std::convert::TryFrom;
+
 use serde_derive::Serialize;
 
+use crate::components::database_header::TextEncoding;
 use crate::components::page_header::PageType;
-use crate::components::record::Record;
-use crate::utils::convert::TryFromBytes;
+use crate::components::record::{Record, RecordRef};
+use crate::utils::convert::{ToBytes, TryFromBytes};
 use crate::utils::error::MyError;
 use crate::utils::error::ErrorKind;
-use crate::utils::varint::decode_varint_to_usize;
+use crate::utils::json::{serialize_option_i64, serialize_option_u64};
+use crate::utils::varint::{decode_varint, decode_varint_i64, encode_varint, encode_varint_i64};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Cell {
     pub page_type: PageType,
     pub left_child_page_number: Option<u32>,
-    pub row_id: Option<usize>,
-    pub payload_length: Option<usize>,
+    #[serde(serialize_with = "serialize_option_i64")]
+    pub row_id: Option<i64>,
+    #[serde(serialize_with = "serialize_option_u64")]
+    pub payload_length: Option<u64>,
     pub payload: Option<Record>,
     pub overflow_page_number: Option<u32>,
     pub overflow_length: Option<usize>,
+    #[serde(skip_serializing)]
+    pub local_payload: Option<Vec<u8>>,
+    // the encoding the TEXT of the payload is written back in
+    #[serde(skip_serializing)]
+    pub text_encoding: TextEncoding,
 }
 
+pub type LocalPayload<'a> = (Option<&'a [u8]>/*payload*/,
+                         Option<u32>/*overflow_page_number*/,
+                         Option<usize>/*remaining page length in overflow page*/);
+
 impl Default for Cell {
     fn default() -> Self {
         Cell {
@@ -28,134 +43,252 @@ impl Default for Cell {
             payload: None,
             overflow_page_number: None,
             overflow_length: None,
+            local_payload: None,
+            text_encoding: TextEncoding::UTF8,
         }
     }
 }
 
 impl Cell {
-    pub fn try_from_bytes(bytes: &[u8], page_type: PageType, page_size: u16) -> Result<Cell, MyError> {
-
-        //payload_length
-        if page_type == PageType::IndexInteriorBtreePage {
-            return Self::build_index_interior_page_cell(&bytes, page_size.into());
-        } else if page_type == PageType::TableLeafBtreePage {
-            return Self::build_table_leaf_page_cell(&bytes, page_size.into());
-        } else if page_type == PageType::TableInteriorBtreePage {
-            return Self::build_table_interior_page_cell(&bytes);
-        } else if page_type == PageType::IndexLeafBtreePage {
-            return Self::build_index_leaf_page_cell(&bytes, page_size.into());
-        }
-
-        Err(
-            MyError::new(ErrorKind::UnreachableCode)
-        )
+    pub fn try_from_bytes(bytes: &[u8], page_type: PageType, page_size: usize) -> Result<Cell, MyError> {
+        CellRef::try_from_bytes(bytes, page_type, page_size)?.to_cell()
+    }
 
+    // A damaged cell may point past the end of its page.
+    fn slice(bytes: &[u8], start: usize, end: usize) -> Result<&[u8], MyError> {
+        bytes.get(start..end)
+            .ok_or_else(|| MyError::new(ErrorKind::SliceLengthError(end, bytes.len())))
     }
 
-    fn get_left_child_page_number(bytes: &[u8]) -> u32 {
-        u32::try_from_be_bytes(&bytes[0..4]).unwrap()
+    fn get_left_child_page_number(bytes: &[u8]) -> Result<u32, MyError> {
+        u32::try_from_be_bytes(Self::slice(bytes, 0, 4)?)
     }
 
-    fn get_payload(bytes: &[u8], payload_length: usize, page_size: usize, x: usize) -> (Option<&[u8]>/*payload*/, 
-                                                                                        Option<u32>/*overflow_page_number*/, 
-                                                                                        Option<usize>/*remaining page length in overflow page*/) {
+    fn get_payload(bytes: &[u8], payload_length: u64, page_size: usize, x: usize) -> Result<LocalPayload<'_>, MyError> {
         let u: usize = page_size;
-        let p: usize = payload_length;
+        let p: usize = usize::try_from(payload_length)
+            .map_err(|_| MyError::new(ErrorKind::PayloadTooLarge(payload_length)))?;
 
         if p <= x {
-            return (Some(&bytes[..payload_length]), None, None);
+            return Ok((Some(Self::slice(bytes, 0, p)?), None, None));
         } else {
             let m: usize = ((u-12)*32/255)-23;
             let k: usize = m+((p-m)%(u-4));
 
             if p > x && k <= x {
                 let k: usize = m+((p-m)%(u-4));
-                let overflow_page_number =  Self::get_overflow_page_number(&bytes[k..]);
-                return (Some(&bytes[0..k]), Some(overflow_page_number), Some(p - k)); 
+                let overflow_page_number =  Self::get_overflow_page_number(Self::slice(bytes, k, k + 4)?)?;
+                return Ok((Some(&bytes[0..k]), Some(overflow_page_number), Some(p - k))); 
             } else if p > x && k > x {
-                let overflow_page_number =  Self::get_overflow_page_number(&bytes[m..]);
-                return (Some(&bytes[0..m]), Some(overflow_page_number), Some(p - m)); 
+                let overflow_page_number =  Self::get_overflow_page_number(Self::slice(bytes, m, m + 4)?)?;
+                return Ok((Some(&bytes[0..m]), Some(overflow_page_number), Some(p - m))); 
             }
         } 
 
-        (None, None, None)
+        Ok((None, None, None))
     }
 
-    fn get_overflow_page_number(bytes: &[u8]) -> u32 {
-        u32::try_from_be_bytes(&bytes[0..=3]).unwrap()
-    } 
+    // A record spilling into overflow pages can't be decoded from the local part alone,
+    // its local bytes are kept so the full payload can be assembled later.
+    fn decode_local_record(payload: Option<&[u8]>, overflow_page_number: Option<u32>, text_encoding: &TextEncoding) -> Result<Option<Record>, MyError> {
+        match (payload, overflow_page_number) {
+            (Some(bytes), None) => Ok(Some(Record::try_from_be_bytes_encoded(bytes, text_encoding)?)),
+            _ => Ok(None),
+        }
+    }
 
-    fn build_table_interior_page_cell(bytes: &[u8]) -> Result<Cell, MyError> {
-        let left_child_page_number = Self::get_left_child_page_number(&bytes[0..4]);
-        let (row_id, _row_id_varint_len) = decode_varint_to_usize(&bytes[4..]).unwrap();
+    fn keep_local_payload(payload: Option<&[u8]>, overflow_page_number: Option<u32>) -> Option<Vec<u8>> {
+        match (payload, overflow_page_number) {
+            (Some(bytes), Some(_)) => Some(bytes.to_vec()),
+            _ => None,
+        }
+    }
 
-        Ok(Cell{
-            page_type: PageType::TableInteriorBtreePage,
-            left_child_page_number: Some(left_child_page_number),
-            row_id: Some(row_id),
-            ..Default::default()
-        })
+    fn get_overflow_page_number(bytes: &[u8]) -> Result<u32, MyError> {
+        u32::try_from_be_bytes(Self::slice(bytes, 0, 4)?)
+    } 
+}
+
+impl ToBytes for Cell {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if let Some(left_child_page_number) = self.left_child_page_number {
+            bytes.extend_from_slice(&left_child_page_number.to_be_bytes());
+        }
+        if let Some(payload_length) = self.payload_length {
+            bytes.extend_from_slice(&encode_varint(payload_length));
+        }
+        if let Some(row_id) = self.row_id {
+            bytes.extend_from_slice(&encode_varint_i64(row_id));
+        }
+        // only the local part of a payload spilling into overflow pages is kept
+        match (&self.payload, &self.local_payload) {
+            (Some(record), _) => match self.text_encoding {
+                TextEncoding::UTF16le | TextEncoding::UTF16be => bytes.extend_from_slice(&record.to_be_bytes_encoded(&self.text_encoding)),
+                _ => bytes.extend_from_slice(&record.to_be_bytes()),
+            },
+            (None, Some(local_payload)) => bytes.extend_from_slice(local_payload),
+            (None, None) => (),
+        }
+        if let Some(overflow_page_number) = self.overflow_page_number {
+            bytes.extend_from_slice(&overflow_page_number.to_be_bytes());
+        }
+        bytes
     }
+}
 
-    fn build_table_leaf_page_cell(bytes: &[u8], page_size: usize) -> Result<Cell, MyError> {
-        let (payload_length, payload_length_varint_len) = decode_varint_to_usize(&bytes).unwrap();
-        let (row_id, row_id_varint_len) = decode_varint_to_usize(&bytes[payload_length_varint_len..]).unwrap();
+// The fields in front of the payload of a cell.
+struct CellPrefix {
+    left_child_page_number: Option<u32>,
+    row_id: Option<i64>,
+    payload_length: Option<u64>,
+    payload_start_index: usize,
+    // the largest payload kept on the page, X in the file format description
+    max_local: usize,
+}
 
-        let u = page_size;
-        let x = u -35;
-        let payload_start_index = payload_length_varint_len + row_id_varint_len;
-        let (payload, overflow_page_number, overflow_length) = 
-        Self::get_payload(&bytes[payload_start_index..], payload_length, page_size, x);
+/// A cell read in place, from its offset to the end of the page. Nothing is decoded
+/// until a field is asked for, so skipping over cells costs nothing.
+#[derive(Debug, Clone, Copy)]
+pub struct CellRef<'a> {
+    pub page_type: PageType,
+    bytes: &'a [u8],
+    page_size: usize,
+    text_encoding: TextEncoding,
+}
 
-        Ok(Cell{
-            page_type: PageType::TableLeafBtreePage,
-            payload_length: Some(payload_length),
-            row_id: Some(row_id),
-            payload: match payload {
-                Some(b) => 
-                    Some(Record::try_from_be_bytes(&b).unwrap()), 
-                None => None, 
+impl<'a> CellRef<'a> {
+    pub fn try_from_bytes(bytes: &'a [u8], page_type: PageType, page_size: usize) -> Result<CellRef<'a>, MyError> {
+        match page_type {
+            PageType::TableInteriorBtreePage | PageType::TableLeafBtreePage |
+            PageType::IndexInteriorBtreePage | PageType::IndexLeafBtreePage => Ok(CellRef {
+                page_type,
+                bytes,
+                page_size,
+                text_encoding: TextEncoding::UTF8,
+            }),
+            _ => Err(MyError::new(ErrorKind::UnreachableCode)),
+        }
+    }
+
+    /// The cell of a database with `text_encoding`, the TEXT of its record is decoded with it.
+    pub fn with_text_encoding(self, text_encoding: TextEncoding) -> CellRef<'a> {
+        CellRef { text_encoding, ..self }
+    }
+
+    fn prefix(&self) -> Result<CellPrefix, MyError> {
+        let bytes = self.bytes;
+        let u = self.page_size;
+        let prefix = match self.page_type {
+            PageType::TableInteriorBtreePage => {
+                let left_child_page_number = Cell::get_left_child_page_number(bytes)?;
+                let (row_id, row_id_varint_len) = decode_varint_i64(&bytes[4..])?;
+                CellPrefix {
+                    left_child_page_number: Some(left_child_page_number),
+                    row_id: Some(row_id),
+                    payload_length: None,
+                    payload_start_index: 4 + row_id_varint_len,
+                    max_local: 0,
+                }
             },
-            overflow_page_number,
-            overflow_length, 
-            ..Default::default()
-        })
+            PageType::TableLeafBtreePage => {
+                let (payload_length, payload_length_varint_len) = decode_varint(bytes)?;
+                let (row_id, row_id_varint_len) = decode_varint_i64(&bytes[payload_length_varint_len..])?;
+                CellPrefix {
+                    left_child_page_number: None,
+                    row_id: Some(row_id),
+                    payload_length: Some(payload_length),
+                    payload_start_index: payload_length_varint_len + row_id_varint_len,
+                    max_local: u - 35,
+                }
+            },
+            PageType::IndexInteriorBtreePage => {
+                let left_child_page_number = Cell::get_left_child_page_number(bytes)?;
+                let (payload_length, payload_length_varint_len) = decode_varint(&bytes[4..])?;
+                CellPrefix {
+                    left_child_page_number: Some(left_child_page_number),
+                    row_id: None,
+                    payload_length: Some(payload_length),
+                    payload_start_index: 4 + payload_length_varint_len,
+                    max_local: ((u-12)*64/255)-23,
+                }
+            },
+            PageType::IndexLeafBtreePage => {
+                let (payload_length, payload_length_varint_len) = decode_varint(bytes)?;
+                CellPrefix {
+                    left_child_page_number: None,
+                    row_id: None,
+                    payload_length: Some(payload_length),
+                    payload_start_index: payload_length_varint_len,
+                    max_local: ((u-12)*64/255)-23,
+                }
+            },
+            _ => return Err(MyError::new(ErrorKind::UnreachableCode)),
+        };
+        Ok(prefix)
     }
 
-    fn build_index_interior_page_cell(bytes: &[u8], page_size: usize) -> Result<Cell, MyError> {
-        let left_child_page_number = Self::get_left_child_page_number(&bytes[0..4]);
-        let (payload_length, payload_length_varint_len) = decode_varint_to_usize(&bytes[4..]).unwrap();
-        let u = page_size;
-        let x = ((u-12)*64/255)-23;
-        let payload_start_index = 4 + payload_length_varint_len;
-        let (payload, overflow_page_number, overflow_length) = Self::get_payload(&bytes[payload_start_index..], payload_length, page_size, x);
+    fn local_payload_of(&self, prefix: &CellPrefix) -> Result<LocalPayload<'a>, MyError> {
+        match prefix.payload_length {
+            Some(payload_length) => Cell::get_payload(&self.bytes[prefix.payload_start_index..], payload_length, self.page_size, prefix.max_local),
+            None => Ok((None, None, None)),
+        }
+    }
 
-        Ok(Cell{
-            page_type: PageType::IndexInteriorBtreePage,
-            left_child_page_number: Some(left_child_page_number),
-            payload_length: Some(payload_length),
-            payload: match payload {Some(bytes) => Some(Record::try_from_be_bytes(&bytes).unwrap()), None => None, },
-            overflow_page_number,
-            overflow_length,
-            ..Default::default()
-        })
+    pub fn left_child_page_number(&self) -> Result<Option<u32>, MyError> {
+        match self.page_type {
+            PageType::TableInteriorBtreePage | PageType::IndexInteriorBtreePage =>
+                Ok(Some(Cell::get_left_child_page_number(self.bytes)?)),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn row_id(&self) -> Result<Option<i64>, MyError> {
+        Ok(self.prefix()?.row_id)
     }
 
-    fn build_index_leaf_page_cell(bytes: &[u8], page_size: usize) -> Result<Cell, MyError> {
-        let (payload_length, payload_length_varint_len) = decode_varint_to_usize(&bytes).unwrap();
-        let u = page_size;
-        let x = ((u-12)*64/255)-23;
-        let payload_start_index = payload_length_varint_len;
-        let (payload, overflow_page_number, overflow_length) = Self::get_payload(&bytes[payload_start_index..], payload_length, page_size, x);
+    pub fn payload_length(&self) -> Result<Option<u64>, MyError> {
+        Ok(self.prefix()?.payload_length)
+    }
+
+    /// The part of the payload stored on the page, the overflow page it continues on and
+    /// the length left for the overflow chain.
+    pub fn local_payload(&self) -> Result<LocalPayload<'a>, MyError> {
+        let prefix = self.prefix()?;
+        self.local_payload_of(&prefix)
+    }
+
+    /// The record when the payload fits on the page, `Database::payload_of` assembles the
+    /// payload of a record spilling into overflow pages.
+    pub fn record(&self) -> Result<Option<RecordRef<'a>>, MyError> {
+        match self.local_payload()? {
+            (Some(bytes), None, _) => Ok(Some(RecordRef::try_from_be_bytes_encoded(bytes, &self.text_encoding)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The bytes the cell takes on the page, up to its overflow page number.
+    pub fn size(&self) -> Result<usize, MyError> {
+        let prefix = self.prefix()?;
+        let (payload, overflow_page_number, _) = self.local_payload_of(&prefix)?;
+        let overflow_pointer_length = if overflow_page_number.is_some() { 4 } else { 0 };
+        Ok(prefix.payload_start_index + payload.map_or(0, |payload| payload.len()) + overflow_pointer_length)
+    }
+
+    pub fn to_cell(&self) -> Result<Cell, MyError> {
+        let prefix = self.prefix()?;
+        let (payload, overflow_page_number, overflow_length) = self.local_payload_of(&prefix)?;
 
         Ok(Cell{
-            page_type: PageType::IndexLeafBtreePage,
-            payload_length: Some(payload_length),
-            payload: match payload {Some(bytes) => Some(Record::try_from_be_bytes(&bytes).unwrap()), None => None, },
+            page_type: self.page_type,
+            left_child_page_number: prefix.left_child_page_number,
+            row_id: prefix.row_id,
+            payload_length: prefix.payload_length,
+            payload: Cell::decode_local_record(payload, overflow_page_number, &self.text_encoding)?,
             overflow_page_number,
             overflow_length,
-            ..Default::default()
+            local_payload: Cell::keep_local_payload(payload, overflow_page_number),
+            text_encoding: self.text_encoding,
         })
     }
 }
-
@@ -5,7 +5,7 @@ use serde_derive::Serialize;
 
 use crate::utils::error::MyError;
 use crate::utils::error::ErrorKind;
-use crate::utils::convert::TryFromBytes;
+use crate::utils::convert::{ToBytes, TryFromBytes};
 
 #[derive(Debug, Serialize)]
 pub struct CellPointer {
@@ -44,3 +44,9 @@ impl TryFrom<&[u8]> for CellPointer {
         CellPointer::try_from_be_bytes(value)
     }
 }
+
+impl ToBytes for CellPointer {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        self.offset.to_be_bytes().to_vec()
+    }
+}
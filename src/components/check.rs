@@ -0,0 +1,161 @@
+use serde_derive::Serialize;
+
+use crate::components::btree::{BtreeTraversal, TraversalProblem};
+use crate::components::database::Database;
+use crate::components::schema::SQLITE_SCHEMA_ROOT_PAGE;
+use crate::utils::convert::TryFromBytes;
+
+#[derive(Debug, Serialize)]
+pub struct CheckReport {
+    pub page_count: u32,
+    pub problems: Vec<String>,
+}
+
+impl CheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+// Records which object every page belongs to while the file is checked.
+struct PageOwners {
+    owners: Vec<Option<String>>,
+    problems: Vec<String>,
+}
+
+impl PageOwners {
+    // false when the page must not be visited: out of range or already owned
+    fn claim(&mut self, page_number: u32, owner: &str) -> bool {
+        if page_number == 0 || page_number as usize >= self.owners.len() {
+            self.problems.push(format!("{} references page {} which is out of range", owner, page_number));
+            return false;
+        }
+        match &self.owners[page_number as usize] {
+            Some(previous_owner) => {
+                self.problems.push(format!("page {} is used by both {} and {}", page_number, previous_owner, owner));
+                false
+            },
+            None => {
+                self.owners[page_number as usize] = Some(owner.to_string());
+                true
+            },
+        }
+    }
+}
+
+impl Database {
+    fn check_overflow_chain(&self, first_page_number: u32, overflow_length: usize, owner: &str, owners: &mut PageOwners) {
+        let mut remaining = overflow_length;
+        let mut page_number = first_page_number;
+        while remaining > 0 {
+            if !owners.claim(page_number, owner) {
+                return;
+            }
+            let bytes = self.page_bytes(page_number).unwrap();
+            remaining -= remaining.min(self.usable_size() - 4);
+            page_number = u32::try_from_be_bytes(&bytes[0..4]).unwrap();
+        }
+    }
+
+    // The btree pages are claimed by the traversal, which reports cycles and bad pointers, and
+    // owned by the btree so that overflow chains and the freelist can't use them too.
+    fn check_btree(&self, name: &str, root_page: u32, traversal: &mut BtreeTraversal, owners: &mut PageOwners) {
+        let problem_count = traversal.problems.len();
+        let mut overflow_chains: Vec<(u32, usize)> = Vec::new();
+        let traversed = self.traverse_btree(root_page, traversal, |page, page_number, _, _| {
+            owners.claim(page_number, name);
+            overflow_chains.extend(page.cells.iter().filter_map(|cell| Some((cell.overflow_page_number?, cell.overflow_length?))));
+            Ok(())
+        });
+        if let Err(e) = traversed {
+            owners.problems.push(format!("{} can't be traversed: {}", name, e));
+        }
+        for (overflow_page_number, overflow_length) in overflow_chains {
+            self.check_overflow_chain(overflow_page_number, overflow_length, &format!("overflow of {}", name), owners);
+        }
+
+        for problem in traversal.problems[problem_count..].iter() {
+            let owner_of = |page_number: u32| owners.owners.get(page_number as usize).cloned().flatten();
+            let text = match problem {
+                // a page reached from two btrees, or twice from one
+                TraversalProblem::MultipleParents { page_number, .. } => match owner_of(*page_number) {
+                    Some(owner) => format!("page {} is used by both {} and {}", page_number, owner, name),
+                    None => format!("{} of {}", problem, name),
+                },
+                TraversalProblem::Unreadable { page_number, error } => format!("page {} of {} can't be parsed: {}", page_number, name, error),
+                problem => format!("{} in {}", problem, name),
+            };
+            owners.problems.push(text);
+        }
+    }
+
+    fn check_freelist(&self, owners: &mut PageOwners) {
+        let mut freelist_pages = 0;
+        let mut trunk_page_number = self.header().first_freelist_trunk_page_number;
+        while trunk_page_number != 0 {
+            if !owners.claim(trunk_page_number, "freelist trunk") {
+                break;
+            }
+            freelist_pages += 1;
+            let bytes = self.page_bytes(trunk_page_number).unwrap();
+            let leaf_number = u32::try_from_be_bytes(&bytes[4..8]).unwrap() as usize;
+            if 8 + leaf_number * 4 > bytes.len() {
+                owners.problems.push(format!("freelist trunk page {} claims {} leaves", trunk_page_number, leaf_number));
+                break;
+            }
+            for i in 0..leaf_number {
+                let leaf_page_number = u32::try_from_be_bytes(&bytes[8 + i * 4..12 + i * 4]).unwrap();
+                if owners.claim(leaf_page_number, "freelist leaf") {
+                    freelist_pages += 1;
+                }
+            }
+            trunk_page_number = u32::try_from_be_bytes(&bytes[0..4]).unwrap();
+        }
+
+        let expected = self.header().total_freelist_page_number;
+        if freelist_pages != expected {
+            owners.problems.push(format!("the header counts {} freelist pages but {} were found", expected, freelist_pages));
+        }
+    }
+
+    /// Walk every btree, overflow chain and the freelist, making sure each page is used exactly once.
+    /// The file length must agree with the page count too.
+    pub fn check(&self) -> CheckReport {
+        let page_count = self.page_count();
+        let mut owners = PageOwners {
+            owners: vec![None; page_count as usize + 1],
+            problems: self.size().problems,
+        };
+        // nothing may use the lock-byte page, owning it up front reports whatever points to it
+        if let Some(page_number) = self.lock_byte_page() {
+            owners.claim(page_number, "the lock-byte page");
+        }
+
+        let mut traversal = BtreeTraversal::new(page_count);
+        if let Some(page_number) = self.lock_byte_page() {
+            traversal.claim(page_number, 0);
+        }
+        self.check_btree("sqlite_schema", SQLITE_SCHEMA_ROOT_PAGE, &mut traversal, &mut owners);
+        match self.schema() {
+            Ok(schema) => {
+                for entry in schema.iter().filter(|entry| entry.root_page > 0) {
+                    self.check_btree(&entry.name, entry.root_page, &mut traversal, &mut owners);
+                }
+            },
+            Err(e) => owners.problems.push(format!("the schema can't be read: {}", e)),
+        }
+        self.check_freelist(&mut owners);
+
+        for page_number in 1..=page_count {
+            // unreadable btree pages are reported already
+            if owners.owners[page_number as usize].is_none() && !traversal.is_visited(page_number) {
+                owners.problems.push(format!("page {} is never used", page_number));
+            }
+        }
+
+        CheckReport {
+            page_count,
+            problems: owners.problems,
+        }
+    }
+}
@@ -1,40 +1,244 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::Read;
+
+use serde_derive::Serialize;
+
+use crate::components::cell::{Cell, CellRef};
 use crate::components::file_manager::FileManager;
 use crate::components::database_header::DatabaseHeader;
+use crate::components::database_header::has_sqlite_header_string;
+use crate::components::page::{Page, PageRef};
+use crate::components::record::Record;
+use crate::utils::convert::TryFromBytes;
+use crate::utils::error::MyError;
+use crate::utils::error::ErrorKind;
+
+pub const DATABASE_HEADER_LENGTH: usize = 100;
+/// The byte sqlite locks files on, the page holding it is never a btree or freelist page.
+pub const PENDING_BYTE: usize = 0x4000_0000;
+
+/// The page holding the lock bytes for a page size, whether the file reaches it or not.
+pub fn lock_byte_page_number(page_size: usize) -> u32 {
+    (PENDING_BYTE / page_size + 1) as u32
+}
 
 pub struct Database {
     fm: FileManager,
     header: DatabaseHeader,
-    //freelist: Vec<()>,
-    //pages: Vec<()>, 
+    page_count: u32,
 }
 
-
-
-pub struct CellPointer {
-    pointer: u16,
+/// The page count sqlite works with and how the file length agrees with it.
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseSize {
+    pub page_size: usize,
+    pub file_length: usize,
+    // whole pages in the file, and the bytes of the partial page after them
+    pub file_pages: u32,
+    pub trailing_bytes: usize,
+    pub in_header_database_size: u32,
+    // the size in the header is ignored when it is 0 or the file was last written by sqlite before 3.7.0
+    pub in_header_size_valid: bool,
+    pub effective_page_count: u32,
+    pub problems: Vec<String>,
 }
 
-pub struct Cell {
-    left_child_page_number: u32,
-    payload_length: u64,
-    rowid: u64,
-    payload: Vec<u8>,
-    overflow_page_number: u32,
-
-
+impl DatabaseSize {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
 }
 
 /**
 * implement of Database
 */
-
 impl Database {
-    //pub fn new(file_manager: &FileManager) -> Self{
+    /// The header in the first 100 bytes of a file, read even when the file doesn't open as a
+    /// database so `validate` can tell what is wrong with it.
+    pub fn read_header(file_name: &str) -> Result<DatabaseHeader, MyError> {
+        DatabaseHeader::try_from_be_bytes(&Database::read_header_bytes(file_name)?)
+    }
 
-        //let mut db: Database = Database { fm: file_manager, header: (), freelist: (), pages: () };
+    /// The first 100 bytes of a file, fewer when the file is shorter.
+    pub fn read_header_bytes(file_name: &str) -> Result<Vec<u8>, MyError> {
+        let mut bytes = Vec::with_capacity(DATABASE_HEADER_LENGTH);
+        File::open(file_name)?.take(DATABASE_HEADER_LENGTH as u64).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
 
-        //let database_header_bytes = file_manager.read(0, 100);
+    pub fn open(file_name: &str) -> Result<Database, MyError> {
+        let fm = FileManager::open(file_name)?;
+        if fm.len() < DATABASE_HEADER_LENGTH {
+            return Err(MyError::new(ErrorKind::SliceLengthError(DATABASE_HEADER_LENGTH, fm.len())));
+        }
+        if !has_sqlite_header_string(fm.slice(0, DATABASE_HEADER_LENGTH)) {
+            return Err(MyError::new(ErrorKind::NotDatabaseFile));
+        }
+        let header = DatabaseHeader::try_from_be_bytes(fm.slice(0, DATABASE_HEADER_LENGTH))?;
+        if !header.has_valid_page_size() {
+            return Err(MyError::new(ErrorKind::InvalidPageSize(header.page_size_in_bytes() as usize)));
+        }
 
-    //}
-}
+        let mut db = Database {
+            fm,
+            header,
+            page_count: 0,
+        };
+        let size = db.size();
+        db.page_count = size.effective_page_count.min(size.file_pages);
+        Ok(db)
+    }
+
+    pub fn header(&self) -> &DatabaseHeader {
+        &self.header
+    }
+
+    pub fn file_length(&self) -> usize {
+        self.fm.len()
+    }
+
+    //the value 1 in the header means 65536
+    pub fn page_size(&self) -> usize {
+        match self.header.page_size {
+            1 => 65536,
+            page_size => page_size as usize,
+        }
+    }
+
+    pub fn usable_size(&self) -> usize {
+        self.page_size() - self.header.bytes_at_unused_page_end as usize
+    }
 
+    /// The pages read from the file: the in-header database size when sqlite trusts it, the
+    /// file length otherwise, never more than the whole pages the file holds.
+    pub fn page_count(&self) -> u32 {
+        self.page_count
+    }
+
+    /// The page holding the lock bytes, only files larger than 1 GiB reach it.
+    pub fn lock_byte_page(&self) -> Option<u32> {
+        let page_number = lock_byte_page_number(self.page_size());
+        if page_number <= self.page_count() { Some(page_number) } else { None }
+    }
+
+    pub fn is_lock_byte_page(&self, page_number: u32) -> bool {
+        self.lock_byte_page() == Some(page_number)
+    }
+
+    /// Work the page count out the way sqlite does: the in-header database size is used when it
+    /// isn't 0 and `version_valid_for_number` equals `file_change_count`, otherwise the file length
+    /// rounded up to whole pages. A file shorter than the header says is truncated.
+    pub fn size(&self) -> DatabaseSize {
+        let page_size = self.page_size();
+        let file_length = self.fm.len();
+        // open refuses a page size of 0, a file too large for u32 pages is counted as u32::MAX
+        let file_pages: u32 = file_length.checked_div(page_size).and_then(|pages| pages.try_into().ok()).unwrap_or(u32::MAX);
+        let trailing_bytes = file_length.checked_rem(page_size).unwrap_or(0);
+        let in_header_database_size = self.header.in_header_database_size;
+        let in_header_size_valid = in_header_database_size != 0 && self.header.version_valid_for_number == self.header.file_change_count;
+        // sqlite reads the partial page as if it was padded with zeros
+        let file_page_count = file_pages.saturating_add(if trailing_bytes > 0 { 1 } else { 0 });
+        let effective_page_count = if in_header_size_valid { in_header_database_size } else { file_page_count };
+
+        let mut problems = Vec::new();
+        if trailing_bytes > 0 {
+            problems.push(format!("the file ends with a partial page of {} bytes after its {} whole pages", trailing_bytes, file_pages));
+        }
+        if in_header_size_valid && in_header_database_size > file_page_count {
+            problems.push(format!("the header counts {} pages but the file holds {}, it is truncated", in_header_database_size, file_page_count));
+        } else if in_header_size_valid && in_header_database_size < file_page_count {
+            problems.push(format!("the file holds {} pages, the {} pages after the {} the header counts are ignored",
+                file_page_count, file_page_count - in_header_database_size, in_header_database_size));
+        } else if !in_header_size_valid && in_header_database_size != file_page_count {
+            problems.push(format!("the header counts {} pages but it is stale, the {} pages of the file length are used", in_header_database_size, file_page_count));
+        }
+
+        DatabaseSize {
+            page_size,
+            file_length,
+            file_pages,
+            trailing_bytes,
+            in_header_database_size,
+            in_header_size_valid,
+            effective_page_count,
+            problems,
+        }
+    }
+
+    /// The raw bytes of a page, `page_number` starts from 1 like the btree pointers do.
+    pub fn page_bytes(&self, page_number: u32) -> Result<&[u8], MyError> {
+        let page_count = self.page_count();
+        if page_number == 0 || page_number > page_count {
+            return Err(MyError::new(ErrorKind::PageNumberOutOfRange(page_number, page_count)));
+        }
+        let page_size = self.page_size();
+        Ok(self.fm.slice((page_number as usize - 1) * page_size, page_size))
+    }
+
+    pub fn read_page(&self, page_number: u32) -> Result<Page, MyError> {
+        self.page_ref(page_number)?.to_page()
+    }
+
+    /// The page borrowed from the memory map, its cells are decoded when they are asked for.
+    /// The lock-byte page isn't parsed, its bytes are still there with `page_bytes`.
+    pub fn page_ref(&self, page_number: u32) -> Result<PageRef<'_>, MyError> {
+        if self.is_lock_byte_page(page_number) {
+            return Err(MyError::new(ErrorKind::LockBytePage(page_number)));
+        }
+        let bytes = self.page_bytes(page_number)?;
+        let header_start_index = if page_number == 1 { DATABASE_HEADER_LENGTH } else { 0 };
+        Ok(PageRef::try_from_be_bytes(&bytes[..self.usable_size()], Some(header_start_index))?.with_text_encoding(self.header.text_encoding))
+    }
+
+    /// Local payload of the cell followed by the content of its overflow page chain.
+    pub fn read_full_payload(&self, cell: &Cell) -> Result<Vec<u8>, MyError> {
+        match (&cell.local_payload, cell.overflow_page_number, cell.overflow_length) {
+            (Some(local_payload), Some(page_number), Some(length)) => self.follow_overflow_chain(local_payload, page_number, length),
+            _ => Err(MyError::new(ErrorKind::UnreachableCode)),
+        }
+    }
+
+    /// The whole payload of the cell, borrowed from the page when it isn't spilled into
+    /// overflow pages. None for the cells of table interior pages.
+    pub fn payload_of<'a>(&'a self, cell: &CellRef<'a>) -> Result<Option<Cow<'a, [u8]>>, MyError> {
+        match cell.local_payload()? {
+            (Some(local_payload), None, _) => Ok(Some(Cow::Borrowed(local_payload))),
+            (Some(local_payload), Some(page_number), Some(length)) =>
+                Ok(Some(Cow::Owned(self.follow_overflow_chain(local_payload, page_number, length)?))),
+            _ => Ok(None),
+        }
+    }
+
+    fn follow_overflow_chain(&self, local_payload: &[u8], overflow_page_number: u32, overflow_length: usize) -> Result<Vec<u8>, MyError> {
+        let mut payload = local_payload.to_vec();
+        let mut remaining = overflow_length;
+        let mut page_number = overflow_page_number;
+        while remaining > 0 && page_number != 0 {
+            let bytes = self.page_bytes(page_number)?;
+            let next_page_number = u32::try_from_be_bytes(&bytes[0..4])?;
+            let content_length = remaining.min(self.usable_size() - 4);
+            payload.extend_from_slice(&bytes[4..4 + content_length]);
+            remaining -= content_length;
+            page_number = next_page_number;
+        }
+
+        if remaining > 0 {
+            return Err(MyError::new(ErrorKind::SliceLengthError(overflow_length, overflow_length - remaining)));
+        }
+        Ok(payload)
+    }
+
+    /// The record stored in the cell, following overflow pages when needed.
+    pub fn read_record(&self, cell: &Cell) -> Result<Option<Record>, MyError> {
+        if cell.payload.is_some() {
+            return Ok(cell.payload.clone());
+        }
+        if cell.overflow_page_number.is_none() {
+            return Ok(None);
+        }
+        let payload = self.read_full_payload(cell)?;
+        Ok(Some(Record::try_from_be_bytes_encoded(&payload, &self.header.text_encoding)?))
+    }
+}
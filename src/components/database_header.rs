@@ -1,34 +1,126 @@
 use serde_derive::Serialize;
 
-use num_derive::FromPrimitive;
-use num_derive::ToPrimitive;
-
 use crate::utils::error::MyError;
 use crate::utils::error::ErrorKind;
-use crate::utils::convert::TryFromBytes;
+use crate::utils::convert::{ToBytes, TryFromBytes};
+
+// The numbered fields of the header, the known variants are numbered from 1 and any other
+// number read from a file is kept as it is for `validate` to report.
+trait HeaderEnum: Sized + Clone + std::fmt::Debug + 'static {
+    const KNOWN: &'static [Self];
+
+    fn unknown(number: u32) -> Self;
 
-#[derive(Debug, FromPrimitive, ToPrimitive, Serialize)]
+    fn known_number(&self) -> Option<u32>;
+
+    fn from_number(number: u32) -> Self {
+        match number {
+            1..=255 => Self::KNOWN.get(number as usize - 1).cloned().unwrap_or_else(|| Self::unknown(number)),
+            _ => Self::unknown(number),
+        }
+    }
+
+    fn number(&self) -> u32;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum FileFormatVersion {
-    Legacy = 1,
-    WAL = 2,
+    Legacy,
+    WAL,
+    Unknown(u8),
 }
 
-#[derive(Debug, FromPrimitive, ToPrimitive, Serialize)]
+impl HeaderEnum for FileFormatVersion {
+    const KNOWN: &'static [Self] = &[FileFormatVersion::Legacy, FileFormatVersion::WAL];
+
+    fn unknown(number: u32) -> Self {
+        FileFormatVersion::Unknown(number as u8)
+    }
+
+    fn known_number(&self) -> Option<u32> {
+        match self {
+            FileFormatVersion::Legacy => Some(1),
+            FileFormatVersion::WAL => Some(2),
+            FileFormatVersion::Unknown(_) => None,
+        }
+    }
+
+    fn number(&self) -> u32 {
+        match self {
+            FileFormatVersion::Unknown(number) => *number as u32,
+            known => known.known_number().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum SchemaFormatNumber {
-    One = 1,
-    Two = 2,
-    Three = 3,
-    Four = 4,
+    One,
+    Two,
+    Three,
+    Four,
+    Unknown(u32),
+}
+
+impl HeaderEnum for SchemaFormatNumber {
+    const KNOWN: &'static [Self] = &[SchemaFormatNumber::One, SchemaFormatNumber::Two, SchemaFormatNumber::Three, SchemaFormatNumber::Four];
+
+    fn unknown(number: u32) -> Self {
+        SchemaFormatNumber::Unknown(number)
+    }
+
+    fn known_number(&self) -> Option<u32> {
+        match self {
+            SchemaFormatNumber::One => Some(1),
+            SchemaFormatNumber::Two => Some(2),
+            SchemaFormatNumber::Three => Some(3),
+            SchemaFormatNumber::Four => Some(4),
+            SchemaFormatNumber::Unknown(_) => None,
+        }
+    }
+
+    fn number(&self) -> u32 {
+        match self {
+            SchemaFormatNumber::Unknown(number) => *number,
+            known => known.known_number().unwrap_or_default(),
+        }
+    }
 }
 
-#[derive(Debug, FromPrimitive, ToPrimitive, Serialize)]
+/// The encoding of every string of the file, `Unknown(0)` until sqlite writes the first table.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum TextEncoding {
-    UTF8 = 1,
-    UTF16le = 2,
-    UTF16be = 3,
+    UTF8,
+    UTF16le,
+    UTF16be,
+    Unknown(u32),
 }
 
-#[derive(Debug, Serialize)]
+impl HeaderEnum for TextEncoding {
+    const KNOWN: &'static [Self] = &[TextEncoding::UTF8, TextEncoding::UTF16le, TextEncoding::UTF16be];
+
+    fn unknown(number: u32) -> Self {
+        TextEncoding::Unknown(number)
+    }
+
+    fn known_number(&self) -> Option<u32> {
+        match self {
+            TextEncoding::UTF8 => Some(1),
+            TextEncoding::UTF16le => Some(2),
+            TextEncoding::UTF16be => Some(3),
+            TextEncoding::Unknown(_) => None,
+        }
+    }
+
+    fn number(&self) -> u32 {
+        match self {
+            TextEncoding::Unknown(number) => *number,
+            known => known.known_number().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct DatabaseHeader {
     pub header_string: String,
     pub page_size: u16,
@@ -55,10 +147,39 @@ pub struct DatabaseHeader {
     pub sqlite_version_number: u32,
 }
 
+/// How bad a header issue is: sqlite refuses the file on errors, warnings are values it copes with.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A header field holding a value it should not.
+#[derive(Debug, Clone, Serialize)]
+pub struct HeaderIssue {
+    pub severity: Severity,
+    pub field: String,
+    pub message: String,
+}
+
 /**
 * implement of DatabaseHeader
 */
-pub const SQLITE_DB_HEADER_STRING: &'static str = "SQLite format 3\0";
+pub const SQLITE_DB_HEADER_STRING: &str = "SQLite format 3\0";
+
+pub fn has_sqlite_header_string(bytes: &[u8]) -> bool {
+    bytes.starts_with(SQLITE_DB_HEADER_STRING.as_bytes())
+}
+
+/// Write the fields `after` changed from `before` into the header bytes `before` was read from,
+/// every other byte is kept as it is even when it doesn't survive a parse.
+pub fn patch_header_bytes(bytes: &mut [u8], before: &DatabaseHeader, after: &DatabaseHeader) {
+    for (byte, (old, new)) in bytes.iter_mut().zip(before.to_be_bytes().into_iter().zip(after.to_be_bytes())) {
+        if old != new {
+            *byte = new;
+        }
+    }
+}
 
 impl Default for DatabaseHeader {
    fn default() -> Self { 
@@ -90,6 +211,184 @@ impl Default for DatabaseHeader {
     }
 }
 
+// Unsigned 32 bits, user_version and application_id are read as signed by sqlite and may be negative.
+fn parse_u32(field: &str, value: &str, signed: bool) -> Result<u32, MyError> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse::<u32>().ok().or_else(|| if signed { value.parse::<i32>().ok().map(|v| v as u32) } else { None }),
+    };
+    let domain = if signed { "a 32 bit integer" } else { "an unsigned 32 bit integer" };
+    parsed.ok_or_else(|| invalid_value(field, value, domain))
+}
+
+fn invalid_value(field: &str, value: &str, domain: &str) -> MyError {
+    MyError::new(ErrorKind::InvalidHeaderValue(field.to_string(), value.to_string(), domain.to_string()))
+}
+
+// The names of the known variants with their numbers.
+fn variant_names<T: HeaderEnum>() -> String {
+    let names: Vec<String> = T::KNOWN.iter().enumerate().map(|(idx, variant)| format!("{} ({:?})", idx + 1, variant)).collect();
+    names.join(", ")
+}
+
+// A known variant of an enum field, named or numbered.
+fn parse_variant<T: HeaderEnum>(field: &str, value: &str) -> Result<T, MyError> {
+    let by_number = value.parse::<u32>().ok().map(T::from_number);
+    let by_name = T::KNOWN.iter()
+        .find(|variant| format!("{:?}", variant).eq_ignore_ascii_case(&value.replace('-', "")))
+        .cloned();
+    by_number.or(by_name)
+        .filter(|variant| variant.known_number().is_some())
+        .ok_or_else(|| invalid_value(field, value, &format!("one of {}", variant_names::<T>())))
+}
+
+// An issue for an enum field holding a number sqlite doesn't know, 0 is allowed where sqlite
+// leaves it until the first table is written.
+fn unknown_variant_issue<T: HeaderEnum>(field: &str, variant: &T, zero_allowed: bool, consequence: &str) -> Option<HeaderIssue> {
+    if variant.known_number().is_some() || (zero_allowed && variant.number() == 0) {
+        return None;
+    }
+    Some(HeaderIssue {
+        severity: Severity::Error,
+        field: field.to_string(),
+        message: format!("{} is not one of {}, {}", variant.number(), variant_names::<T>(), consequence),
+    })
+}
+
+impl DatabaseHeader {
+    /// The page size in bytes, 1 stands for 65536.
+    pub fn page_size_in_bytes(&self) -> u32 {
+        if self.page_size == 1 { 65536 } else { self.page_size as u32 }
+    }
+
+    /// A power of two between 512 and 65536 leaving at least 480 usable bytes, the pages of any
+    /// other size can't be read.
+    pub fn has_valid_page_size(&self) -> bool {
+        let page_size = self.page_size_in_bytes();
+        page_size.is_power_of_two() && (512..=65536).contains(&page_size) && page_size - self.bytes_at_unused_page_end as u32 >= 480
+    }
+
+    /// Check the fields against the file format, an empty list means the header is sound.
+    pub fn validate(&self) -> Vec<HeaderIssue> {
+        let mut issues = Vec::new();
+        let mut issue = |severity: Severity, field: &str, message: String| issues.push(HeaderIssue {
+            severity,
+            field: field.to_string(),
+            message,
+        });
+
+        if self.header_string != SQLITE_DB_HEADER_STRING {
+            issue(Severity::Error, "header_string", format!("{:?} is not the magic string {:?} every database starts with", self.header_string, SQLITE_DB_HEADER_STRING));
+        }
+        let page_size = self.page_size_in_bytes();
+        if !page_size.is_power_of_two() || !(512..=65536).contains(&page_size) {
+            issue(Severity::Error, "page_size", format!("{} is not a power of two between 512 and 65536", self.page_size));
+        } else if page_size - (self.bytes_at_unused_page_end as u32) < 480 {
+            issue(Severity::Error, "bytes_at_unused_page_end", format!("{} reserved bytes leave less than the 480 usable bytes a page needs", self.bytes_at_unused_page_end));
+        }
+        for (field, value, expected) in [
+            ("max_embedded_payload_fraction", self.max_embedded_payload_fraction, 64),
+            ("min_embedded_payload_fraction", self.min_embedded_payload_fraction, 32),
+            ("leaf_payload_fraction", self.leaf_payload_fraction, 32),
+        ].iter() {
+            if value != expected {
+                issue(Severity::Error, field, format!("{} is not {}, the only value sqlite accepts", value, expected));
+            }
+        }
+        if self.version_valid_for_number != self.file_change_count {
+            issue(Severity::Warning, "version_valid_for_number", format!(
+                "{} differs from the file change count {}, the file was last written by a version of sqlite older than 3.7.0: the database size in the header is ignored and the file length is trusted instead",
+                self.version_valid_for_number, self.file_change_count));
+        }
+        if self.reserved.iter().any(|byte| *byte != 0) {
+            issue(Severity::Warning, "reserved", "the area reserved for expansion must be zero".to_string());
+        }
+        if self.incremental_vacuum_mode != 0 && self.largest_root_btree_page_number == 0 {
+            issue(Severity::Warning, "incremental_vacuum_mode", "incremental vacuum is set but auto vacuum is off, it has no effect".to_string());
+        }
+        issues.extend(unknown_variant_issue("file_format_read_version", &self.file_format_read_version, false, "sqlite refuses to read the file"));
+        issues.extend(unknown_variant_issue("file_format_write_version", &self.file_format_write_version, false, "sqlite only opens the file read-only"));
+        issues.extend(unknown_variant_issue("schema_format_number", &self.schema_format_number, true, "sqlite can't read the schema"));
+        issues.extend(unknown_variant_issue("text_encoding", &self.text_encoding, true, "the strings of the file can't be decoded"));
+        issues
+    }
+
+    /// The fields `set_field` changes, the header string and the reserved area can't be set.
+    pub const FIELDS: [&'static str; 21] = [
+        "page_size", "file_format_read_version", "file_format_write_version", "bytes_at_unused_page_end",
+        "max_embedded_payload_fraction", "min_embedded_payload_fraction", "leaf_payload_fraction",
+        "file_change_count", "in_header_database_size", "first_freelist_trunk_page_number",
+        "total_freelist_page_number", "schema_cookie", "schema_format_number", "default_page_cache_size",
+        "largest_root_btree_page_number", "text_encoding", "user_version", "incremental_vacuum_mode",
+        "application_id", "version_valid_for_number", "sqlite_version_number",
+    ];
+
+    /// The fields the pages are laid out or their text encoded by, a new value only in the
+    /// header makes every page or string read wrong.
+    pub const PAGE_LAYOUT_FIELDS: [&'static str; 3] = ["page_size", "bytes_at_unused_page_end", "text_encoding"];
+
+    /// Set a field from its text, which must lie in the domain of the field: a power of two
+    /// for the page size, a variant name or number for the enums, the only values sqlite
+    /// accepts for the payload fractions.
+    pub fn set_field(&mut self, field: &str, value: &str) -> Result<(), MyError> {
+        let value = value.trim();
+        match field {
+            "page_size" => {
+                let page_size = value.parse::<u32>().ok()
+                    .filter(|page_size| page_size.is_power_of_two() && (512..=65536).contains(page_size))
+                    .ok_or_else(|| invalid_value(field, value, "a power of two from 512 to 65536"))?;
+                let usable_size = page_size - self.bytes_at_unused_page_end as u32;
+                if usable_size < 480 {
+                    return Err(invalid_value(field, value, "a page size leaving 480 usable bytes"));
+                }
+                // 65536 doesn't fit, 1 stands for it
+                self.page_size = if page_size == 65536 { 1 } else { page_size as u16 };
+            },
+            "file_format_read_version" => self.file_format_read_version = parse_variant(field, value)?,
+            "file_format_write_version" => self.file_format_write_version = parse_variant(field, value)?,
+            "bytes_at_unused_page_end" => {
+                let page_size = self.page_size_in_bytes();
+                self.bytes_at_unused_page_end = value.parse::<u8>().ok()
+                    .filter(|reserved| page_size.saturating_sub(*reserved as u32) >= 480)
+                    .ok_or_else(|| invalid_value(field, value, "0 to 255 bytes leaving 480 usable bytes"))?;
+            },
+            "max_embedded_payload_fraction" | "min_embedded_payload_fraction" | "leaf_payload_fraction" => {
+                let expected = if field == "max_embedded_payload_fraction" { 64 } else { 32 };
+                if value.parse::<u8>().ok() != Some(expected) {
+                    return Err(invalid_value(field, value, &expected.to_string()));
+                }
+                match field {
+                    "max_embedded_payload_fraction" => self.max_embedded_payload_fraction = expected,
+                    "min_embedded_payload_fraction" => self.min_embedded_payload_fraction = expected,
+                    _ => self.leaf_payload_fraction = expected,
+                }
+            },
+            "file_change_count" => self.file_change_count = parse_u32(field, value, false)?,
+            "in_header_database_size" => self.in_header_database_size = parse_u32(field, value, false)?,
+            "first_freelist_trunk_page_number" => self.first_freelist_trunk_page_number = parse_u32(field, value, false)?,
+            "total_freelist_page_number" => self.total_freelist_page_number = parse_u32(field, value, false)?,
+            "schema_cookie" => self.schema_cookie = parse_u32(field, value, false)?,
+            "schema_format_number" => self.schema_format_number = parse_variant(field, value)?,
+            "default_page_cache_size" => self.default_page_cache_size = parse_u32(field, value, true)?,
+            "largest_root_btree_page_number" => self.largest_root_btree_page_number = parse_u32(field, value, false)?,
+            "text_encoding" => self.text_encoding = parse_variant(field, value)?,
+            "user_version" => self.user_version = parse_u32(field, value, true)?,
+            "incremental_vacuum_mode" => {
+                self.incremental_vacuum_mode = match value {
+                    "0" => 0,
+                    "1" => 1,
+                    _ => return Err(invalid_value(field, value, "0 or 1")),
+                };
+            },
+            "application_id" => self.application_id = parse_u32(field, value, true)?,
+            "version_valid_for_number" => self.version_valid_for_number = parse_u32(field, value, false)?,
+            "sqlite_version_number" => self.sqlite_version_number = parse_u32(field, value, false)?,
+            _ => return Err(MyError::new(ErrorKind::UnknownHeaderField(field.to_string()))),
+        }
+        Ok(())
+    }
+}
+
 impl TryFromBytes for DatabaseHeader {
 
     fn try_from_le_bytes(_bytes: &[u8]) -> Result<Self, MyError> {
@@ -97,14 +396,17 @@ impl TryFromBytes for DatabaseHeader {
     }
 
     fn try_from_be_bytes(bytes: &[u8]) -> Result<Self, MyError> {
+        if bytes.len() < 100 {
+            return Err(MyError::new(ErrorKind::SliceLengthError(100, bytes.len())));
+        }
         //header_string
         let header_string = String::from_utf8_lossy(&bytes[0..=15]);
         //page_size
         let page_size = u16::try_from_be_bytes(&bytes[16..=17]).unwrap();
         //file_format_read_version
-        let file_format_read_version: FileFormatVersion = num::FromPrimitive::from_u8(bytes[18]).unwrap();
+        let file_format_read_version = FileFormatVersion::from_number(bytes[18] as u32);
         //file_format_write_version
-        let file_format_write_version: FileFormatVersion = num::FromPrimitive::from_u8(bytes[19]).unwrap();
+        let file_format_write_version = FileFormatVersion::from_number(bytes[19] as u32);
         //bytes_at_unused_page_end
         let bytes_at_unused_page_end = bytes[20];
         //max_embedded_payload_fraction
@@ -125,13 +427,13 @@ impl TryFromBytes for DatabaseHeader {
         //schema_cookie
         let schema_cookie = u32::try_from_be_bytes(&bytes[40..=43]).unwrap();
         //schema_format_number
-        let schema_format_number: SchemaFormatNumber = num::FromPrimitive::from_u32(u32::try_from_be_bytes(&bytes[44..=47]).unwrap()).unwrap();
+        let schema_format_number = SchemaFormatNumber::from_number(u32::try_from_be_bytes(&bytes[44..=47]).unwrap());
         //default_page_cache_size
         let default_page_cache_size = u32::try_from_be_bytes(&bytes[48..=51]).unwrap();
         //largest_root_btree_page_number
         let largest_root_btree_page_number = u32::try_from_be_bytes(&bytes[52..=55]).unwrap();
         //text_encoding
-        let text_encoding: TextEncoding = num::FromPrimitive::from_u32(u32::try_from_be_bytes(&bytes[56..=59]).unwrap()).unwrap();
+        let text_encoding = TextEncoding::from_number(u32::try_from_be_bytes(&bytes[56..=59]).unwrap());
         //user_version
         let user_version = u32::try_from_be_bytes(&bytes[60..=63]).unwrap();
         //incremental_vacuum_mode
@@ -175,3 +477,36 @@ impl TryFromBytes for DatabaseHeader {
         Ok(db_header)
     }
 }
+
+impl ToBytes for DatabaseHeader {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(100);
+        let mut header_string = [0; 16];
+        let len = self.header_string.len().min(16);
+        header_string[..len].copy_from_slice(&self.header_string.as_bytes()[..len]);
+        bytes.extend_from_slice(&header_string);
+        bytes.extend_from_slice(&self.page_size.to_be_bytes());
+        bytes.push(self.file_format_read_version.number() as u8);
+        bytes.push(self.file_format_write_version.number() as u8);
+        bytes.push(self.bytes_at_unused_page_end);
+        bytes.push(self.max_embedded_payload_fraction);
+        bytes.push(self.min_embedded_payload_fraction);
+        bytes.push(self.leaf_payload_fraction);
+        bytes.extend_from_slice(&self.file_change_count.to_be_bytes());
+        bytes.extend_from_slice(&self.in_header_database_size.to_be_bytes());
+        bytes.extend_from_slice(&self.first_freelist_trunk_page_number.to_be_bytes());
+        bytes.extend_from_slice(&self.total_freelist_page_number.to_be_bytes());
+        bytes.extend_from_slice(&self.schema_cookie.to_be_bytes());
+        bytes.extend_from_slice(&self.schema_format_number.number().to_be_bytes());
+        bytes.extend_from_slice(&self.default_page_cache_size.to_be_bytes());
+        bytes.extend_from_slice(&self.largest_root_btree_page_number.to_be_bytes());
+        bytes.extend_from_slice(&self.text_encoding.number().to_be_bytes());
+        bytes.extend_from_slice(&self.user_version.to_be_bytes());
+        bytes.extend_from_slice(&self.incremental_vacuum_mode.to_be_bytes());
+        bytes.extend_from_slice(&self.application_id.to_be_bytes());
+        bytes.extend_from_slice(&self.reserved);
+        bytes.extend_from_slice(&self.version_valid_for_number.to_be_bytes());
+        bytes.extend_from_slice(&self.sqlite_version_number.to_be_bytes());
+        bytes
+    }
+}
@@ -0,0 +1,275 @@
+use serde_derive::Serialize;
+use serde_json::Value;
+
+use crate::components::database::Database;
+use crate::components::database_header::DatabaseHeader;
+use crate::components::page::Page;
+use crate::components::page_header::PageType;
+use crate::components::record::Column;
+use crate::utils::error::MyError;
+use crate::utils::json::serialize_i64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum PageChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Cells of table pages are told apart by rowid, cells of index pages by their whole entry.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum CellKey {
+    RowId(#[serde(serialize_with = "serialize_i64")] i64),
+    Entry(Vec<Column>),
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnChange {
+    pub column: usize,
+    pub before: Option<Column>,
+    pub after: Option<Column>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CellChange {
+    pub key: CellKey,
+    pub left_child_before: Option<u32>,
+    pub left_child_after: Option<u32>,
+    pub columns: Vec<ColumnChange>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CellDiff {
+    pub inserted: Vec<CellKey>,
+    pub deleted: Vec<CellKey>,
+    pub changed: Vec<CellChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageDiff {
+    pub page_number: u32,
+    pub change: PageChange,
+    pub page_type_before: Option<PageType>,
+    pub page_type_after: Option<PageType>,
+    // only for btree pages of the same type on both sides
+    pub cells: Option<CellDiff>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeaderChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseDiff {
+    pub page_count_before: u32,
+    pub page_count_after: u32,
+    pub header: Vec<HeaderChange>,
+    pub pages: Vec<PageDiff>,
+}
+
+struct KeyedCell {
+    key: CellKey,
+    left_child: Option<u32>,
+    columns: Vec<Column>,
+}
+
+fn column_to_text(column: &Option<Column>) -> String {
+    match column {
+        Some(column) => column.to_sql_literal(),
+        None => "(none)".to_string(),
+    }
+}
+
+fn key_to_text(key: &CellKey) -> String {
+    match key {
+        CellKey::RowId(row_id) => format!("rowid {}", row_id),
+        CellKey::Entry(columns) => format!("entry ({})",
+            columns.iter().map(|column| column.to_sql_literal()).collect::<Vec<String>>().join(", ")),
+    }
+}
+
+fn diff_cells(before: &[KeyedCell], after: &[KeyedCell]) -> CellDiff {
+    let mut cell_diff = CellDiff::default();
+    for after_cell in after.iter() {
+        let before_cell = match before.iter().find(|before_cell| before_cell.key == after_cell.key) {
+            Some(before_cell) => before_cell,
+            None => {
+                cell_diff.inserted.push(after_cell.key.clone());
+                continue;
+            },
+        };
+
+        let columns: Vec<ColumnChange> = (0..before_cell.columns.len().max(after_cell.columns.len()))
+            .filter(|&idx| before_cell.columns.get(idx) != after_cell.columns.get(idx))
+            .map(|idx| ColumnChange {
+                column: idx,
+                before: before_cell.columns.get(idx).cloned(),
+                after: after_cell.columns.get(idx).cloned(),
+            })
+            .collect();
+        if !columns.is_empty() || before_cell.left_child != after_cell.left_child {
+            cell_diff.changed.push(CellChange {
+                key: after_cell.key.clone(),
+                left_child_before: before_cell.left_child,
+                left_child_after: after_cell.left_child,
+                columns,
+            });
+        }
+    }
+    cell_diff.deleted = before.iter()
+        .filter(|before_cell| !after.iter().any(|after_cell| after_cell.key == before_cell.key))
+        .map(|before_cell| before_cell.key.clone())
+        .collect();
+    cell_diff
+}
+
+/// The header fields whose value differs.
+pub fn diff_headers(before: &DatabaseHeader, after: &DatabaseHeader) -> Vec<HeaderChange> {
+    let before = serde_json::to_value(before).unwrap();
+    let after = serde_json::to_value(after).unwrap();
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => before.into_iter()
+            .filter(|(field, value)| after.get(field) != Some(value))
+            .map(|(field, value)| HeaderChange {
+                after: after.get(&field).cloned().unwrap_or(Value::Null),
+                field,
+                before: value,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl Database {
+    // Records spilling to overflow pages are read whole, cells which can't be read keep no columns.
+    fn keyed_cells(&self, page: &Page) -> Vec<KeyedCell> {
+        page.cells.iter()
+            .map(|cell| {
+                let columns = match self.read_record(cell) {
+                    Ok(Some(record)) => record.columns,
+                    _ => Vec::new(),
+                };
+                match cell.row_id {
+                    Some(row_id) => KeyedCell {
+                        key: CellKey::RowId(row_id),
+                        left_child: cell.left_child_page_number,
+                        columns,
+                    },
+                    None => KeyedCell {
+                        key: CellKey::Entry(columns),
+                        left_child: cell.left_child_page_number,
+                        columns: Vec::new(),
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn diff_page(&self, other: &Database, page_number: u32) -> Result<Option<PageDiff>, MyError> {
+        if page_number > other.page_count() {
+            return Ok(Some(PageDiff {
+                page_number,
+                change: PageChange::Removed,
+                page_type_before: self.read_page(page_number).ok().map(|page| page.header.page_type),
+                page_type_after: None,
+                cells: None,
+            }));
+        }
+        if page_number > self.page_count() {
+            return Ok(Some(PageDiff {
+                page_number,
+                change: PageChange::Added,
+                page_type_before: None,
+                page_type_after: other.read_page(page_number).ok().map(|page| page.header.page_type),
+                cells: None,
+            }));
+        }
+        if self.page_bytes(page_number)? == other.page_bytes(page_number)? {
+            return Ok(None);
+        }
+
+        // overflow, freelist and damaged pages have no page type
+        let before = self.read_page(page_number).ok();
+        let after = other.read_page(page_number).ok();
+        let cells = match (&before, &after) {
+            (Some(before), Some(after)) if before.header.page_type == after.header.page_type =>
+                Some(diff_cells(&self.keyed_cells(before), &other.keyed_cells(after))),
+            _ => None,
+        };
+        Ok(Some(PageDiff {
+            page_number,
+            change: PageChange::Modified,
+            page_type_before: before.map(|page| page.header.page_type),
+            page_type_after: after.map(|page| page.header.page_type),
+            cells,
+        }))
+    }
+
+    /// Compare this database with `other` page by page, `other` being the newer one.
+    pub fn diff(&self, other: &Database) -> Result<DatabaseDiff, MyError> {
+        let mut pages = Vec::new();
+        for page_number in 1..=self.page_count().max(other.page_count()) {
+            pages.extend(self.diff_page(other, page_number)?);
+        }
+        Ok(DatabaseDiff {
+            page_count_before: self.page_count(),
+            page_count_after: other.page_count(),
+            header: diff_headers(self.header(), other.header()),
+            pages,
+        })
+    }
+}
+
+impl DatabaseDiff {
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty() && self.pages.is_empty()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for header_change in self.header.iter() {
+            text.push_str(&format!("header {}: {} -> {}\n", header_change.field, header_change.before, header_change.after));
+        }
+        for page_diff in self.pages.iter() {
+            let page_type = page_diff.page_type_after.or(page_diff.page_type_before);
+            match page_type {
+                Some(page_type) => text.push_str(&format!("page {} {:?} {:?}\n", page_diff.page_number, page_diff.change, page_type)),
+                None => text.push_str(&format!("page {} {:?}\n", page_diff.page_number, page_diff.change)),
+            }
+            if let (Some(before), Some(after)) = (page_diff.page_type_before, page_diff.page_type_after) {
+                if before != after {
+                    text.push_str(&format!("  page type {:?} -> {:?}\n", before, after));
+                }
+            }
+
+            let cell_diff = match &page_diff.cells {
+                Some(cell_diff) => cell_diff,
+                None => continue,
+            };
+            for key in cell_diff.inserted.iter() {
+                text.push_str(&format!("  + {}\n", key_to_text(key)));
+            }
+            for key in cell_diff.deleted.iter() {
+                text.push_str(&format!("  - {}\n", key_to_text(key)));
+            }
+            for cell_change in cell_diff.changed.iter() {
+                text.push_str(&format!("  ~ {}\n", key_to_text(&cell_change.key)));
+                if cell_change.left_child_before != cell_change.left_child_after {
+                    text.push_str(&format!("      left child: {:?} -> {:?}\n", cell_change.left_child_before, cell_change.left_child_after));
+                }
+                for column_change in cell_change.columns.iter() {
+                    text.push_str(&format!("      column {}: {} -> {}\n", column_change.column,
+                        column_to_text(&column_change.before), column_to_text(&column_change.after)));
+                }
+            }
+        }
+        if text.is_empty() {
+            text.push_str("identical\n");
+        }
+        text
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::Write;
+
+use serde_json::{Number, Value};
+
+use crate::components::database::Database;
+use crate::components::record::Column;
+use crate::utils::error::MyError;
+use crate::utils::json::integer_to_json;
+use crate::utils::string::{encode_base64, encode_hex};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlobEncoding {
+    Hex,
+    Base64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quoting {
+    // only fields holding the delimiter, a quote or a line break
+    Minimal,
+    All,
+    NonNumeric,
+    Never,
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub quoting: Quoting,
+    pub blob_encoding: BlobEncoding,
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            quoting: Quoting::Minimal,
+            blob_encoding: BlobEncoding::Hex,
+            header: true,
+        }
+    }
+}
+
+fn encode_blob(blob: &[u8], blob_encoding: BlobEncoding) -> String {
+    match blob_encoding {
+        BlobEncoding::Hex => encode_hex(blob),
+        BlobEncoding::Base64 => encode_base64(blob),
+    }
+}
+
+fn csv_field(text: &str, numeric: bool, options: &CsvOptions) -> String {
+    let quote = match options.quoting {
+        Quoting::All => true,
+        Quoting::NonNumeric => !numeric,
+        Quoting::Never => false,
+        Quoting::Minimal => text.contains([options.delimiter, '"', '\r', '\n']),
+    };
+    if quote {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// One csv field, NULL is an empty field, never quoted. Infinities are written as 9e999 and
+/// -9e999 like sqlite quotes them, they read back as infinities.
+pub fn column_to_csv(column: &Column, options: &CsvOptions) -> String {
+    match column {
+        Column::STRING(s) => csv_field(s, false, options),
+        Column::BLOB(b) => csv_field(&encode_blob(b, options.blob_encoding), false, options),
+        Column::F64(v) if v.is_finite() => csv_field(&format!("{:?}", v), true, options),
+        Column::F64(v) if v.is_infinite() => csv_field(if *v > 0.0 { "9e999" } else { "-9e999" }, true, options),
+        column => match column.as_i64() {
+            Some(v) => csv_field(&v.to_string(), true, options),
+            None => String::new(),
+        },
+    }
+}
+
+pub fn column_to_json(column: &Column, blob_encoding: BlobEncoding) -> Value {
+    match column {
+        Column::STRING(s) => Value::String(s.clone()),
+        Column::BLOB(b) => Value::String(encode_blob(b, blob_encoding)),
+        Column::F64(v) => Number::from_f64(*v).map_or(Value::Null, Value::Number),
+        column => match column.as_i64() {
+            Some(v) => integer_to_json(v),
+            None => Value::Null,
+        },
+    }
+}
+
+impl Database {
+    /// Write the rows of `table` as CSV, the header line holds the column names of CREATE TABLE.
+    /// Rows are written while the btree is walked, returns the number of rows.
+    pub fn export_csv<W: Write>(&self, table: &str, options: &CsvOptions, out: &mut W) -> Result<usize, MyError> {
+        let table = self.table(table)?;
+        let delimiter = options.delimiter.to_string();
+        if options.header {
+            let names: Vec<String> = table.column_names().iter()
+                .map(|name| csv_field(name, false, options))
+                .collect();
+            write!(out, "{}\r\n", names.join(&delimiter))?;
+        }
+
+        self.for_each_table_row(table.entry.root_page, |row| {
+            let fields: Vec<String> = table.values(row).iter()
+                .map(|column| column_to_csv(column, options))
+                .collect();
+            write!(out, "{}\r\n", fields.join(&delimiter))?;
+            Ok(())
+        })
+    }
+
+    /// Write the rows of `table` as JSON Lines, one object keyed by column name per line.
+    pub fn export_json_lines<W: Write>(&self, table: &str, blob_encoding: BlobEncoding, out: &mut W) -> Result<usize, MyError> {
+        let table = self.table(table)?;
+        let names = table.column_names();
+
+        // keys in the column order rather than sorted as a json map would
+        let keys: Vec<String> = names.iter().map(|name| Value::String(name.clone()).to_string()).collect();
+        self.for_each_table_row(table.entry.root_page, |row| {
+            let members: Vec<String> = keys.iter()
+                .zip(table.values(row).iter())
+                .map(|(key, column)| format!("{}:{}", key, column_to_json(column, blob_encoding)))
+                .collect();
+            writeln!(out, "{{{}}}", members.join(","))?;
+            Ok(())
+        })
+    }
+}
@@ -1,49 +1,62 @@
 use std::fs::File;
-use std::sync::Arc;
-use std::sync::Mutex;
 use std::result::Result;
 
 use memmap::Mmap;
 use memmap::MmapOptions;
 
-use crate::components::database::Database;
-
 pub struct FileManager{
     f: File,
-    mmap: Arc<Mutex<Mmap>>,
-    db: Arc<Mutex<Database>>,
+    mmap: Mmap,
 }
 
 impl FileManager {
-    pub fn open(&mut self, file_name: String ) -> Result<Arc<Mutex<Database>>, std::io::Error> {
+    pub fn open(file_name: &str) -> Result<FileManager, std::io::Error> {
 
-        self.f = match File::open(file_name) {
+        let f = match File::open(file_name) {
             Ok(f) => f,
             Err(e) => return Err(e),
         };
 
-        let mmap = unsafe{ MmapOptions::new().map(&self.f)? };
-        self.mmap = Arc::new(Mutex::new(mmap));
+        let mmap = unsafe{ MmapOptions::new().map(&f)? };
+
+        Ok(FileManager {
+            f,
+            mmap,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.mmap.len()
+    }
 
-        let result = Arc::clone(&self.db);
-        Ok(result)
+    pub fn is_empty(&self) -> bool {
+        self.mmap.is_empty()
+    }
 
+    pub fn file(&self) -> &File {
+        &self.f
     }
 
-    pub fn read(&self, _offset: usize, length: usize) -> Vec<u8> {
+    /// Borrow `length` bytes starting at `offset`, clamped to the end of the file.
+    pub fn slice(&self, offset: usize, length: usize) -> &[u8] {
+        let start = offset.min(self.mmap.len());
+        let end = offset.saturating_add(length).min(self.mmap.len());
+        &self.mmap[start..end]
+    }
 
-        vec![]
+    pub fn read(&self, offset: usize, length: usize) -> Vec<u8> {
+        self.slice(offset, length).to_vec()
     }
 
-    pub fn write(&self, offset: usize, data: Vec<u8>) -> Result<(), String> {
+    pub fn write(&self, _offset: usize, _data: Vec<u8>) -> Result<(), String> {
 
         Ok(())
     }
 
-    pub fn append(&self, data: Vec<u8>) -> Result<(), String> {
+    pub fn append(&self, _data: Vec<u8>) -> Result<(), String> {
 
         Ok(())
 
     }
-    
+
 }
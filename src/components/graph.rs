@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use serde_derive::Serialize;
+
+use crate::components::btree::{BtreeTraversal, TraversalProblem};
+use crate::components::database::Database;
+use crate::components::database::DATABASE_HEADER_LENGTH;
+use crate::components::page_header::PageType;
+use crate::components::schema::SQLITE_SCHEMA_ROOT_PAGE;
+use crate::utils::convert::TryFromBytes;
+use crate::utils::error::MyError;
+
+// size and spacing of the svg boxes, in pixels
+const NODE_WIDTH: usize = 120;
+const NODE_HEIGHT: usize = 58;
+const NODE_GAP: usize = 16;
+const LEVEL_GAP: usize = 44;
+const TITLE_HEIGHT: usize = 28;
+const MARGIN: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum GraphNodeKind {
+    Btree(PageType),
+    Overflow,
+    // out of range or failing to parse
+    Unreadable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum GraphEdgeKind {
+    Child,
+    Overflow,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    pub page_number: u32,
+    pub btree: String,
+    pub kind: GraphNodeKind,
+    pub cell_number: u16,
+    // used bytes in percent of the usable size
+    pub fill: f64,
+    // the page it was first reached from, None for the btree roots
+    pub parent: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphEdge {
+    pub from: u32,
+    pub to: u32,
+    pub kind: GraphEdgeKind,
+}
+
+/// Every btree of the file with its child pointers and overflow chains, nodes are in depth first order.
+#[derive(Debug, Serialize)]
+pub struct BtreeGraph {
+    pub btrees: Vec<(String, u32)>,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    // cycles, bad pointers and unreadable pages found on the way
+    pub problems: Vec<TraversalProblem>,
+}
+
+impl Database {
+    fn unreadable_node(name: &str, page_number: u32, parent: Option<u32>) -> GraphNode {
+        GraphNode {
+            page_number,
+            btree: name.to_string(),
+            kind: GraphNodeKind::Unreadable,
+            cell_number: 0,
+            fill: 0.0,
+            parent,
+        }
+    }
+
+    // The overflow pages of a cell, `overflow_length` bytes of the payload are left for them.
+    fn graph_overflow_chain(&self, name: &str, parent: u32, first_page_number: u32, overflow_length: usize, drawn: &mut HashSet<u32>, graph: &mut BtreeGraph) {
+        let usable_size = self.usable_size();
+        let mut remaining = overflow_length;
+        let (mut parent, mut page_number) = (parent, first_page_number);
+        while remaining > 0 && page_number != 0 {
+            graph.edges.push(GraphEdge { from: parent, to: page_number, kind: GraphEdgeKind::Overflow });
+            // shared pages and cycles get the edge but are drawn once
+            if !drawn.insert(page_number) {
+                return;
+            }
+            let mut node = Database::unreadable_node(name, page_number, Some(parent));
+            let bytes = match self.page_bytes(page_number) {
+                Ok(bytes) => bytes,
+                Err(_) => {
+                    graph.nodes.push(node);
+                    return;
+                },
+            };
+            let content_length = remaining.min(usable_size - 4);
+            node.kind = GraphNodeKind::Overflow;
+            node.fill = 100.0 * (content_length + 4) as f64 / usable_size as f64;
+            graph.nodes.push(node);
+            remaining -= content_length;
+            parent = page_number;
+            page_number = u32::try_from_be_bytes(&bytes[0..4]).unwrap_or(0);
+        }
+    }
+
+    fn graph_of(&self, name: &str, root_page: u32, traversal: &mut BtreeTraversal, drawn: &mut HashSet<u32>, graph: &mut BtreeGraph) -> Result<(), MyError> {
+        let usable_size = self.usable_size();
+        let problem_count = traversal.problems.len();
+        self.traverse_btree(root_page, traversal, |page, page_number, parent, _| {
+            let parent = if parent == 0 { None } else { Some(parent) };
+            if drawn.insert(page_number) {
+                let bytes = self.page_bytes(page_number)?;
+                let header_start = if page_number == 1 { DATABASE_HEADER_LENGTH } else { 0 };
+                let unused = self.btree_page_unused_bytes(bytes, header_start, &page.header).min(usable_size);
+                graph.nodes.push(GraphNode {
+                    page_number,
+                    btree: name.to_string(),
+                    kind: GraphNodeKind::Btree(page.header.page_type),
+                    cell_number: page.header.cell_number,
+                    fill: 100.0 * (usable_size - unused) as f64 / usable_size as f64,
+                    parent,
+                });
+            }
+
+            // every child pointer gets its edge, the traversal only visits the pages they lead to once
+            for cell in page.cells.iter() {
+                if let Some(left_child_page_number) = cell.left_child_page_number {
+                    graph.edges.push(GraphEdge { from: page_number, to: left_child_page_number, kind: GraphEdgeKind::Child });
+                }
+                if let (Some(overflow_page_number), Some(overflow_length)) = (cell.overflow_page_number, cell.overflow_length) {
+                    self.graph_overflow_chain(name, page_number, overflow_page_number, overflow_length, drawn, graph);
+                }
+            }
+            if let Some(right_most_pointer) = page.header.right_most_pointer {
+                graph.edges.push(GraphEdge { from: page_number, to: right_most_pointer, kind: GraphEdgeKind::Child });
+            }
+            Ok(())
+        })?;
+
+        // pages which can't be parsed or don't exist are drawn where their parent points to them
+        for problem in traversal.problems[problem_count..].iter() {
+            let (page_number, parent) = match problem {
+                TraversalProblem::Unreadable { page_number, .. } => (*page_number, traversal.parent(*page_number).filter(|parent| *parent != 0)),
+                TraversalProblem::DanglingPointer { page_number, parent } => (*page_number, Some(*parent)),
+                _ => continue,
+            };
+            if drawn.insert(page_number) {
+                graph.nodes.push(Database::unreadable_node(name, page_number, parent));
+            }
+        }
+        Ok(())
+    }
+
+    /// The pages of sqlite_schema and of every table and index, damaged pages end up as unreadable nodes.
+    pub fn btree_graph(&self) -> Result<BtreeGraph, MyError> {
+        let mut btrees = vec![("sqlite_schema".to_string(), SQLITE_SCHEMA_ROOT_PAGE)];
+        btrees.extend(self.schema()?.into_iter()
+            .filter(|entry| entry.root_page > 0)
+            .map(|entry| (entry.name, entry.root_page)));
+
+        let mut graph = BtreeGraph {
+            btrees: Vec::new(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            problems: Vec::new(),
+        };
+        let mut traversal = BtreeTraversal::new(self.page_count());
+        let mut drawn = HashSet::new();
+        for (name, root_page) in btrees.iter() {
+            self.graph_of(name, *root_page, &mut traversal, &mut drawn, &mut graph)?;
+        }
+        graph.btrees = btrees;
+        graph.problems = traversal.problems;
+        Ok(graph)
+    }
+}
+
+fn kind_label(kind: GraphNodeKind) -> &'static str {
+    match kind {
+        GraphNodeKind::Btree(PageType::TableInteriorBtreePage) => "table interior",
+        GraphNodeKind::Btree(PageType::TableLeafBtreePage) => "table leaf",
+        GraphNodeKind::Btree(PageType::IndexInteriorBtreePage) => "index interior",
+        GraphNodeKind::Btree(PageType::IndexLeafBtreePage) => "index leaf",
+        GraphNodeKind::Btree(PageType::UnknowType) => "unknown",
+        GraphNodeKind::Overflow => "overflow",
+        GraphNodeKind::Unreadable => "unreadable",
+    }
+}
+
+fn kind_color(kind: GraphNodeKind) -> &'static str {
+    match kind {
+        GraphNodeKind::Btree(PageType::TableInteriorBtreePage) => "#9ecae1",
+        GraphNodeKind::Btree(PageType::TableLeafBtreePage) => "#deebf7",
+        GraphNodeKind::Btree(PageType::IndexInteriorBtreePage) => "#a1d99b",
+        GraphNodeKind::Btree(PageType::IndexLeafBtreePage) => "#e5f5e0",
+        GraphNodeKind::Overflow => "#fdd0a2",
+        _ => "#fc9272",
+    }
+}
+
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+impl GraphNode {
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("page {}", self.page_number), kind_label(self.kind).to_string()];
+        match self.kind {
+            GraphNodeKind::Btree(_) => lines.push(format!("{} cells, {:.0}%", self.cell_number, self.fill)),
+            GraphNodeKind::Overflow => lines.push(format!("{:.0}%", self.fill)),
+            GraphNodeKind::Unreadable => {},
+        }
+        lines
+    }
+}
+
+impl BtreeGraph {
+    /// Graphviz source, one cluster per btree, overflow chains are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph btrees {\n");
+        dot.push_str("  node [shape=box, style=filled, fontname=\"monospace\", fontsize=10];\n");
+        for (idx, (name, root_page)) in self.btrees.iter().enumerate() {
+            writeln!(dot, "  subgraph cluster_{} {{", idx).unwrap();
+            writeln!(dot, "    label={};", dot_string(&format!("{} (root page {})", name, root_page))).unwrap();
+            for node in self.nodes.iter().filter(|node| &node.btree == name) {
+                writeln!(dot, "    p{} [label={}, fillcolor=\"{}\"];",
+                    node.page_number, dot_string(&node.lines().join("\n")), kind_color(node.kind)).unwrap();
+            }
+            dot.push_str("  }\n");
+        }
+        for edge in self.edges.iter() {
+            match edge.kind {
+                GraphEdgeKind::Child => writeln!(dot, "  p{} -> p{};", edge.from, edge.to).unwrap(),
+                GraphEdgeKind::Overflow => writeln!(dot, "  p{} -> p{} [style=dashed];", edge.from, edge.to).unwrap(),
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Position of the top left corner of every node. Each btree is a tidy tree: the leaves are
+    /// laid out left to right in key order and a parent is centered above its children.
+    fn layout(&self) -> (HashMap<u32, (usize, usize)>, usize, usize) {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for node in self.nodes.iter() {
+            if let Some(parent) = node.parent {
+                children.entry(parent).or_default().push(node.page_number);
+            }
+        }
+
+        let mut positions: HashMap<u32, (usize, usize)> = HashMap::new();
+        let mut slot = 0;
+        let (mut width, mut height) = (0, 0);
+        for (name, _) in self.btrees.iter() {
+            // the nodes are in depth first order, parents come before their children
+            let nodes: Vec<&GraphNode> = self.nodes.iter().filter(|node| &node.btree == name).collect();
+            let mut depths: HashMap<u32, usize> = HashMap::new();
+            let mut centers: HashMap<u32, f64> = HashMap::new();
+            for node in nodes.iter() {
+                let depth = node.parent.and_then(|parent| depths.get(&parent)).map_or(0, |depth| depth + 1);
+                depths.insert(node.page_number, depth);
+                if !children.contains_key(&node.page_number) {
+                    centers.insert(node.page_number, slot as f64);
+                    slot += 1;
+                }
+            }
+            for node in nodes.iter().rev() {
+                if let Some(node_children) = children.get(&node.page_number) {
+                    let first = centers[&node_children[0]];
+                    let last = centers[&node_children[node_children.len() - 1]];
+                    centers.insert(node.page_number, (first + last) / 2.0);
+                }
+            }
+            for node in nodes.iter() {
+                let x = MARGIN + (centers[&node.page_number] * (NODE_WIDTH + NODE_GAP) as f64) as usize;
+                let y = MARGIN + TITLE_HEIGHT + depths[&node.page_number] * (NODE_HEIGHT + LEVEL_GAP);
+                width = width.max(x + NODE_WIDTH + MARGIN);
+                height = height.max(y + NODE_HEIGHT + MARGIN);
+                positions.insert(node.page_number, (x, y));
+            }
+            // an empty column between two btrees
+            slot += 1;
+        }
+        (positions, width, height)
+    }
+
+    /// A standalone svg drawing, laid out here so no graphviz install is needed.
+    pub fn to_svg(&self) -> String {
+        let (positions, width, height) = self.layout();
+        let mut svg = String::new();
+        writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">", width, height, width, height).unwrap();
+        svg.push_str("<style>text { font-family: monospace; font-size: 11px; } .title { font-size: 13px; font-weight: bold; } \
+            .child { stroke: #555; } .overflow { stroke: #e6550d; stroke-dasharray: 4 3; } .shared { stroke: #de2d26; stroke-dasharray: 2 2; }</style>\n");
+        svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+        for (name, root_page) in self.btrees.iter() {
+            if let Some((x, _)) = positions.get(root_page) {
+                let left = self.nodes.iter()
+                    .filter(|node| &node.btree == name)
+                    .filter_map(|node| positions.get(&node.page_number))
+                    .map(|(x, _)| *x)
+                    .min()
+                    .unwrap_or(*x);
+                writeln!(svg, "<text class=\"title\" x=\"{}\" y=\"{}\">{}</text>", left, MARGIN + 14, xml_escape(name)).unwrap();
+            }
+        }
+
+        let parents: HashMap<u32, Option<u32>> = self.nodes.iter().map(|node| (node.page_number, node.parent)).collect();
+        for edge in self.edges.iter() {
+            let (from, to) = match (positions.get(&edge.from), positions.get(&edge.to)) {
+                (Some(from), Some(to)) => (from, to),
+                _ => continue,
+            };
+            // the edge a page was not laid out by points at a shared page or closes a cycle
+            let is_tree_edge = parents.get(&edge.to) == Some(&Some(edge.from));
+            let class = match edge.kind {
+                _ if !is_tree_edge => "shared",
+                GraphEdgeKind::Child => "child",
+                GraphEdgeKind::Overflow => "overflow",
+            };
+            writeln!(svg, "<line class=\"{}\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>", class,
+                from.0 + NODE_WIDTH / 2, from.1 + NODE_HEIGHT, to.0 + NODE_WIDTH / 2, to.1).unwrap();
+        }
+
+        for node in self.nodes.iter() {
+            let (x, y) = positions[&node.page_number];
+            writeln!(svg, "<g><title>{} page {}</title>", xml_escape(&node.btree), node.page_number).unwrap();
+            writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"#333\"/>",
+                x, y, NODE_WIDTH, NODE_HEIGHT, kind_color(node.kind)).unwrap();
+            // the fill level as a bar along the bottom
+            let bar = (node.fill.clamp(0.0, 100.0) / 100.0 * (NODE_WIDTH - 2) as f64) as usize;
+            writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"4\" fill=\"#3182bd\"/>", x + 1, y + NODE_HEIGHT - 5, bar).unwrap();
+            for (idx, line) in node.lines().iter().enumerate() {
+                writeln!(svg, "<text x=\"{}\" y=\"{}\">{}</text>", x + 6, y + 15 + idx * 14, xml_escape(line)).unwrap();
+            }
+            svg.push_str("</g>\n");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
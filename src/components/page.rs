@@ -1,10 +1,11 @@
 use serde_derive::Serialize;
 
-use crate::components::cell::Cell;
+use crate::components::cell::{Cell, CellRef};
 use crate::components::cell_pointer::CellPointer;
-use crate::components::page_header::PageHeader;
-use crate::utils::convert::TryFromBytes;
-use crate::utils::error::MyError;
+use crate::components::database_header::TextEncoding;
+use crate::components::page_header::{PageHeader, PageType};
+use crate::utils::convert::{ToBytes, TryFromBytes};
+use crate::utils::error::{ErrorKind, MyError};
 
 #[derive(Debug, Serialize)]
 pub struct Page {
@@ -18,38 +19,251 @@ impl Page {
         bytes: &[u8],
         header_start_index: Option<usize>,
     ) -> Result<Self, MyError> {
+        PageRef::try_from_be_bytes(bytes, header_start_index)?.to_page()
+    }
+
+    /// The usable area of the page with the header, the cell pointer array and every cell
+    /// written where they were read from, over the bytes of the free space of `source`, the usable
+    /// area the page was read from: an unchanged page comes out as it was read. The first
+    /// `header_start_index` bytes of page 1 are left zeroed.
+    pub fn to_be_bytes(&self, source: &[u8], header_start_index: usize) -> Result<Vec<u8>, MyError> {
+        let mut bytes = vec![0; source.len()];
+        // the free space is only worked out when a page is written, not each time one is read
+        for (offset, free_bytes) in PageRef::try_from_be_bytes(source, Some(header_start_index))?.free_space_bytes()? {
+            write_at(&mut bytes, offset, &free_bytes)?;
+        }
+        let header = self.header.to_be_bytes();
+        write_at(&mut bytes, header_start_index, &header)?;
+        let mut idx = header_start_index + header.len();
+        for (cell_pointer, cell) in self.cell_pointers.iter().zip(self.cells.iter()) {
+            write_at(&mut bytes, idx, &cell_pointer.to_be_bytes())?;
+            idx += 2;
+            write_at(&mut bytes, cell_pointer.offset as usize, &cell.to_be_bytes())?;
+        }
+        Ok(bytes)
+    }
+}
+
+fn write_at(bytes: &mut [u8], offset: usize, content: &[u8]) -> Result<(), MyError> {
+    let end = offset + content.len();
+    if end > bytes.len() {
+        return Err(MyError::new(ErrorKind::SliceLengthError(end, bytes.len())));
+    }
+    bytes[offset..end].copy_from_slice(content);
+    Ok(())
+}
+
+/// Lays out a btree page the way sqlite rebuilds one: the cells packed against the end of the
+/// usable area, the first cell last, behind a header and cell pointer array without freeblocks.
+pub struct PageBuilder {
+    page_type: PageType,
+    usable_size: usize,
+    header_start_index: usize,
+    right_most_pointer: Option<u32>,
+    cells: Vec<Vec<u8>>,
+    cells_length: usize,
+}
+
+impl PageBuilder {
+    /// `header_start_index` is 100 for page 1, which starts with the database header.
+    pub fn new(page_type: PageType, usable_size: usize, header_start_index: usize) -> PageBuilder {
+        PageBuilder {
+            page_type,
+            usable_size,
+            header_start_index,
+            right_most_pointer: None,
+            cells: Vec::new(),
+            cells_length: 0,
+        }
+    }
+
+    /// The page holding the cells of `page`, in the same order.
+    pub fn from_page(page: &Page, usable_size: usize, header_start_index: usize) -> Result<PageBuilder, MyError> {
+        let mut builder = PageBuilder::new(page.header.page_type, usable_size, header_start_index);
+        builder.set_right_most_pointer(page.header.right_most_pointer);
+        for cell in page.cells.iter() {
+            builder.push_cell(cell)?;
+        }
+        Ok(builder)
+    }
+
+    fn is_interior(&self) -> bool {
+        matches!(self.page_type, PageType::IndexInteriorBtreePage | PageType::TableInteriorBtreePage)
+    }
+
+    pub fn set_right_most_pointer(&mut self, right_most_pointer: Option<u32>) {
+        self.right_most_pointer = right_most_pointer;
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Bytes left for more cells and their pointers.
+    pub fn free_space(&self) -> usize {
+        let header_length = if self.is_interior() { 12 } else { 8 };
+        let used = self.header_start_index + header_length + 2 * self.cells.len() + self.cells_length;
+        self.usable_size.saturating_sub(used)
+    }
+
+    /// Append a cell after the ones already pushed, a cell that doesn't fit is refused.
+    pub fn push_cell(&mut self, cell: &Cell) -> Result<(), MyError> {
+        let bytes = cell.to_be_bytes();
+        let needed = bytes.len() + 2;
+        if needed > self.free_space() {
+            return Err(MyError::new(ErrorKind::PageFull(needed, self.free_space())));
+        }
+        self.cells_length += bytes.len();
+        self.cells.push(bytes);
+        Ok(())
+    }
+
+    /// The usable area of the page, the first `header_start_index` bytes are left zeroed.
+    pub fn build(&self) -> Vec<u8> {
+        let mut cell_pointers = Vec::with_capacity(self.cells.len());
+        let mut offset = self.usable_size;
+        for cell in self.cells.iter() {
+            offset -= cell.len();
+            cell_pointers.push(CellPointer { offset: offset as u16 });
+        }
+        // a content area starting at 65536 is stored as 0
+        let cell_content_area_offset = if offset == 65536 { 0 } else { offset as u16 };
+        let header = PageHeader {
+            page_type: self.page_type,
+            first_free_block_offset: 0,
+            cell_number: self.cells.len() as u16,
+            cell_content_area_offset,
+            fragmented_free_bytes: 0,
+            right_most_pointer: if self.is_interior() { Some(self.right_most_pointer.unwrap_or(0)) } else { None },
+            length: if self.is_interior() { 12 } else { 8 },
+        };
+
+        let mut bytes = vec![0; self.usable_size];
+        let mut idx = self.header_start_index;
+        let header = header.to_be_bytes();
+        bytes[idx..idx + header.len()].copy_from_slice(&header);
+        idx += header.len();
+        for (cell_pointer, cell) in cell_pointers.iter().zip(self.cells.iter()) {
+            bytes[idx..idx + 2].copy_from_slice(&cell_pointer.to_be_bytes());
+            idx += 2;
+            let offset = cell_pointer.offset as usize;
+            bytes[offset..offset + cell.len()].copy_from_slice(cell);
+        }
+        bytes
+    }
+}
+
+/// A page read in place, only its header is decoded up front. The cells are
+/// borrowed from the page bytes as they are asked for.
+#[derive(Debug)]
+pub struct PageRef<'a> {
+    pub header: PageHeader,
+    bytes: &'a [u8],
+    cell_pointers_start_index: usize,
+    text_encoding: TextEncoding,
+}
+
+impl<'a> PageRef<'a> {
+    /// `bytes` is the usable area of the whole page, the header of page 1 starts after the database header.
+    pub fn try_from_be_bytes(
+        bytes: &'a [u8],
+        header_start_index: Option<usize>,
+    ) -> Result<PageRef<'a>, MyError> {
         //page header
         let (header, header_start_idx) = match header_start_index {
             Some(start_idx) => (
-                PageHeader::try_from_be_bytes(&bytes[start_idx..]).unwrap(),
+                PageHeader::try_from_be_bytes(&bytes[start_idx..])?,
                 start_idx,
             ),
-            None => (PageHeader::try_from_be_bytes(bytes).unwrap(), 0),
+            None => (PageHeader::try_from_be_bytes(bytes)?, 0),
         };
-        //cell number
-        let cell_number: usize = header.cell_number.into();
         //cell cell_pointers
-        let cell_pointers_start_index: usize = (header_start_idx + header.length).into();
-        let cell_pointers_length: usize =
-            (std::mem::size_of::<u16>() as usize * cell_number).into();
-        let cell_pointers_end_index = cell_pointers_start_index + cell_pointers_length - 1;
-        let cell_pointers: Vec<CellPointer> = <Vec<CellPointer>>::try_from_be_bytes(
-            &bytes[cell_pointers_start_index..=cell_pointers_end_index],
-        )
-        .unwrap();
-
-        //cells
-        let cells: Vec<Cell> = cell_pointers
-            .iter()
-            .map(|cell_pointer| {
-                let offset: usize = cell_pointer.offset.into();
-                let cell = Cell::try_from_bytes(&bytes[offset..], header.page_type, 4096).unwrap();
-                return cell;
-            })
-            .collect();
+        let cell_pointers_start_index = header_start_idx + header.length;
+        let cell_pointers_end_index = cell_pointers_start_index + std::mem::size_of::<u16>() * header.cell_number as usize;
+        if cell_pointers_end_index > bytes.len() {
+            return Err(MyError::new(ErrorKind::SliceLengthError(cell_pointers_end_index, bytes.len())));
+        }
 
-        Ok(Page {
+        Ok(PageRef {
             header,
+            bytes,
+            cell_pointers_start_index,
+            text_encoding: TextEncoding::UTF8,
+        })
+    }
+
+    /// The page of a database with `text_encoding`, the TEXT of its cells is decoded with it.
+    pub fn with_text_encoding(self, text_encoding: TextEncoding) -> PageRef<'a> {
+        PageRef { text_encoding, ..self }
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.header.cell_number as usize
+    }
+
+    pub fn cell_pointers(&self) -> Result<Vec<CellPointer>, MyError> {
+        let cell_pointers_end_index = self.cell_pointers_start_index + std::mem::size_of::<u16>() * self.cell_count();
+        <Vec<CellPointer>>::try_from_be_bytes(&self.bytes[self.cell_pointers_start_index..cell_pointers_end_index])
+    }
+
+    pub fn cell(&self, index: usize) -> Result<CellRef<'a>, MyError> {
+        let cell_count = self.cell_count();
+        if index >= cell_count {
+            return Err(MyError::new(ErrorKind::CellIndexOutOfRange(index, cell_count)));
+        }
+        let pointer_index = self.cell_pointers_start_index + index * std::mem::size_of::<u16>();
+        let offset = u16::try_from_be_bytes(&self.bytes[pointer_index..pointer_index + 2])? as usize;
+        let page_size = self.bytes.len();
+        if offset >= page_size {
+            return Err(MyError::new(ErrorKind::SliceLengthError(offset, page_size)));
+        }
+        Ok(CellRef::try_from_bytes(&self.bytes[offset..], self.header.page_type, page_size)?.with_text_encoding(self.text_encoding))
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = Result<CellRef<'a>, MyError>> + '_ {
+        (0..self.cell_count()).map(move |index| self.cell(index))
+    }
+
+    /// The runs of bytes after the header that neither the cell pointer array nor a cell covers
+    /// with their offset: freeblocks, fragments and the unallocated space, runs of zeros are left out.
+    pub fn free_space_bytes(&self) -> Result<Vec<(usize, Vec<u8>)>, MyError> {
+        let header_start_index = self.cell_pointers_start_index - self.header.length;
+        let cell_pointers_end_index = self.cell_pointers_start_index + std::mem::size_of::<u16>() * self.cell_count();
+        let mut covered = vec![false; self.bytes.len()];
+        covered[..cell_pointers_end_index].iter_mut().for_each(|covered| *covered = true);
+        for (cell_pointer, cell) in self.cell_pointers()?.iter().zip(self.cells()) {
+            let offset = cell_pointer.offset as usize;
+            let end = (offset + cell?.size()?).min(self.bytes.len());
+            covered[offset..end].iter_mut().for_each(|covered| *covered = true);
+        }
+
+        let mut runs = Vec::new();
+        let mut idx = header_start_index;
+        while idx < self.bytes.len() {
+            if covered[idx] {
+                idx += 1;
+                continue;
+            }
+            let start = idx;
+            while idx < self.bytes.len() && !covered[idx] {
+                idx += 1;
+            }
+            let run = &self.bytes[start..idx];
+            if run.iter().any(|byte| *byte != 0) {
+                runs.push((start, run.to_vec()));
+            }
+        }
+        Ok(runs)
+    }
+
+    pub fn to_page(self) -> Result<Page, MyError> {
+        let cell_pointers = self.cell_pointers()?;
+        let cells: Vec<Cell> = self.cells()
+            .map(|cell| cell?.to_cell())
+            .collect::<Result<Vec<Cell>, MyError>>()?;
+
+        Ok(Page {
+            header: self.header,
             cell_pointers,
             cells,
         })
@@ -8,7 +8,7 @@ use serde_derive::Serialize;
 
 use crate::utils::error::MyError;
 use crate::utils::error::ErrorKind;
-use crate::utils::convert::TryFromBytes;
+use crate::utils::convert::{ToBytes, TryFromBytes};
 
 #[derive(Debug, PartialEq, FromPrimitive, ToPrimitive, Clone, Copy, Serialize)]
 pub enum PageType {
@@ -114,7 +114,17 @@ impl PageHeader {
     }
 }
 
-
-
-
-
+impl ToBytes for PageHeader {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12);
+        bytes.push(num::ToPrimitive::to_u8(&self.page_type).unwrap());
+        bytes.extend_from_slice(&self.first_free_block_offset.to_be_bytes());
+        bytes.extend_from_slice(&self.cell_number.to_be_bytes());
+        bytes.extend_from_slice(&self.cell_content_area_offset.to_be_bytes());
+        bytes.push(self.fragmented_free_bytes);
+        if let Some(right_most_pointer) = self.right_most_pointer {
+            bytes.extend_from_slice(&right_most_pointer.to_be_bytes());
+        }
+        bytes
+    }
+}
@@ -0,0 +1,64 @@
+use rayon::prelude::*;
+
+use crate::components::database::Database;
+use crate::components::page::Page;
+use crate::utils::error::MyError;
+
+// pages parsed in parallel before they are handed over in order, bounds the memory in use
+pub const PARALLEL_BATCH_PAGES: u32 = 4096;
+
+/// Parses the pages of the memory map one at a time in page order, only the current page is held.
+pub struct Pages<'a> {
+    db: &'a Database,
+    next_page_number: u32,
+    page_count: u32,
+}
+
+impl<'a> Iterator for Pages<'a> {
+    type Item = (u32, Result<Page, MyError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_page_number > self.page_count {
+            return None;
+        }
+        let page_number = self.next_page_number;
+        self.next_page_number += 1;
+        Some((page_number, self.db.read_page(page_number)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.page_count + 1 - self.next_page_number) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl Database {
+    /// Every page of the file, overflow and freelist pages come out as errors since they have no page header.
+    pub fn pages(&self) -> Pages<'_> {
+        Pages {
+            db: self,
+            next_page_number: 1,
+            page_count: self.page_count(),
+        }
+    }
+
+    /// Parse the pages on the rayon thread pool, batch by batch, and hand them to `visit` in page order.
+    /// Pages don't depend on each other, only the order of the results needs keeping.
+    pub fn for_each_page_parallel<F>(&self, mut visit: F) -> Result<(), MyError>
+    where F: FnMut(u32, Result<Page, MyError>) -> Result<(), MyError> {
+        let page_count = self.page_count();
+        let mut first_page_number = 1;
+        while first_page_number <= page_count {
+            let last_page_number = page_count.min(first_page_number + PARALLEL_BATCH_PAGES - 1);
+            let pages: Vec<Result<Page, MyError>> = (first_page_number..=last_page_number)
+                .into_par_iter()
+                .map(|page_number| self.read_page(page_number))
+                .collect();
+            for (page_number, page) in (first_page_number..).zip(pages) {
+                visit(page_number, page)?;
+            }
+            first_page_number = last_page_number + 1;
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,16 @@
-use std::convert::TryInto;
+use std::borrow::Cow;
 
 use serde_derive::Serialize;
 
-use crate::utils::convert::TryFromBytes;
+use crate::components::database_header::TextEncoding;
+use crate::utils::convert::{ToBytes, TryFromBytes};
 use crate::utils::error::MyError;
 use crate::utils::error::ErrorKind;
-use crate::utils::varint::decode_varint_to_usize;
+use crate::utils::json::serialize_i64;
+use crate::utils::string::encode_hex;
+use crate::utils::varint::{decode_varint_to_usize, encode_varint, varint_length};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum SerialType {
     UNKNOWN,
     NULL,
@@ -26,7 +29,62 @@ pub enum SerialType {
     STRING(usize /*string length*/),
 }
 
-#[derive(Debug, Serialize)]
+impl SerialType {
+    pub fn from_value(serial_type_val: usize) -> SerialType {
+        match serial_type_val {
+            0 => SerialType::NULL,
+            1 => SerialType::I8,
+            2 => SerialType::I16,
+            3 => SerialType::I24,
+            4 => SerialType::I32,
+            5 => SerialType::I48,
+            6 => SerialType::I64,
+            7 => SerialType::F64,
+            8 => SerialType::I0,
+            9 => SerialType::I1,
+            10 => SerialType::RESERVED10,
+            11 => SerialType::RESERVED11,
+            n if (n%2 == 0 && n >=12) => SerialType::BLOB((n-12)/2),
+            n if (n%2 == 1 && n >=13) => SerialType::STRING((n-13)/2),
+            _ => SerialType::UNKNOWN,
+        }
+    }
+
+    /// The varint stored in the record header, UNKNOWN is never decoded since every value has a type.
+    pub fn value(&self) -> usize {
+        match self {
+            SerialType::UNKNOWN | SerialType::NULL => 0,
+            SerialType::I8 => 1,
+            SerialType::I16 => 2,
+            SerialType::I24 => 3,
+            SerialType::I32 => 4,
+            SerialType::I48 => 5,
+            SerialType::I64 => 6,
+            SerialType::F64 => 7,
+            SerialType::I0 => 8,
+            SerialType::I1 => 9,
+            SerialType::RESERVED10 => 10,
+            SerialType::RESERVED11 => 11,
+            SerialType::BLOB(len) => 12 + len * 2,
+            SerialType::STRING(len) => 13 + len * 2,
+        }
+    }
+
+    pub fn content_size(&self) -> usize {
+        match self {
+            SerialType::I8 => 1,
+            SerialType::I16 => 2,
+            SerialType::I24 => 3,
+            SerialType::I32 => 4,
+            SerialType::I48 => 6,
+            SerialType::I64 | SerialType::F64 => 8,
+            SerialType::BLOB(len) | SerialType::STRING(len) => *len,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum Column {
     UNKNOWN,
     NULL,
@@ -35,7 +93,7 @@ pub enum Column {
     I24(i32),
     I32(i32),
     I48(i64),
-    I64(i64),
+    I64(#[serde(serialize_with = "serialize_i64")] i64),
     F64(f64),
     I0,
     I1,
@@ -45,132 +103,436 @@ pub enum Column {
     STRING(String),
 }
 
-#[derive(Debug, Serialize)]
+impl Column {
+    /// The integer in the smallest serial type holding it.
+    pub fn integer(value: i64) -> Column {
+        match value {
+            0 => Column::I0,
+            1 => Column::I1,
+            v if v >= i8::MIN as i64 && v <= i8::MAX as i64 => Column::I8(v as i8),
+            v if v >= i16::MIN as i64 && v <= i16::MAX as i64 => Column::I16(v as i16),
+            v if (-(1 << 23)..(1 << 23)).contains(&v) => Column::I24(v as i32),
+            v if v >= i32::MIN as i64 && v <= i32::MAX as i64 => Column::I32(v as i32),
+            v if (-(1 << 47)..(1 << 47)).contains(&v) => Column::I48(v),
+            v => Column::I64(v),
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Column::I8(v) => Some(*v as i64),
+            Column::I16(v) => Some(*v as i64),
+            Column::I24(v) | Column::I32(v) => Some(*v as i64),
+            Column::I48(v) | Column::I64(v) => Some(*v),
+            Column::I0 => Some(0),
+            Column::I1 => Some(1),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Column::STRING(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_blob(&self) -> Option<&[u8]> {
+        match self {
+            Column::BLOB(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Column::NULL)
+    }
+
+    pub fn serial_type(&self) -> SerialType {
+        match self {
+            Column::UNKNOWN => SerialType::UNKNOWN,
+            Column::NULL => SerialType::NULL,
+            Column::I8(_) => SerialType::I8,
+            Column::I16(_) => SerialType::I16,
+            Column::I24(_) => SerialType::I24,
+            Column::I32(_) => SerialType::I32,
+            Column::I48(_) => SerialType::I48,
+            Column::I64(_) => SerialType::I64,
+            Column::F64(_) => SerialType::F64,
+            Column::I0 => SerialType::I0,
+            Column::I1 => SerialType::I1,
+            Column::RESERVED10 => SerialType::RESERVED10,
+            Column::RESERVED11 => SerialType::RESERVED11,
+            Column::BLOB(b) => SerialType::BLOB(b.len()),
+            Column::STRING(s) => SerialType::STRING(s.len()),
+        }
+    }
+
+    /// The content of the column in the record body, I24 and I48 keep their low bytes.
+    pub fn content_bytes(&self) -> Vec<u8> {
+        match self {
+            Column::I8(v) => v.to_be_bytes().to_vec(),
+            Column::I16(v) => v.to_be_bytes().to_vec(),
+            Column::I24(v) => v.to_be_bytes()[1..].to_vec(),
+            Column::I32(v) => v.to_be_bytes().to_vec(),
+            Column::I48(v) => v.to_be_bytes()[2..].to_vec(),
+            Column::I64(v) => v.to_be_bytes().to_vec(),
+            Column::F64(v) => v.to_be_bytes().to_vec(),
+            Column::BLOB(b) => b.to_vec(),
+            Column::STRING(s) => s.as_bytes().to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The column as a SQL literal, e.g. 'it''s', X'00ff' or NULL.
+    pub fn to_sql_literal(&self) -> String {
+        match self {
+            Column::STRING(s) => format!("'{}'", s.replace('\'', "''")),
+            Column::BLOB(b) => format!("X'{}'", encode_hex(b)),
+            Column::F64(v) if v.is_nan() => "NULL".to_string(),
+            Column::F64(v) if v.is_infinite() => if *v > 0.0 { "1e999".to_string() } else { "-1e999".to_string() },
+            Column::F64(v) => format!("{:?}", v),
+            column => match column.as_i64() {
+                Some(v) => v.to_string(),
+                None => "NULL".to_string(),
+            },
+        }
+    }
+}
+
+/// TEXT as stored in a database with `text_encoding`.
+pub fn encode_text(text: &str, text_encoding: &TextEncoding) -> Vec<u8> {
+    match text_encoding {
+        // the strings of a file whose encoding is unknown are read as UTF-8 too
+        TextEncoding::UTF8 | TextEncoding::Unknown(_) => text.as_bytes().to_vec(),
+        TextEncoding::UTF16le => text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect(),
+        TextEncoding::UTF16be => text.encode_utf16().flat_map(|u| u.to_be_bytes()).collect(),
+    }
+}
+
+/// TEXT read from a database with `text_encoding`, invalid sequences are replaced. Borrowed
+/// when the text is valid UTF-8.
+pub fn decode_text<'a>(bytes: &'a [u8], text_encoding: &TextEncoding) -> Cow<'a, str> {
+    let units = |to_u16: fn([u8; 2]) -> u16| -> Vec<u16> {
+        bytes.chunks_exact(2).map(|pair| to_u16([pair[0], pair[1]])).collect()
+    };
+    match text_encoding {
+        TextEncoding::UTF8 | TextEncoding::Unknown(_) => String::from_utf8_lossy(bytes),
+        TextEncoding::UTF16le => Cow::Owned(String::from_utf16_lossy(&units(u16::from_le_bytes))),
+        TextEncoding::UTF16be => Cow::Owned(String::from_utf16_lossy(&units(u16::from_be_bytes))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Record {
     pub header_length: usize,
     pub serial_types: Vec<SerialType>,
     pub columns: Vec<Column>,
 }
 
+impl Record {
+    /// A record holding `columns`, their serial types and the header length follow from the values.
+    pub fn new(columns: Vec<Column>) -> Record {
+        let serial_types: Vec<SerialType> = columns.iter().map(|column| column.serial_type()).collect();
+        Record {
+            header_length: Self::header_length_of(&serial_types),
+            serial_types,
+            columns,
+        }
+    }
+
+    // the header length counts its own varint
+    fn header_length_of(serial_types: &[SerialType]) -> usize {
+        let serial_types_length: usize = serial_types.iter()
+            .map(|serial_type| varint_length(serial_type.value() as u64))
+            .sum();
+        let mut header_length_var_length = 1;
+        while varint_length((serial_types_length + header_length_var_length) as u64) > header_length_var_length {
+            header_length_var_length += 1;
+        }
+        serial_types_length + header_length_var_length
+    }
+
+    /// The record as written to a database with `text_encoding`, the serial types follow the
+    /// columns since a TEXT column takes another length in UTF-16.
+    pub fn to_be_bytes_encoded(&self, text_encoding: &TextEncoding) -> Vec<u8> {
+        let contents: Vec<Vec<u8>> = self.columns.iter()
+            .map(|column| match column {
+                Column::STRING(s) => encode_text(s, text_encoding),
+                column => column.content_bytes(),
+            })
+            .collect();
+        let serial_types: Vec<SerialType> = self.columns.iter()
+            .zip(contents.iter())
+            .map(|(column, content)| match column {
+                Column::STRING(_) => SerialType::STRING(content.len()),
+                column => column.serial_type(),
+            })
+            .collect();
+        Self::write_record(&serial_types, &contents)
+    }
+
+    fn write_record(serial_types: &[SerialType], contents: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = encode_varint(Self::header_length_of(serial_types) as u64);
+        for serial_type in serial_types.iter() {
+            bytes.extend_from_slice(&encode_varint(serial_type.value() as u64));
+        }
+        for content in contents.iter() {
+            bytes.extend_from_slice(content);
+        }
+        bytes
+    }
+}
+
+impl ToBytes for Record {
+    fn to_be_bytes(&self) -> Vec<u8> {
+        let contents: Vec<Vec<u8>> = self.columns.iter().map(|column| column.content_bytes()).collect();
+        Self::write_record(&self.serial_types, &contents)
+    }
+}
+
 impl TryFromBytes for Record {
     fn try_from_le_bytes(_bytes: &[u8]) -> Result<Self, crate::utils::error::MyError> {
         Err(MyError::new(ErrorKind::NotImplemented))
     }
 
     fn try_from_be_bytes(bytes: &[u8]) -> Result<Self, crate::utils::error::MyError> {
-        let (header_length, header_length_var_length) = decode_varint_to_usize(&bytes).unwrap();
-
-        let header_in_bytes = &bytes[0..header_length.try_into().unwrap()];
-        let mut serial_type_start_idx = header_length_var_length;
-        let mut serial_types : Vec<SerialType> = Vec::new();
-
-        //fetch all serial types
-        while serial_type_start_idx < header_length {
-            let (serial_type_val, serial_type_var_length) = decode_varint_to_usize(&header_in_bytes[serial_type_start_idx..]).unwrap();
-            serial_type_start_idx += serial_type_var_length;
-            let serial_type = match serial_type_val {
-                0 => SerialType::NULL,
-                1 => SerialType::I8,
-                2 => SerialType::I16,
-                3 => SerialType::I24,
-                4 => SerialType::I32,
-                5 => SerialType::I48,
-                6 => SerialType::I64,
-                7 => SerialType::F64,
-                8 => SerialType::I0,
-                9 => SerialType::I1,
-                10 => SerialType::RESERVED10,
-                11 => SerialType::RESERVED11,
-                n if (n%2 == 0 && n >=12) => SerialType::BLOB((n-12)/12),
-                n if (n%2 == 1 && n >=13) => SerialType::STRING((n-13)/2),
-                _ => SerialType::UNKNOWN,
-            }; 
-            serial_types.push(serial_type);
-        }
-
-        //fetch columns according to serial types
-        let mut column_start_idx = header_length;
-        let mut columns : Vec<Column> = Vec::new();
-        for serial_type in serial_types.iter() {
-            let column = match serial_type {
-                SerialType::UNKNOWN => {
-                    Column::UNKNOWN
-                },
-                SerialType::NULL => {
-                    Column::NULL
-                },
-                SerialType::I8 => {
-                    let r = i8::try_from_be_bytes(&bytes[column_start_idx..=column_start_idx]).unwrap();
-                    column_start_idx += 1;
-                    Column::I8(r)
-                }, 
-                SerialType::I16 => {
-                    let r = i16::try_from_be_bytes(&bytes[column_start_idx..(column_start_idx + 2)]).unwrap();
-                    column_start_idx += 2;
-                    Column::I16(r)
-                },
-                SerialType::I24 => {
-                    let mut d: [u8; 4] = [0; 4];
-                    &d[1..].copy_from_slice(&bytes[column_start_idx..(column_start_idx + 3)]);
-                    let r = i32::try_from_be_bytes(&d).unwrap();
-                    column_start_idx += 3;
-                    Column::I24(r)
-                },
-                SerialType::I32 => {
-                    let r = i32::try_from_be_bytes(&bytes[column_start_idx..(column_start_idx + 4)]).unwrap();
-                    column_start_idx += 4;
-                    Column::I32(r)
-                },
-                SerialType::I48 => {
-                    let mut d: [u8; 8] = [0; 8];
-                    &d[2..].copy_from_slice(&bytes[column_start_idx..(column_start_idx + 6)]);
-                    let r = i64::try_from_be_bytes(&d).unwrap();
-                    column_start_idx += 6;
-                    Column::I48(r)
-                },
-                SerialType::I64 => {
-                    let r = i64::try_from_be_bytes(&bytes[column_start_idx..(column_start_idx + 8)]).unwrap();
-                    column_start_idx += 8;
-                    Column::I64(r)
-                },
-                SerialType::F64 => {
-                    let r = f64::try_from_be_bytes(&bytes[column_start_idx..(column_start_idx + 8)]).unwrap();
-                    column_start_idx += 8;
-                    Column::F64(r)
-                },
-                SerialType::I0 => {
-                    Column::I0
-                },
-                SerialType::I1 => {
-                    Column::I1
-                },
-                SerialType::RESERVED10 => {
-                    Column::RESERVED10
-                },
-                SerialType::RESERVED11 => {
-                    Column::RESERVED11
-                },
-                SerialType::BLOB(len) => {
-                    let mut blob = vec![0; *len].into_boxed_slice();
-                    blob.copy_from_slice(&bytes[column_start_idx..(column_start_idx + len)]);
-                    column_start_idx += len;
-                    Column::BLOB(blob)
-                },
-                SerialType::STRING(len) => {
-                    let s = String::from_utf8(bytes[column_start_idx..(column_start_idx + len)].to_vec()).unwrap();
-                    column_start_idx += len;
-                    Column::STRING(s)
-                },
-            };
-            
-            columns.push(column);
+        RecordRef::try_from_be_bytes(bytes)?.to_record()
+    }
+}
+
+impl Record {
+    /// The record as read from a database with `text_encoding`.
+    pub fn try_from_be_bytes_encoded(bytes: &[u8], text_encoding: &TextEncoding) -> Result<Record, MyError> {
+        RecordRef::try_from_be_bytes_encoded(bytes, text_encoding)?.to_record()
+    }
+}
+
+/// A column still pointing into the page, TEXT is kept as raw bytes in the encoding of its
+/// database until it is asked for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnRef<'a> {
+    UNKNOWN,
+    NULL,
+    I8(i8),
+    I16(i16),
+    I24(i32),
+    I32(i32),
+    I48(i64),
+    I64(i64),
+    F64(f64),
+    I0,
+    I1,
+    RESERVED10,
+    RESERVED11,
+    BLOB(&'a [u8]),
+    STRING(&'a [u8], TextEncoding),
+}
+
+impl<'a> ColumnRef<'a> {
+    fn sign_byte(bytes: &[u8]) -> u8 {
+        match bytes.first() {
+            Some(byte) if byte & 0x80 != 0 => 0xff,
+            _ => 0,
+        }
+    }
+
+    // `bytes` holds exactly the content of the column
+    fn decode(serial_type: SerialType, bytes: &'a [u8], text_encoding: TextEncoding) -> Result<ColumnRef<'a>, MyError> {
+        let column = match serial_type {
+            SerialType::UNKNOWN => ColumnRef::UNKNOWN,
+            SerialType::NULL => ColumnRef::NULL,
+            SerialType::I8 => ColumnRef::I8(i8::try_from_be_bytes(bytes)?),
+            SerialType::I16 => ColumnRef::I16(i16::try_from_be_bytes(bytes)?),
+            // the missing high bytes repeat the sign bit
+            SerialType::I24 => {
+                let mut d: [u8; 4] = [Self::sign_byte(bytes); 4];
+                d[1..].copy_from_slice(bytes);
+                ColumnRef::I24(i32::try_from_be_bytes(&d)?)
+            },
+            SerialType::I32 => ColumnRef::I32(i32::try_from_be_bytes(bytes)?),
+            SerialType::I48 => {
+                let mut d: [u8; 8] = [Self::sign_byte(bytes); 8];
+                d[2..].copy_from_slice(bytes);
+                ColumnRef::I48(i64::try_from_be_bytes(&d)?)
+            },
+            SerialType::I64 => ColumnRef::I64(i64::try_from_be_bytes(bytes)?),
+            SerialType::F64 => ColumnRef::F64(f64::try_from_be_bytes(bytes)?),
+            SerialType::I0 => ColumnRef::I0,
+            SerialType::I1 => ColumnRef::I1,
+            SerialType::RESERVED10 => ColumnRef::RESERVED10,
+            SerialType::RESERVED11 => ColumnRef::RESERVED11,
+            SerialType::BLOB(_) => ColumnRef::BLOB(bytes),
+            SerialType::STRING(_) => ColumnRef::STRING(bytes, text_encoding),
         };
+        Ok(column)
+    }
 
-        Ok(Record {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            ColumnRef::I8(v) => Some(*v as i64),
+            ColumnRef::I16(v) => Some(*v as i64),
+            ColumnRef::I24(v) | ColumnRef::I32(v) => Some(*v as i64),
+            ColumnRef::I48(v) | ColumnRef::I64(v) => Some(*v),
+            ColumnRef::I0 => Some(0),
+            ColumnRef::I1 => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Borrowed when the text is valid UTF-8, invalid sequences are replaced otherwise.
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        match self {
+            ColumnRef::STRING(s, text_encoding) => Some(decode_text(s, text_encoding)),
+            _ => None,
+        }
+    }
+
+    pub fn as_blob(&self) -> Option<&'a [u8]> {
+        match self {
+            ColumnRef::BLOB(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, ColumnRef::NULL)
+    }
+
+    pub fn to_column(&self) -> Column {
+        match *self {
+            ColumnRef::UNKNOWN => Column::UNKNOWN,
+            ColumnRef::NULL => Column::NULL,
+            ColumnRef::I8(v) => Column::I8(v),
+            ColumnRef::I16(v) => Column::I16(v),
+            ColumnRef::I24(v) => Column::I24(v),
+            ColumnRef::I32(v) => Column::I32(v),
+            ColumnRef::I48(v) => Column::I48(v),
+            ColumnRef::I64(v) => Column::I64(v),
+            ColumnRef::F64(v) => Column::F64(v),
+            ColumnRef::I0 => Column::I0,
+            ColumnRef::I1 => Column::I1,
+            ColumnRef::RESERVED10 => Column::RESERVED10,
+            ColumnRef::RESERVED11 => Column::RESERVED11,
+            ColumnRef::BLOB(b) => Column::BLOB(b.into()),
+            ColumnRef::STRING(s, text_encoding) => Column::STRING(decode_text(s, &text_encoding).into_owned()),
+        }
+    }
+}
+
+/// A record read in place, its header and columns are decoded while they are iterated.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordRef<'a> {
+    bytes: &'a [u8],
+    header_length: usize,
+    header_length_var_length: usize,
+    text_encoding: TextEncoding,
+}
+
+impl<'a> RecordRef<'a> {
+    pub fn try_from_be_bytes(bytes: &'a [u8]) -> Result<RecordRef<'a>, MyError> {
+        RecordRef::try_from_be_bytes_encoded(bytes, &TextEncoding::UTF8)
+    }
+
+    /// The record of a database with `text_encoding`, its TEXT columns are decoded with it.
+    pub fn try_from_be_bytes_encoded(bytes: &'a [u8], text_encoding: &TextEncoding) -> Result<RecordRef<'a>, MyError> {
+        let (header_length, header_length_var_length) = decode_varint_to_usize(bytes)?;
+        if header_length > bytes.len() {
+            return Err(MyError::new(ErrorKind::SliceLengthError(header_length, bytes.len())));
+        }
+        Ok(RecordRef {
+            bytes,
             header_length,
-            serial_types,
-            columns,
+            header_length_var_length,
+            text_encoding: *text_encoding,
+        })
+    }
+
+    pub fn header_length(&self) -> usize {
+        self.header_length
+    }
+
+    pub fn serial_types(&self) -> SerialTypes<'a> {
+        SerialTypes {
+            header: &self.bytes[..self.header_length],
+            serial_type_start_idx: self.header_length_var_length,
+        }
+    }
+
+    pub fn columns(&self) -> Columns<'a> {
+        Columns {
+            serial_types: self.serial_types(),
+            bytes: self.bytes,
+            column_start_idx: self.header_length,
+            text_encoding: self.text_encoding,
+        }
+    }
+
+    /// The column at `index`, None when the record is shorter.
+    pub fn column(&self, index: usize) -> Result<Option<ColumnRef<'a>>, MyError> {
+        self.columns().nth(index).transpose()
+    }
+
+    pub fn to_record(&self) -> Result<Record, MyError> {
+        Ok(Record {
+            header_length: self.header_length,
+            serial_types: self.serial_types().collect::<Result<Vec<SerialType>, MyError>>()?,
+            columns: self.columns()
+                .map(|column| column.map(|column| column.to_column()))
+                .collect::<Result<Vec<Column>, MyError>>()?,
         })
+    }
+}
 
+/// The serial types of a record header, an unreadable varint ends the iteration after its error.
+pub struct SerialTypes<'a> {
+    header: &'a [u8],
+    serial_type_start_idx: usize,
+}
+
+impl<'a> Iterator for SerialTypes<'a> {
+    type Item = Result<SerialType, MyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.serial_type_start_idx >= self.header.len() {
+            return None;
+        }
+        match decode_varint_to_usize(&self.header[self.serial_type_start_idx..]) {
+            Ok((serial_type_val, serial_type_var_length)) => {
+                self.serial_type_start_idx += serial_type_var_length;
+                Some(Ok(SerialType::from_value(serial_type_val)))
+            },
+            Err(e) => {
+                self.serial_type_start_idx = self.header.len();
+                Some(Err(e))
+            },
+        }
+    }
+}
+
+/// The columns of a record in order, a column running past the record ends the iteration after its error.
+pub struct Columns<'a> {
+    serial_types: SerialTypes<'a>,
+    bytes: &'a [u8],
+    column_start_idx: usize,
+    text_encoding: TextEncoding,
+}
+
+impl<'a> Iterator for Columns<'a> {
+    type Item = Result<ColumnRef<'a>, MyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let serial_type = match self.serial_types.next()? {
+            Ok(serial_type) => serial_type,
+            Err(e) => return Some(Err(e)),
+        };
+        let column_end_idx = self.column_start_idx + serial_type.content_size();
+        if column_end_idx > self.bytes.len() {
+            self.serial_types.serial_type_start_idx = self.serial_types.header.len();
+            return Some(Err(MyError::new(ErrorKind::SliceLengthError(column_end_idx, self.bytes.len()))));
+        }
+        let column = ColumnRef::decode(serial_type, &self.bytes[self.column_start_idx..column_end_idx], self.text_encoding);
+        self.column_start_idx = column_end_idx;
+        Some(column)
     }
 }
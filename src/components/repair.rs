@@ -0,0 +1,509 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_derive::Serialize;
+
+use crate::components::btree::{BtreeTraversal, TraversalProblem};
+use crate::components::cell::Cell;
+use crate::components::database::Database;
+use crate::components::database_header::TextEncoding;
+use crate::components::page_header::PageType;
+use crate::components::record::{Column, Record};
+use crate::components::schema::{SchemaEntry, SQLITE_SCHEMA_ROOT_PAGE};
+use crate::components::table::{is_without_rowid, parse_create_table, without_rowid_column_order};
+use crate::components::writer::{DatabaseWriter, WriterOptions};
+use crate::utils::convert::TryFromBytes;
+use crate::utils::error::MyError;
+use crate::utils::json::serialize_option_i64;
+
+const SQLITE_SCHEMA: &str = "sqlite_schema";
+
+/// A btree page whose rows are lost.
+#[derive(Debug, Serialize)]
+pub struct LostPage {
+    pub page_number: u32,
+    pub table: String,
+    // the rowids the parent page gives to the page, unknown for roots
+    #[serde(serialize_with = "serialize_option_i64")]
+    pub min_row_id: Option<i64>,
+    #[serde(serialize_with = "serialize_option_i64")]
+    pub max_row_id: Option<i64>,
+    pub reason: String,
+}
+
+/// A row of a readable page whose record is lost.
+#[derive(Debug, Serialize)]
+pub struct LostRow {
+    pub table: String,
+    pub page_number: u32,
+    // unknown when the cell itself can't be decoded
+    #[serde(serialize_with = "serialize_option_i64")]
+    pub row_id: Option<i64>,
+    pub reason: String,
+}
+
+/// What a repair recovered and what it lost.
+#[derive(Debug, Default, Serialize)]
+pub struct RepairReport {
+    pub tables: usize,
+    pub indexes: usize,
+    pub rows: usize,
+    // rows read from table leaves no btree reaches any more
+    pub orphan_rows: usize,
+    pub lost_pages: Vec<LostPage>,
+    pub lost_rows: Vec<LostRow>,
+    // schema entries left out, orphan pages which can't be given back to a table
+    pub problems: Vec<String>,
+}
+
+impl RepairReport {
+    pub fn is_ok(&self) -> bool {
+        self.lost_pages.is_empty() && self.lost_rows.is_empty() && self.problems.is_empty()
+    }
+}
+
+// The smallest and largest rowid a parent gives to a child page, unknown for roots.
+type RowIdRange = (Option<i64>, Option<i64>);
+
+type Rows = BTreeMap<i64, Vec<Column>>;
+
+fn lost_page(table: &str, page_number: u32, (min_row_id, max_row_id): RowIdRange, reason: String) -> LostPage {
+    LostPage {
+        page_number,
+        table: table.to_string(),
+        min_row_id,
+        max_row_id,
+        reason,
+    }
+}
+
+// The pages reached so far and the rows read out of them.
+struct Salvage<'a> {
+    db: &'a Database,
+    // pages of a btree, an overflow chain or the freelist, the pages outside btrees are roots
+    traversal: BtreeTraversal,
+    report: RepairReport,
+}
+
+impl<'a> Salvage<'a> {
+    // false when the page is out of range or was reached before
+    fn reach(&mut self, page_number: u32) -> bool {
+        page_number > 0 && page_number <= self.db.page_count() && !self.traversal.is_visited(page_number) && self.traversal.claim(page_number, 0)
+    }
+
+    fn reach_overflow_chain(&mut self, cell: &Cell) {
+        if let (Some(mut page_number), Some(overflow_length)) = (cell.overflow_page_number, cell.overflow_length) {
+            let mut remaining = overflow_length;
+            while remaining > 0 && self.reach(page_number) {
+                remaining -= remaining.min(self.db.usable_size() - 4);
+                page_number = match self.db.page_bytes(page_number).and_then(|bytes| u32::try_from_be_bytes(&bytes[0..4])) {
+                    Ok(next_page_number) => next_page_number,
+                    Err(_) => return,
+                };
+            }
+        }
+    }
+
+    // Freelist pages hold deleted rows, they must not be taken for orphan leaves.
+    fn reach_freelist(&mut self) {
+        let mut trunk_page_number = self.db.header().first_freelist_trunk_page_number;
+        while trunk_page_number != 0 && self.reach(trunk_page_number) {
+            let bytes = match self.db.page_bytes(trunk_page_number) {
+                Ok(bytes) => bytes,
+                Err(_) => return,
+            };
+            let leaf_number = u32::try_from_be_bytes(&bytes[4..8]).unwrap_or(0) as usize;
+            for chunk in bytes[8..].chunks_exact(4).take(leaf_number) {
+                self.reach(u32::try_from_be_bytes(chunk).unwrap_or(0));
+            }
+            trunk_page_number = u32::try_from_be_bytes(&bytes[0..4]).unwrap_or(0);
+        }
+    }
+
+    // The rows of a leaf page, cells which can't be read are lost rows.
+    fn leaf_rows(&mut self, table: &str, page_number: u32) -> Result<Vec<(i64, Record)>, MyError> {
+        let page = self.db.page_ref(page_number)?;
+        let mut rows = Vec::new();
+        for cell in page.cells() {
+            let mut row_id = None;
+            let row = cell
+                .and_then(|cell| cell.to_cell())
+                .and_then(|cell| {
+                    row_id = cell.row_id;
+                    self.reach_overflow_chain(&cell);
+                    self.db.read_record(&cell)
+                });
+            match (row_id, row) {
+                (Some(row_id), Ok(Some(record))) => rows.push((row_id, record)),
+                (_, Ok(_)) => {},
+                (row_id, Err(e)) => self.report.lost_rows.push(LostRow {
+                    table: table.to_string(),
+                    page_number,
+                    row_id,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+        Ok(rows)
+    }
+
+    // Keep the rows of a leaf page, the first copy of a rowid read wins.
+    fn keep_rows(&mut self, table: &str, page_number: u32, leaf_rows: Vec<(i64, Record)>, rows: &mut Rows) {
+        for (row_id, record) in leaf_rows {
+            if rows.insert(row_id, record.columns).is_some() {
+                self.report.problems.push(format!("rowid {} of {} is stored twice, the copy on page {} is kept", row_id, table, page_number));
+            }
+        }
+    }
+
+    // Read the rows of a table btree, following every child pointer which still leads to a page.
+    fn salvage_table(&mut self, table: &str, root_page: u32, rows: &mut Rows) {
+        let db = self.db;
+        // the rowids of the pointers by parent and child page
+        let mut ranges: HashMap<(u32, u32), RowIdRange> = HashMap::new();
+        ranges.insert((0, root_page), (None, None));
+        let mut leaves: Vec<(u32, Vec<(i64, Record)>)> = Vec::new();
+        let mut overflow_cells: Vec<Cell> = Vec::new();
+        let problem_count = self.traversal.problems.len();
+        let report = &mut self.report;
+        let traversed = db.traverse_btree(root_page, &mut self.traversal, |page, page_number, parent, _| {
+            let range = ranges.get(&(parent, page_number)).copied().unwrap_or((None, None));
+            match page.header.page_type {
+                PageType::TableInteriorBtreePage => {
+                    // the left child of a cell holds the rowids up to the key of the cell
+                    let mut min_row_id = range.0;
+                    for cell in page.cells.iter() {
+                        ranges.insert((page_number, cell.left_child_page_number.unwrap_or(0)), (min_row_id, cell.row_id));
+                        min_row_id = cell.row_id.map(|row_id| row_id.saturating_add(1));
+                    }
+                    ranges.insert((page_number, page.header.right_most_pointer.unwrap_or(0)), (min_row_id, range.1));
+                },
+                PageType::TableLeafBtreePage => {
+                    let mut leaf_rows = Vec::new();
+                    for cell in page.cells.iter() {
+                        match (cell.row_id, db.read_record(cell)) {
+                            (Some(row_id), Ok(Some(record))) => leaf_rows.push((row_id, record)),
+                            (_, Ok(_)) => {},
+                            (row_id, Err(e)) => report.lost_rows.push(LostRow {
+                                table: table.to_string(),
+                                page_number,
+                                row_id,
+                                reason: e.to_string(),
+                            }),
+                        }
+                        if cell.overflow_page_number.is_some() {
+                            overflow_cells.push(cell.clone());
+                        }
+                    }
+                    leaves.push((page_number, leaf_rows));
+                },
+                page_type => report.lost_pages.push(lost_page(table, page_number, range, format!("the page is a {:?}", page_type))),
+            }
+            Ok(())
+        });
+        if let Err(e) = traversed {
+            self.report.problems.push(format!("{} can't be read: {}", table, e));
+        }
+
+        for (page_number, leaf_rows) in leaves {
+            self.keep_rows(table, page_number, leaf_rows, rows);
+        }
+        for cell in overflow_cells.iter() {
+            self.reach_overflow_chain(cell);
+        }
+        let problems = self.traversal.problems[problem_count..].to_vec();
+        for problem in problems {
+            match problem {
+                TraversalProblem::Unreadable { page_number, error } => {
+                    let parent = self.traversal.parent(page_number).unwrap_or(0);
+                    let range = ranges.get(&(parent, page_number)).copied().unwrap_or((None, None));
+                    // a leaf with a cell which can't be decoded still gives the rows of the others
+                    match self.db.page_ref(page_number) {
+                        Ok(page) if page.header.page_type == PageType::TableLeafBtreePage => match self.leaf_rows(table, page_number) {
+                            Ok(leaf_rows) => self.keep_rows(table, page_number, leaf_rows, rows),
+                            Err(e) => self.report.lost_pages.push(lost_page(table, page_number, range, e.to_string())),
+                        },
+                        _ => self.report.lost_pages.push(lost_page(table, page_number, range, error)),
+                    }
+                },
+                TraversalProblem::DanglingPointer { page_number, parent } => {
+                    let range = ranges.get(&(parent, page_number)).copied().unwrap_or((None, None));
+                    let reason = format!("the page is out of range, the database has {} pages", self.db.page_count());
+                    self.report.lost_pages.push(lost_page(table, page_number, range, reason));
+                },
+                // the page the pointer should lead to is lost, its leaves may turn up as orphans
+                TraversalProblem::Cycle { page_number, parent } | TraversalProblem::MultipleParents { page_number, parent, .. } if parent != 0 => {
+                    let range = ranges.get(&(parent, page_number)).copied().unwrap_or((None, None));
+                    self.report.lost_pages.push(lost_page(table, page_number, range, problem.to_string()));
+                },
+                problem => self.report.problems.push(format!("{} in {}, skipped", problem, table)),
+            }
+        }
+    }
+
+    // Read the records of the index btree of a WITHOUT ROWID table, every page holds rows.
+    fn salvage_without_rowid_table(&mut self, table: &str, root_page: u32) -> Vec<Record> {
+        let db = self.db;
+        let mut records: Vec<Record> = Vec::new();
+        let mut overflow_cells: Vec<Cell> = Vec::new();
+        let problem_count = self.traversal.problems.len();
+        let report = &mut self.report;
+        let traversed = db.traverse_btree(root_page, &mut self.traversal, |page, page_number, _, _| {
+            match page.header.page_type {
+                PageType::IndexInteriorBtreePage | PageType::IndexLeafBtreePage => for cell in page.cells.iter() {
+                    match db.read_record(cell) {
+                        Ok(Some(record)) => records.push(record),
+                        Ok(None) => {},
+                        Err(e) => report.lost_rows.push(LostRow {
+                            table: table.to_string(),
+                            page_number,
+                            row_id: None,
+                            reason: e.to_string(),
+                        }),
+                    }
+                    if cell.overflow_page_number.is_some() {
+                        overflow_cells.push(cell.clone());
+                    }
+                },
+                page_type => report.lost_pages.push(lost_page(table, page_number, (None, None), format!("the page is a {:?}", page_type))),
+            }
+            Ok(())
+        });
+        if let Err(e) = traversed {
+            self.report.problems.push(format!("{} can't be read: {}", table, e));
+        }
+        for cell in overflow_cells.iter() {
+            self.reach_overflow_chain(cell);
+        }
+        let problems = self.traversal.problems[problem_count..].to_vec();
+        for problem in problems {
+            match problem {
+                TraversalProblem::Unreadable { page_number, error } => self.report.lost_pages.push(lost_page(table, page_number, (None, None), error)),
+                TraversalProblem::DanglingPointer { page_number, .. } => {
+                    let reason = format!("the page is out of range, the database has {} pages", self.db.page_count());
+                    self.report.lost_pages.push(lost_page(table, page_number, (None, None), reason));
+                },
+                problem => self.report.problems.push(format!("{} in {}, skipped", problem, table)),
+            }
+        }
+        records
+    }
+
+    // Mark the pages of an index btree, its entries are built again from the rows.
+    fn reach_btree(&mut self, name: &str, root_page: u32) {
+        let mut overflow_cells: Vec<Cell> = Vec::new();
+        let problem_count = self.traversal.problems.len();
+        let traversed = self.db.traverse_btree(root_page, &mut self.traversal, |page, _, _, _| {
+            overflow_cells.extend(page.cells.iter().filter(|cell| cell.overflow_page_number.is_some()).cloned());
+            Ok(())
+        });
+        if let Err(e) = traversed {
+            self.report.problems.push(format!("{} can't be read: {}", name, e));
+        }
+        for cell in overflow_cells.iter() {
+            self.reach_overflow_chain(cell);
+        }
+        for problem in self.traversal.problems[problem_count..].iter() {
+            self.report.problems.push(format!("{} in {}, skipped", problem, name));
+        }
+    }
+
+    // Table leaves no btree reaches are the children of lost interior pages. A leaf is given back
+    // to a table which lost a page whose rowid range holds every row of the leaf, when no other
+    // table could have it. `columns` is the column count of the tables, `accepts` vets the rows.
+    fn adopt_orphan_leaves<F>(&mut self, columns: &HashMap<String, usize>, rows: &mut HashMap<String, Rows>, accepts: F)
+    where F: Fn(&Record) -> bool {
+        for page_number in 1..=self.db.page_count() {
+            if self.traversal.is_visited(page_number) {
+                continue;
+            }
+            match self.db.page_ref(page_number) {
+                Ok(page) if page.header.page_type == PageType::TableLeafBtreePage && page.cell_count() > 0 => {},
+                _ => continue,
+            }
+            let orphan_rows: Vec<(i64, Record)> = match Salvage::orphan_rows(self.db, page_number) {
+                Some(orphan_rows) if !orphan_rows.is_empty() => orphan_rows,
+                _ => continue,
+            };
+            let first_row_id = orphan_rows[0].0;
+            let last_row_id = orphan_rows[orphan_rows.len() - 1].0;
+            let column_count = orphan_rows.iter().map(|(_, record)| record.columns.len()).max().unwrap_or(0);
+
+            let mut owners: Vec<&str> = self.report.lost_pages.iter()
+                .filter(|lost| lost.min_row_id.is_none_or(|min_row_id| min_row_id <= first_row_id)
+                    && lost.max_row_id.is_none_or(|max_row_id| last_row_id <= max_row_id))
+                .map(|lost| lost.table.as_str())
+                .filter(|table| matches!(columns.get(*table), Some(&count) if count >= column_count))
+                .filter(|_| orphan_rows.iter().all(|(_, record)| accepts(record)))
+                .collect();
+            owners.sort_unstable();
+            owners.dedup();
+            // rows have fewer columns than their table only when it was altered since
+            if owners.len() > 1 {
+                owners.retain(|table| columns.get(*table) == Some(&column_count));
+            }
+            if owners.len() != 1 {
+                continue;
+            }
+            let table = owners[0].to_string();
+            self.reach(page_number);
+            let table_rows = rows.entry(table.clone()).or_default();
+            let mut duplicates = 0;
+            for (row_id, record) in orphan_rows {
+                if table_rows.contains_key(&row_id) {
+                    duplicates += 1;
+                    continue;
+                }
+                table_rows.insert(row_id, record.columns);
+                self.report.orphan_rows += 1;
+            }
+            if duplicates > 0 {
+                self.report.problems.push(format!("{} rows of the orphan page {} of {} were already read, skipped", duplicates, page_number, table));
+            }
+        }
+    }
+
+    // The rows of a leaf page, None when any of them can't be read.
+    fn orphan_rows(db: &Database, page_number: u32) -> Option<Vec<(i64, Record)>> {
+        let page = db.read_page(page_number).ok()?;
+        let mut rows = page.cells.iter()
+            .map(|cell| Some((cell.row_id?, db.read_record(cell).ok()??)))
+            .collect::<Option<Vec<(i64, Record)>>>()?;
+        rows.sort_by_key(|(row_id, _)| *row_id);
+        Some(rows)
+    }
+}
+
+impl Database {
+    /// Read every row which can still be reached, skipping the pages which can't be read and
+    /// giving orphan table leaves back to the table they were cut from, and load them into a
+    /// writer with the same schema, page size, text encoding, user_version and application_id. The
+    /// report lists what was lost. The salvaged rows are all held in memory, and so is the file
+    /// the writer makes out of them.
+    pub fn repair(&self) -> Result<(DatabaseWriter, RepairReport), MyError> {
+        let mut salvage = Salvage {
+            db: self,
+            traversal: BtreeTraversal::new(self.page_count()),
+            report: RepairReport::default(),
+        };
+        if let Some(page_number) = self.lock_byte_page() {
+            salvage.reach(page_number);
+        }
+        salvage.reach_freelist();
+
+        // the schema goes first, the orphans of a damaged schema btree tell which btrees to walk
+        let mut rows: HashMap<String, Rows> = HashMap::new();
+        let mut schema_rows = Rows::new();
+        salvage.salvage_table(SQLITE_SCHEMA, SQLITE_SCHEMA_ROOT_PAGE, &mut schema_rows);
+        rows.insert(SQLITE_SCHEMA.to_string(), schema_rows);
+        let schema_columns: HashMap<String, usize> = vec![(SQLITE_SCHEMA.to_string(), 5)].into_iter().collect();
+        salvage.adopt_orphan_leaves(&schema_columns, &mut rows, |record| {
+            matches!(SchemaEntry::try_from_record(record), Ok(entry) if ["table", "index", "view", "trigger"].contains(&entry.entry_type.as_str()))
+        });
+        let mut schema: Vec<SchemaEntry> = Vec::new();
+        for (row_id, columns) in rows.remove(SQLITE_SCHEMA).unwrap_or_default() {
+            match SchemaEntry::try_from_record(&Record::new(columns)) {
+                Ok(entry) => schema.push(entry),
+                Err(e) => salvage.report.problems.push(format!("schema row {} can't be read, skipped: {}", row_id, e)),
+            }
+        }
+
+        let mut columns: HashMap<String, usize> = HashMap::new();
+        let mut without_rowid_rows: HashMap<String, Vec<Record>> = HashMap::new();
+        for entry in schema.iter().filter(|entry| entry.root_page > 0) {
+            let sql = entry.sql.as_deref().unwrap_or_default();
+            if entry.is_table() && is_without_rowid(sql) {
+                let records = salvage.salvage_without_rowid_table(&entry.name, entry.root_page);
+                without_rowid_rows.insert(entry.name.clone(), records);
+            } else if entry.is_table() {
+                let mut table_rows = Rows::new();
+                salvage.salvage_table(&entry.name, entry.root_page, &mut table_rows);
+                rows.insert(entry.name.clone(), table_rows);
+                columns.insert(entry.name.clone(), parse_create_table(sql).map_or(usize::MAX, |table_columns| table_columns.len()));
+            } else {
+                salvage.reach_btree(&entry.name, entry.root_page);
+            }
+        }
+        salvage.adopt_orphan_leaves(&columns, &mut rows, |_| true);
+        for page_number in 1..=self.page_count() {
+            if salvage.traversal.is_visited(page_number) {
+                continue;
+            }
+            if let Ok(page) = self.page_ref(page_number) {
+                if page.header.page_type == PageType::TableLeafBtreePage && page.cell_count() > 0 {
+                    salvage.report.problems.push(format!("the rows of the orphan page {} can't be given back to a table, skipped", page_number));
+                }
+            }
+        }
+
+        let mut report = salvage.report;
+        let mut writer = DatabaseWriter::new(WriterOptions {
+            page_size: self.page_size(),
+            reserved_bytes: self.header().bytes_at_unused_page_end,
+            // the strings were read as UTF-8 when the encoding is unknown
+            text_encoding: match self.header().text_encoding {
+                TextEncoding::Unknown(_) => TextEncoding::UTF8,
+                text_encoding => text_encoding,
+            },
+            max_cells_per_page: None,
+            user_version: self.header().user_version,
+            application_id: self.header().application_id,
+        })?;
+        // the indexes of UNIQUE and PRIMARY KEY constraints come with their table
+        for entry in schema.iter().filter(|entry| !entry.is_index()) {
+            let sql = match &entry.sql {
+                Some(sql) => sql,
+                None => {
+                    report.problems.push(format!("{} {} has no sql, left out", entry.entry_type, entry.name));
+                    continue;
+                },
+            };
+            let added = if entry.root_page == 0 {
+                writer.add_schema_entry(&entry.entry_type, &entry.name, &entry.tbl_name, sql)
+            } else if is_without_rowid(sql) {
+                // the records hold the PRIMARY KEY columns first, the writer takes them in declared order
+                let records = without_rowid_rows.remove(&entry.name).unwrap_or_default();
+                without_rowid_column_order(sql).and_then(|order| {
+                    let table_rows: Vec<Vec<Column>> = records.into_iter()
+                        .map(|record| {
+                            let mut columns = vec![Column::NULL; order.len()];
+                            for (column, &position) in record.columns.into_iter().zip(order.iter()) {
+                                columns[position] = column;
+                            }
+                            columns
+                        })
+                        .collect();
+                    let row_count = table_rows.len();
+                    writer.add_without_rowid_table(&entry.name, sql, table_rows).map(|left_out| {
+                        report.tables += 1;
+                        report.rows += row_count;
+                        for (index_name, e) in left_out {
+                            report.problems.push(format!("index {} is left out: {}", index_name, e));
+                        }
+                    })
+                })
+            } else {
+                let table_rows: Vec<(i64, Vec<Column>)> = rows.remove(&entry.name).unwrap_or_default().into_iter().collect();
+                let row_count = table_rows.len();
+                writer.add_table(&entry.name, sql, table_rows).map(|left_out| {
+                    report.tables += 1;
+                    report.rows += row_count;
+                    for (index_name, e) in left_out {
+                        report.problems.push(format!("index {} is left out: {}", index_name, e));
+                    }
+                })
+            };
+            if let Err(e) = added {
+                report.problems.push(format!("{} {} is left out: {}", entry.entry_type, entry.name, e));
+            }
+        }
+        for entry in schema.iter().filter(|entry| entry.is_index()) {
+            if let Some(sql) = &entry.sql {
+                match writer.add_index(&entry.name, &entry.tbl_name, sql) {
+                    Ok(()) => report.indexes += 1,
+                    Err(e) => report.problems.push(format!("index {} is left out: {}", entry.name, e)),
+                }
+            }
+        }
+        Ok((writer, report))
+    }
+}
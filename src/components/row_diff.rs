@@ -0,0 +1,194 @@
+use std::cmp::Ordering;
+
+use serde_derive::Serialize;
+
+use crate::components::btree::TableRow;
+use crate::components::database::Database;
+use crate::components::record::Column;
+use crate::components::table::{quote_identifier, Table};
+use crate::utils::error::MyError;
+use crate::utils::json::serialize_i64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum RowChangeKind {
+    Inserted,
+    Deleted,
+    Updated,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnValueChange {
+    pub column: String,
+    pub before: Option<Column>,
+    pub after: Option<Column>,
+}
+
+/// An inserted or deleted row carries all its values, an updated row only the changed ones.
+#[derive(Debug, Serialize)]
+pub struct RowChange {
+    #[serde(serialize_with = "serialize_i64")]
+    pub row_id: i64,
+    pub change: RowChangeKind,
+    pub columns: Vec<ColumnValueChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TableDiff {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub inserted: usize,
+    pub deleted: usize,
+    pub updated: usize,
+    pub rows: Vec<RowChange>,
+}
+
+// Values by column name, the columns may differ when the table was altered in between.
+fn value_of(table: &Table, values: &[Column], name: &str) -> Option<Column> {
+    table.columns.iter()
+        .position(|column| column.name.eq_ignore_ascii_case(name))
+        .and_then(|idx| values.get(idx).cloned())
+}
+
+fn value_to_text(value: &Option<Column>) -> String {
+    match value {
+        Some(value) => value.to_sql_literal(),
+        None => "(none)".to_string(),
+    }
+}
+
+impl Database {
+    /// Compare the rows of `table` with the same table in `other`, `other` being the newer snapshot.
+    /// Both btrees are walked in rowid order and merged on the rowid.
+    pub fn table_diff(&self, other: &Database, table: &str) -> Result<TableDiff, MyError> {
+        let before_table = self.table(table)?;
+        let after_table = other.table(table)?;
+        // only the current row of each btree is held, not the tables
+        let mut before_rows = self.table_row_iter(before_table.entry.root_page).peekable();
+        let mut after_rows = other.table_row_iter(after_table.entry.root_page).peekable();
+
+        let mut columns = after_table.column_names();
+        for name in before_table.column_names() {
+            if !columns.iter().any(|column| column.eq_ignore_ascii_case(&name)) {
+                columns.push(name);
+            }
+        }
+
+        let all_values = |values: &[Column], table: &Table, before: bool| -> Vec<ColumnValueChange> {
+            table.columns.iter().zip(values.iter())
+                .map(|(column, value)| ColumnValueChange {
+                    column: column.name.clone(),
+                    before: if before { Some(value.clone()) } else { None },
+                    after: if before { None } else { Some(value.clone()) },
+                })
+                .collect()
+        };
+        let inserted = |row: &TableRow| RowChange {
+            row_id: row.row_id,
+            change: RowChangeKind::Inserted,
+            columns: all_values(&after_table.values(row), &after_table, false),
+        };
+        let deleted = |row: &TableRow| RowChange {
+            row_id: row.row_id,
+            change: RowChangeKind::Deleted,
+            columns: all_values(&before_table.values(row), &before_table, true),
+        };
+
+        let mut rows = Vec::new();
+        loop {
+            let ordering = match (before_rows.peek(), after_rows.peek()) {
+                (Some(Ok(before_row)), Some(Ok(after_row))) => before_row.row_id.cmp(&after_row.row_id),
+                (Some(Err(_)), _) => Ordering::Less,
+                (_, Some(Err(_))) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match ordering {
+                Ordering::Less => rows.push(deleted(&before_rows.next().unwrap()?)),
+                Ordering::Greater => rows.push(inserted(&after_rows.next().unwrap()?)),
+                Ordering::Equal => {
+                    let before_row = before_rows.next().unwrap()?;
+                    let after_row = after_rows.next().unwrap()?;
+                    let before_values = before_table.values(&before_row);
+                    let after_values = after_table.values(&after_row);
+                    let changes: Vec<ColumnValueChange> = columns.iter()
+                        .map(|name| ColumnValueChange {
+                            column: name.clone(),
+                            before: value_of(&before_table, &before_values, name),
+                            after: value_of(&after_table, &after_values, name),
+                        })
+                        .filter(|change| change.before != change.after)
+                        .collect();
+                    if !changes.is_empty() {
+                        rows.push(RowChange {
+                            row_id: after_row.row_id,
+                            change: RowChangeKind::Updated,
+                            columns: changes,
+                        });
+                    }
+                },
+            }
+        }
+
+        let count = |kind: RowChangeKind| rows.iter().filter(|row| row.change == kind).count();
+        Ok(TableDiff {
+            table: after_table.entry.name.clone(),
+            inserted: count(RowChangeKind::Inserted),
+            deleted: count(RowChangeKind::Deleted),
+            updated: count(RowChangeKind::Updated),
+            columns,
+            rows,
+        })
+    }
+}
+
+impl TableDiff {
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}: {} inserted, {} deleted, {} updated\n", self.table, self.inserted, self.deleted, self.updated);
+        for row in self.rows.iter() {
+            let values = |before: bool| row.columns.iter()
+                .map(|change| format!("{}={}", change.column, value_to_text(if before { &change.before } else { &change.after })))
+                .collect::<Vec<String>>()
+                .join(", ");
+            match row.change {
+                RowChangeKind::Inserted => text.push_str(&format!("+ rowid {}: {}\n", row.row_id, values(false))),
+                RowChangeKind::Deleted => text.push_str(&format!("- rowid {}: {}\n", row.row_id, values(true))),
+                RowChangeKind::Updated => {
+                    text.push_str(&format!("~ rowid {}\n", row.row_id));
+                    for change in row.columns.iter() {
+                        text.push_str(&format!("    {}: {} -> {}\n", change.column, value_to_text(&change.before), value_to_text(&change.after)));
+                    }
+                },
+            }
+        }
+        text
+    }
+
+    /// Statements turning the old snapshot of the table into the new one.
+    pub fn to_sql(&self) -> String {
+        let table = quote_identifier(&self.table);
+        let mut sql = String::from("BEGIN TRANSACTION;\n");
+        for row in self.rows.iter() {
+            match row.change {
+                RowChangeKind::Inserted => {
+                    let names: Vec<String> = row.columns.iter().map(|change| quote_identifier(&change.column)).collect();
+                    let values: Vec<String> = row.columns.iter().map(|change| value_to_text(&change.after)).collect();
+                    sql.push_str(&format!("INSERT INTO {}(rowid, {}) VALUES({}, {});\n", table, names.join(", "), row.row_id, values.join(", ")));
+                },
+                RowChangeKind::Deleted => sql.push_str(&format!("DELETE FROM {} WHERE rowid = {};\n", table, row.row_id)),
+                RowChangeKind::Updated => {
+                    // columns dropped from the table can't be set
+                    let assignments: Vec<String> = row.columns.iter()
+                        .filter_map(|change| change.after.as_ref()
+                            .map(|after| format!("{} = {}", quote_identifier(&change.column), after.to_sql_literal())))
+                        .collect();
+                    if !assignments.is_empty() {
+                        sql.push_str(&format!("UPDATE {} SET {} WHERE rowid = {};\n", table, assignments.join(", "), row.row_id));
+                    }
+                },
+            }
+        }
+        sql.push_str("COMMIT;\n");
+        sql
+    }
+}
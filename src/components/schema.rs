@@ -0,0 +1,59 @@
+use serde_derive::Serialize;
+
+use crate::components::database::Database;
+use crate::components::record::Record;
+use crate::utils::error::MyError;
+use crate::utils::error::ErrorKind;
+
+pub const SQLITE_SCHEMA_ROOT_PAGE: u32 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaEntry {
+    pub entry_type: String,
+    pub name: String,
+    pub tbl_name: String,
+    pub root_page: u32,
+    pub sql: Option<String>,
+}
+
+impl SchemaEntry {
+    // sqlite_schema(type text, name text, tbl_name text, rootpage integer, sql text)
+    pub fn try_from_record(record: &Record) -> Result<SchemaEntry, MyError> {
+        if record.columns.len() < 5 {
+            return Err(MyError::new(ErrorKind::SliceLengthError(5, record.columns.len())));
+        }
+        let text = |idx: usize| record.columns[idx].as_str().unwrap_or_default().to_string();
+
+        Ok(SchemaEntry {
+            entry_type: text(0),
+            name: text(1),
+            tbl_name: text(2),
+            root_page: record.columns[3].as_i64().unwrap_or(0) as u32,
+            sql: record.columns[4].as_str().map(|sql| sql.to_string()),
+        })
+    }
+
+    pub fn is_table(&self) -> bool {
+        self.entry_type == "table"
+    }
+
+    pub fn is_index(&self) -> bool {
+        self.entry_type == "index"
+    }
+}
+
+impl Database {
+    pub fn schema(&self) -> Result<Vec<SchemaEntry>, MyError> {
+        self.table_rows(SQLITE_SCHEMA_ROOT_PAGE)?
+            .iter()
+            .map(|row| SchemaEntry::try_from_record(&row.record))
+            .collect()
+    }
+
+    pub fn find_schema_entry(&self, name: &str) -> Result<SchemaEntry, MyError> {
+        self.schema()?
+            .into_iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| MyError::new(ErrorKind::TableNotFound(name.to_string())))
+    }
+}
@@ -0,0 +1,224 @@
+use serde_derive::Serialize;
+
+use crate::components::btree::BtreeTraversal;
+use crate::components::database::Database;
+use crate::components::database::DATABASE_HEADER_LENGTH;
+use crate::components::page_header::PageHeader;
+use crate::components::schema::SchemaEntry;
+use crate::components::schema::SQLITE_SCHEMA_ROOT_PAGE;
+use crate::utils::convert::TryFromBytes;
+use crate::utils::error::MyError;
+
+/// Space used by one table or index, the same figures sqlite3_analyzer reports.
+#[derive(Debug, Default, Serialize)]
+pub struct SpaceUsage {
+    pub name: String,
+    pub tbl_name: String,
+    pub entry_type: String,
+    pub root_page: u32,
+    pub entries: usize,
+    pub interior_pages: usize,
+    pub leaf_pages: usize,
+    pub overflow_pages: usize,
+    pub total_pages: usize,
+    pub total_bytes: usize,
+    pub used_bytes: usize,
+    pub unused_bytes: usize,
+    pub payload_bytes: u64,
+    pub max_payload: u64,
+    pub average_payload: f64,
+    pub average_fanout: f64,
+    pub depth: usize,
+    pub fragmented_free_bytes: usize,
+    pub fragmentation: f64,
+    pub percentage_of_file: f64,
+    // cycles, bad pointers and unreadable pages, the figures leave out the pages past them
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SpaceUsageReport {
+    pub page_size: usize,
+    pub page_count: u32,
+    pub file_length: usize,
+    pub freelist_pages: u32,
+    pub objects: Vec<SpaceUsage>,
+}
+
+impl Database {
+    // unallocated gap + freeblocks + fragmented bytes of a btree page
+    pub(crate) fn btree_page_unused_bytes(&self, bytes: &[u8], header_start: usize, header: &PageHeader) -> usize {
+        let cell_pointers_end = header_start + header.length + 2 * header.cell_number as usize;
+        let cell_content_area_offset = match header.cell_content_area_offset {
+            0 => 65536,
+            offset => offset as usize,
+        };
+        let unallocated = cell_content_area_offset.saturating_sub(cell_pointers_end);
+
+        let mut freeblocks = 0;
+        let mut freeblock_offset = header.first_free_block_offset as usize;
+        while freeblock_offset != 0 && freeblock_offset + 4 <= bytes.len() {
+            let next = u16::try_from_be_bytes(&bytes[freeblock_offset..freeblock_offset + 2]).unwrap() as usize;
+            freeblocks += u16::try_from_be_bytes(&bytes[freeblock_offset + 2..freeblock_offset + 4]).unwrap() as usize;
+            // freeblocks are kept in increasing order, anything else is a corrupted chain
+            if next <= freeblock_offset {
+                break;
+            }
+            freeblock_offset = next;
+        }
+
+        unallocated + freeblocks + header.fragmented_free_bytes as usize
+    }
+
+    fn count_overflow_pages(&self, first_page_number: u32, overflow_length: usize, usage: &mut SpaceUsage) -> Result<(), MyError> {
+        let mut remaining = overflow_length;
+        let mut page_number = first_page_number;
+        // the chain ends with the payload, a loop can't make it longer
+        while remaining > 0 && page_number != 0 {
+            let bytes = self.page_bytes(page_number)?;
+            let content_length = remaining.min(self.usable_size() - 4);
+            usage.overflow_pages += 1;
+            usage.unused_bytes += self.usable_size() - 4 - content_length;
+            remaining -= content_length;
+            page_number = u32::try_from_be_bytes(&bytes[0..4])?;
+        }
+        Ok(())
+    }
+
+    pub fn space_usage_of(&self, entry: &SchemaEntry) -> Result<SpaceUsage, MyError> {
+        let mut usage = SpaceUsage {
+            name: entry.name.clone(),
+            tbl_name: entry.tbl_name.clone(),
+            entry_type: entry.entry_type.clone(),
+            root_page: entry.root_page,
+            ..Default::default()
+        };
+
+        let mut interior_children = 0;
+        let mut leaf_page_numbers: Vec<u32> = Vec::new();
+        let mut traversal = BtreeTraversal::new(self.page_count());
+        self.traverse_btree(entry.root_page, &mut traversal, |page, page_number, _, depth| {
+            let header_start = if page_number == 1 { DATABASE_HEADER_LENGTH } else { 0 };
+            let bytes = self.page_bytes(page_number)?;
+
+            usage.depth = usage.depth.max(depth + 1);
+            usage.unused_bytes += self.btree_page_unused_bytes(bytes, header_start, &page.header);
+            usage.fragmented_free_bytes += page.header.fragmented_free_bytes as usize;
+
+            if page.header.right_most_pointer.is_some() {
+                usage.interior_pages += 1;
+                interior_children += page.cells.len() + 1;
+            } else {
+                usage.leaf_pages += 1;
+                leaf_page_numbers.push(page_number);
+            }
+
+            for cell in page.cells.iter() {
+                if let Some(payload_length) = cell.payload_length {
+                    usage.entries += 1;
+                    usage.payload_bytes += payload_length;
+                    usage.max_payload = usage.max_payload.max(payload_length);
+                }
+                if let (Some(overflow_page_number), Some(overflow_length)) = (cell.overflow_page_number, cell.overflow_length) {
+                    if let Err(e) = self.count_overflow_pages(overflow_page_number, overflow_length, &mut usage) {
+                        usage.problems.push(format!("the overflow chain at page {} can't be read: {}", overflow_page_number, e));
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        usage.problems.extend(traversal.problems.iter().map(|problem| problem.to_string()));
+
+        // leaves are visited in key order, a leaf not following its predecessor on disk is a gap
+        let gaps = leaf_page_numbers.windows(2).filter(|w| w[1] != w[0] + 1).count();
+
+        usage.total_pages = usage.interior_pages + usage.leaf_pages + usage.overflow_pages;
+        usage.total_bytes = usage.total_pages * self.page_size();
+        usage.unused_bytes += usage.total_pages * (self.page_size() - self.usable_size());
+        usage.used_bytes = usage.total_bytes - usage.unused_bytes;
+        if usage.entries > 0 {
+            usage.average_payload = usage.payload_bytes as f64 / usage.entries as f64;
+        }
+        if usage.interior_pages > 0 {
+            usage.average_fanout = interior_children as f64 / usage.interior_pages as f64;
+        }
+        if leaf_page_numbers.len() > 1 {
+            usage.fragmentation = 100.0 * gaps as f64 / (leaf_page_numbers.len() - 1) as f64;
+        }
+        usage.percentage_of_file = 100.0 * usage.total_pages as f64 / self.page_count() as f64;
+
+        Ok(usage)
+    }
+
+    pub fn space_usage(&self) -> Result<SpaceUsageReport, MyError> {
+        let sqlite_schema = SchemaEntry {
+            entry_type: "table".to_string(),
+            name: "sqlite_schema".to_string(),
+            tbl_name: "sqlite_schema".to_string(),
+            root_page: SQLITE_SCHEMA_ROOT_PAGE,
+            sql: None,
+        };
+
+        let objects = std::iter::once(sqlite_schema)
+            .chain(self.schema()?.into_iter().filter(|entry| entry.root_page > 0))
+            .map(|entry| self.space_usage_of(&entry))
+            .collect::<Result<Vec<SpaceUsage>, MyError>>()?;
+
+        Ok(SpaceUsageReport {
+            page_size: self.page_size(),
+            page_count: self.page_count(),
+            file_length: self.file_length(),
+            freelist_pages: self.header().total_freelist_page_number,
+            objects,
+        })
+    }
+}
+
+impl SpaceUsage {
+    pub fn to_text(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut line = |label: &str, value: String| {
+            let dots = 45usize.saturating_sub(label.len());
+            lines.push(format!("{}{} {}", label, ".".repeat(dots), value));
+        };
+        line("Percentage of total database", format!("{:.1}%", self.percentage_of_file));
+        line("Number of entries", self.entries.to_string());
+        line("Bytes of storage consumed", self.total_bytes.to_string());
+        line("Bytes of payload", self.payload_bytes.to_string());
+        line("Average payload per entry", format!("{:.2}", self.average_payload));
+        line("Maximum payload per entry", self.max_payload.to_string());
+        line("Average fanout", format!("{:.2}", self.average_fanout));
+        line("B-tree depth", self.depth.to_string());
+        line("Non-sequential pages", format!("{:.1}%", self.fragmentation));
+        line("Total pages used", self.total_pages.to_string());
+        line("Primary pages used", (self.interior_pages + self.leaf_pages).to_string());
+        line("Interior pages used", self.interior_pages.to_string());
+        line("Leaf pages used", self.leaf_pages.to_string());
+        line("Overflow pages used", self.overflow_pages.to_string());
+        line("Bytes used", self.used_bytes.to_string());
+        line("Unused bytes", self.unused_bytes.to_string());
+        line("Fragmented bytes", self.fragmented_free_bytes.to_string());
+
+        for problem in self.problems.iter() {
+            lines.push(format!("Damaged: {}", problem));
+        }
+        format!("*** {} {} ***\n\n{}\n", self.entry_type, self.name, lines.join("\n"))
+    }
+}
+
+impl SpaceUsageReport {
+    pub fn is_ok(&self) -> bool {
+        self.objects.iter().all(|object| object.problems.is_empty())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Page size in bytes................ {}\nPages in the whole file........... {}\nFile length in bytes.............. {}\nPages on the freelist............. {}\n\n",
+            self.page_size, self.page_count, self.file_length, self.freelist_pages);
+        for object in self.objects.iter() {
+            text.push_str(&object.to_text());
+            text.push('\n');
+        }
+        text
+    }
+}
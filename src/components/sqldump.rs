@@ -0,0 +1,164 @@
+use std::io::Write;
+
+use serde_derive::Serialize;
+
+use crate::components::btree::{BtreeTraversal, TableRow, TraversalProblem};
+use crate::components::database::Database;
+use crate::components::page_header::PageType;
+use crate::components::schema::{SchemaEntry, SQLITE_SCHEMA_ROOT_PAGE};
+use crate::components::table::{parse_create_table, quote_identifier, Table};
+use crate::utils::error::MyError;
+
+/// What a dump wrote and what it had to leave out.
+#[derive(Debug, Default, Serialize)]
+pub struct DumpReport {
+    pub tables: usize,
+    pub rows: usize,
+    pub problems: Vec<String>,
+}
+
+impl DumpReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+fn problem<W: Write>(report: &mut DumpReport, out: &mut W, problem: String) -> Result<(), MyError> {
+    writeln!(out, "-- {}", problem.replace('\n', " "))?;
+    report.problems.push(problem);
+    Ok(())
+}
+
+impl Database {
+    /// Visit the rows of a table btree in rowid order, pages and rows which can't be read
+    /// are reported in `problems` and skipped. Only `visit` can make it fail.
+    pub fn salvage_table_rows<F>(&self, name: &str, root_page: u32, problems: &mut Vec<String>, mut visit: F) -> Result<usize, MyError>
+    where F: FnMut(&TableRow) -> Result<(), MyError> {
+        let mut count = 0;
+        let mut traversal = BtreeTraversal::new(self.page_count());
+        self.traverse_btree(root_page, &mut traversal, |page, page_number, _, _| {
+            match page.header.page_type {
+                PageType::TableInteriorBtreePage => {},
+                PageType::TableLeafBtreePage => {
+                    for cell in page.cells.iter() {
+                        let row_id = match cell.row_id {
+                            Some(row_id) => row_id,
+                            None => continue,
+                        };
+                        match self.read_record(cell) {
+                            Ok(Some(record)) => {
+                                visit(&TableRow { row_id, record })?;
+                                count += 1;
+                            },
+                            Ok(None) => {},
+                            Err(e) => problems.push(format!("row {} of {} on page {} can't be read, skipped: {}", row_id, name, page_number, e)),
+                        }
+                    }
+                },
+                page_type => problems.push(format!("page {} of {} is a {:?}, skipped", page_number, name, page_type)),
+            }
+            Ok(())
+        })?;
+        problems.extend(traversal.problems.iter().map(|problem| match problem {
+            TraversalProblem::Unreadable { page_number, error } => format!("page {} of {} can't be read, skipped: {}", page_number, name, error),
+            problem => format!("{} in {}, skipped", problem, name),
+        }));
+        Ok(count)
+    }
+
+    /// Write the schema and every readable row as SQL statements, the way `sqlite3 .dump` does,
+    /// so that stock sqlite3 can replay them. Unreadable pages are skipped and reported as comments.
+    pub fn sql_dump<W: Write>(&self, out: &mut W) -> Result<DumpReport, MyError> {
+        let mut report = DumpReport::default();
+        writeln!(out, "PRAGMA foreign_keys=OFF;")?;
+        writeln!(out, "BEGIN TRANSACTION;")?;
+
+        let mut problems = Vec::new();
+        let mut schema: Vec<SchemaEntry> = Vec::new();
+        let mut schema_problems = Vec::new();
+        self.salvage_table_rows("sqlite_schema", SQLITE_SCHEMA_ROOT_PAGE, &mut problems, |row| {
+            match SchemaEntry::try_from_record(&row.record) {
+                Ok(entry) => schema.push(entry),
+                Err(e) => schema_problems.push(format!("schema row {} can't be read, skipped: {}", row.row_id, e)),
+            }
+            Ok(())
+        })?;
+        problems.extend(schema_problems);
+        for p in problems.drain(..) {
+            problem(&mut report, out, p)?;
+        }
+
+        // sqlite_sequence is filled last, the tables with AUTOINCREMENT create it
+        let mut tables: Vec<&SchemaEntry> = schema.iter()
+            .filter(|entry| entry.is_table() && entry.sql.is_some())
+            .collect();
+        tables.sort_by_key(|entry| entry.name == "sqlite_sequence");
+
+        let mut writable_schema = false;
+        let mut analyzed = false;
+        for entry in tables {
+            let sql = entry.sql.as_deref().unwrap_or_default();
+            if entry.name == "sqlite_sequence" {
+                writeln!(out, "DELETE FROM sqlite_sequence;")?;
+            } else if entry.name == "sqlite_stat1" {
+                if !analyzed {
+                    writeln!(out, "ANALYZE sqlite_schema;")?;
+                    analyzed = true;
+                }
+            } else if entry.name.starts_with("sqlite_stat") {
+                // only sqlite_stat1 exists in a stock build
+                writeln!(out, "-- {} is left out, stock sqlite3 doesn't create it", entry.name)?;
+                continue;
+            } else if entry.root_page == 0 {
+                if !writable_schema {
+                    writeln!(out, "PRAGMA writable_schema=ON;")?;
+                    writable_schema = true;
+                }
+                writeln!(out, "INSERT INTO sqlite_schema(type,name,tbl_name,rootpage,sql) VALUES('table',{},{},0,{});",
+                    sql_string(&entry.name), sql_string(&entry.tbl_name), sql_string(sql))?;
+                continue;
+            } else {
+                writeln!(out, "{};", sql)?;
+            }
+            report.tables += 1;
+
+            let table = Table {
+                entry: entry.clone(),
+                // the raw record columns are dumped when CREATE TABLE can't be parsed
+                columns: parse_create_table(sql).unwrap_or_default(),
+            };
+            if sql.to_uppercase().replace(char::is_whitespace, "").contains("WITHOUTROWID") {
+                problem(&mut report, out, format!("rows of the WITHOUT ROWID table {} are not dumped", entry.name))?;
+                continue;
+            }
+
+            let insert = format!("INSERT INTO {} VALUES(", quote_identifier(&entry.name));
+            let rows = self.salvage_table_rows(&entry.name, entry.root_page, &mut problems, |row| {
+                let values = if table.columns.is_empty() { row.record.columns.clone() } else { table.values(row) };
+                let values: Vec<String> = values.iter().map(|column| column.to_sql_literal()).collect();
+                writeln!(out, "{}{});", insert, values.join(","))?;
+                Ok(())
+            })?;
+            report.rows += rows;
+            for p in problems.drain(..) {
+                problem(&mut report, out, p)?;
+            }
+        }
+
+        for entry in schema.iter().filter(|entry| !entry.is_table()) {
+            // automatic indexes of UNIQUE and PRIMARY KEY have no sql
+            if let Some(sql) = &entry.sql {
+                writeln!(out, "{};", sql)?;
+            }
+        }
+        if writable_schema {
+            writeln!(out, "PRAGMA writable_schema=OFF;")?;
+        }
+        writeln!(out, "COMMIT;")?;
+        Ok(report)
+    }
+}
+
+fn sql_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
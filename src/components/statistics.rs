@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use serde_derive::Serialize;
+
+use crate::components::database::Database;
+use crate::components::database_header::TextEncoding;
+use crate::components::record::Column;
+use crate::components::record::Record;
+use crate::components::schema::SchemaEntry;
+use crate::utils::error::MyError;
+
+pub const SQLITE_STAT1: &str = "sqlite_stat1";
+pub const SQLITE_STAT4: &str = "sqlite_stat4";
+
+/// One row of sqlite_stat1(tbl, idx, stat).
+#[derive(Debug, Serialize)]
+pub struct Stat1Entry {
+    pub table: String,
+    pub index: Option<String>,
+    pub row_estimate: u64,
+    pub rows_per_key: Vec<u64>,
+    pub options: Vec<String>,
+}
+
+/// One row of sqlite_stat4(tbl, idx, neq, nlt, ndlt, sample), with the sample key decoded
+/// and the equal-prefix counts measured on the index btree.
+#[derive(Debug, Serialize)]
+pub struct Stat4Sample {
+    pub table: String,
+    pub index: String,
+    pub neq: Vec<u64>,
+    pub nlt: Vec<u64>,
+    pub ndlt: Vec<u64>,
+    pub sample: Option<Record>,
+    pub actual_neq: Option<Vec<u64>>,
+}
+
+/// The stat1 estimates next to the counts found by walking the btree.
+#[derive(Debug, Serialize)]
+pub struct StatComparison {
+    pub table: String,
+    pub index: Option<String>,
+    pub estimated_rows: u64,
+    pub actual_rows: Option<u64>,
+    pub estimated_rows_per_key: Vec<u64>,
+    pub actual_rows_per_key: Option<Vec<u64>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Statistics {
+    pub stat1: Vec<Stat1Entry>,
+    pub stat4: Vec<Stat4Sample>,
+    pub comparisons: Vec<StatComparison>,
+}
+
+fn column_text(column: Option<&Column>) -> Option<String> {
+    column.and_then(|c| c.as_str()).map(|s| s.to_string())
+}
+
+fn parse_numbers(s: &str) -> Vec<u64> {
+    s.split_whitespace()
+        .map_while(|token| token.parse::<u64>().ok())
+        .collect()
+}
+
+impl Stat1Entry {
+    pub fn try_from_record(record: &Record) -> Option<Stat1Entry> {
+        let table = column_text(record.columns.first())?;
+        let index = column_text(record.columns.get(1));
+        let stat = column_text(record.columns.get(2))?;
+
+        let numbers = parse_numbers(&stat);
+        let options = stat.split_whitespace()
+            .skip(numbers.len())
+            .map(|option| option.to_string())
+            .collect();
+        let (row_estimate, rows_per_key) = match numbers.split_first() {
+            Some((row_estimate, rows_per_key)) => (*row_estimate, rows_per_key.to_vec()),
+            None => (0, vec![]),
+        };
+
+        Some(Stat1Entry {
+            table,
+            index,
+            row_estimate,
+            rows_per_key,
+            options,
+        })
+    }
+}
+
+impl Stat4Sample {
+    /// The sample record is in the text encoding of the database, like its rows.
+    pub fn try_from_record(record: &Record, text_encoding: &TextEncoding) -> Option<Stat4Sample> {
+        let numbers = |idx: usize| parse_numbers(&column_text(record.columns.get(idx)).unwrap_or_default());
+
+        Some(Stat4Sample {
+            table: column_text(record.columns.first())?,
+            index: column_text(record.columns.get(1))?,
+            neq: numbers(2),
+            nlt: numbers(3),
+            ndlt: numbers(4),
+            sample: record.columns.get(5)
+                .and_then(|c| c.as_blob())
+                .and_then(|blob| Record::try_from_be_bytes_encoded(blob, text_encoding).ok()),
+            actual_neq: None,
+        })
+    }
+}
+
+// Number of distinct key prefixes of length 1..=prefix_number, the entries are in index order.
+fn distinct_prefixes(entries: &[Record], prefix_number: usize) -> Vec<u64> {
+    (1..=prefix_number)
+        .map(|prefix_length| {
+            let mut distinct = 0;
+            let mut previous: Option<&[Column]> = None;
+            for entry in entries {
+                let prefix = &entry.columns[..prefix_length.min(entry.columns.len())];
+                if previous != Some(prefix) {
+                    distinct += 1;
+                    previous = Some(prefix);
+                }
+            }
+            distinct
+        })
+        .collect()
+}
+
+impl Database {
+    fn stat_table_rows(&self, schema: &[SchemaEntry], name: &str) -> Result<Vec<Record>, MyError> {
+        match schema.iter().find(|entry| entry.is_table() && entry.name == name) {
+            Some(entry) => Ok(self.table_rows(entry.root_page)?.into_iter().map(|row| row.record).collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    pub fn statistics(&self) -> Result<Statistics, MyError> {
+        let schema = self.schema()?;
+        let root_page_of = |name: &str| schema.iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            .map(|entry| entry.root_page);
+
+        let stat1: Vec<Stat1Entry> = self.stat_table_rows(&schema, SQLITE_STAT1)?
+            .iter()
+            .filter_map(Stat1Entry::try_from_record)
+            .collect();
+        let mut stat4: Vec<Stat4Sample> = self.stat_table_rows(&schema, SQLITE_STAT4)?
+            .iter()
+            .filter_map(|record| Stat4Sample::try_from_record(record, &self.header().text_encoding))
+            .collect();
+
+        let mut index_entries: HashMap<String, Vec<Record>> = HashMap::new();
+        let mut comparisons = Vec::with_capacity(stat1.len());
+        for entry in stat1.iter() {
+            let (actual_rows, actual_rows_per_key) = match &entry.index {
+                Some(index) => match root_page_of(index) {
+                    Some(root_page) => {
+                        if !index_entries.contains_key(index) {
+                            index_entries.insert(index.clone(), self.index_entries(root_page)?);
+                        }
+                        let entries = &index_entries[index];
+                        let rows = entries.len() as u64;
+                        let rows_per_key = distinct_prefixes(entries, entry.rows_per_key.len())
+                            .iter()
+                            .map(|distinct| if *distinct == 0 { 0 } else { rows.div_ceil(*distinct) })
+                            .collect();
+                        (Some(rows), Some(rows_per_key))
+                    },
+                    None => (None, None),
+                },
+                None => match root_page_of(&entry.table) {
+                    Some(root_page) => (Some(self.count_entries(root_page)? as u64), None),
+                    None => (None, None),
+                },
+            };
+
+            comparisons.push(StatComparison {
+                table: entry.table.clone(),
+                index: entry.index.clone(),
+                estimated_rows: entry.row_estimate,
+                actual_rows,
+                estimated_rows_per_key: entry.rows_per_key.clone(),
+                actual_rows_per_key,
+            });
+        }
+
+        for sample in stat4.iter_mut() {
+            let root_page = match root_page_of(&sample.index) {
+                Some(root_page) => root_page,
+                None => continue,
+            };
+            let key = match &sample.sample {
+                Some(record) => &record.columns,
+                None => continue,
+            };
+            if !index_entries.contains_key(&sample.index) {
+                index_entries.insert(sample.index.clone(), self.index_entries(root_page)?);
+            }
+            let entries = &index_entries[&sample.index];
+            sample.actual_neq = Some(
+                (1..=key.len())
+                    .map(|prefix_length| entries.iter()
+                        .filter(|entry| entry.columns.len() >= prefix_length
+                            && entry.columns[..prefix_length] == key[..prefix_length])
+                        .count() as u64)
+                    .collect()
+            );
+        }
+
+        Ok(Statistics {
+            stat1,
+            stat4,
+            comparisons,
+        })
+    }
+}
@@ -0,0 +1,435 @@
+use serde_derive::Serialize;
+
+use crate::components::btree::TableRow;
+use crate::components::database::Database;
+use crate::components::record::Column;
+use crate::components::schema::SchemaEntry;
+use crate::utils::error::MyError;
+use crate::utils::error::ErrorKind;
+use crate::utils::string::decode_hex;
+
+/// A column as declared by CREATE TABLE.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableColumn {
+    pub name: String,
+    pub declared_type: String,
+    // INTEGER PRIMARY KEY, the record holds NULL and the value is the rowid
+    pub is_rowid_alias: bool,
+    // the DEFAULT literal, the value of the rows written before ALTER TABLE added the column
+    pub default: Option<Column>,
+}
+
+/// A rowid table with the columns parsed from its CREATE TABLE statement.
+#[derive(Debug, Clone, Serialize)]
+pub struct Table {
+    pub entry: SchemaEntry,
+    pub columns: Vec<TableColumn>,
+}
+
+// keywords ending the type of a column definition
+const COLUMN_CONSTRAINTS: [&str; 11] = ["CONSTRAINT", "PRIMARY", "NOT", "NULL", "UNIQUE", "CHECK", "DEFAULT", "COLLATE", "REFERENCES", "GENERATED", "AS"];
+const TABLE_CONSTRAINTS: [&str; 5] = ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"];
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    text: String,
+    quoted: bool,
+}
+
+impl Token {
+    fn is(&self, keyword: &str) -> bool {
+        !self.quoted && self.text.eq_ignore_ascii_case(keyword)
+    }
+}
+
+// Split sql into identifiers, keywords, quoted names and single punctuation characters.
+fn tokenize(sql: &str) -> Vec<Token> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        let c = chars[idx];
+        if c.is_whitespace() {
+            idx += 1;
+        } else if c == '-' && chars.get(idx + 1) == Some(&'-') {
+            while idx < chars.len() && chars[idx] != '\n' {
+                idx += 1;
+            }
+        } else if c == '/' && chars.get(idx + 1) == Some(&'*') {
+            idx += 2;
+            while idx < chars.len() && !(chars[idx] == '*' && chars.get(idx + 1) == Some(&'/')) {
+                idx += 1;
+            }
+            idx += 2;
+        } else if c == '"' || c == '\'' || c == '`' || c == '[' {
+            let close = if c == '[' { ']' } else { c };
+            let mut text = String::new();
+            idx += 1;
+            while idx < chars.len() {
+                if chars[idx] == close {
+                    // a doubled quote stands for the quote itself
+                    if close != ']' && chars.get(idx + 1) == Some(&close) {
+                        text.push(close);
+                        idx += 2;
+                        continue;
+                    }
+                    break;
+                }
+                text.push(chars[idx]);
+                idx += 1;
+            }
+            idx += 1;
+            tokens.push(Token { text, quoted: true });
+        } else if c.is_alphanumeric() || c == '_' || c == '$' || c == '.' || c == '+' || c == '-' {
+            let start = idx;
+            while idx < chars.len() && (chars[idx].is_alphanumeric() || "_$.+-".contains(chars[idx])) {
+                idx += 1;
+            }
+            tokens.push(Token { text: chars[start..idx].iter().collect(), quoted: false });
+        } else {
+            tokens.push(Token { text: c.to_string(), quoted: false });
+            idx += 1;
+        }
+    }
+    tokens
+}
+
+// The definitions between the outer parentheses of CREATE TABLE, split on the top level commas.
+fn split_definitions(tokens: &[Token]) -> Option<Vec<&[Token]>> {
+    let open = tokens.iter().position(|token| token.is("("))?;
+    let mut definitions = Vec::new();
+    let mut depth = 0;
+    let mut start = open + 1;
+    for (idx, token) in tokens.iter().enumerate().skip(open + 1) {
+        if token.is("(") {
+            depth += 1;
+        } else if token.is(")") && depth > 0 {
+            depth -= 1;
+        } else if token.is(")") || (token.is(",") && depth == 0) {
+            definitions.push(&tokens[start..idx]);
+            start = idx + 1;
+            if token.is(")") {
+                return Some(definitions);
+            }
+        }
+    }
+    None
+}
+
+// A numeric literal, decimal or 0x hexadecimal, with its sign.
+fn parse_number(text: &str) -> Option<Column> {
+    if let Ok(value) = text.parse::<i64>() {
+        return Some(Column::integer(value));
+    }
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let sign = if negative { -1 } else { 1 };
+    // hexadecimal literals are the bits of a 64-bit integer
+    if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16).ok().map(|value| Column::integer((value as i64).wrapping_mul(sign)));
+    }
+    if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    digits.parse::<f64>().ok().map(|value| Column::F64(sign as f64 * value))
+}
+
+// The literal after DEFAULT, in parentheses or not. NULL and the expressions sqlite computes
+// when a row is written, CURRENT_TIMESTAMP and the like, have none.
+fn parse_default(tokens: &[Token]) -> Option<Column> {
+    let tokens = match tokens {
+        [open, literal @ .., close] if open.is("(") && close.is(")") => literal,
+        tokens => tokens,
+    };
+    match tokens {
+        [literal] if literal.quoted => Some(Column::STRING(literal.text.clone())),
+        [x, literal] if x.is("X") && literal.quoted && literal.text.len() % 2 == 0 =>
+            decode_hex(&literal.text).ok().map(|bytes| Column::BLOB(bytes.into_boxed_slice())),
+        [literal] if literal.is("TRUE") => Some(Column::I1),
+        [literal] if literal.is("FALSE") => Some(Column::I0),
+        [literal] => parse_number(&literal.text),
+        [sign, literal] if (sign.is("-") || sign.is("+")) && !literal.quoted => parse_number(&format!("{}{}", sign.text, literal.text)),
+        _ => None,
+    }
+}
+
+fn parse_column(definition: &[Token]) -> TableColumn {
+    let type_length = definition[1..].iter()
+        .position(|token| COLUMN_CONSTRAINTS.iter().any(|keyword| token.is(keyword)))
+        .unwrap_or(definition.len() - 1);
+    let declared_type = definition[1..1 + type_length].iter()
+        .map(|token| token.text.to_uppercase())
+        .collect::<Vec<String>>()
+        .join(" ")
+        .replace(" (", "(")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(" ,", ",");
+    // INTEGER PRIMARY KEY DESC is not an alias of the rowid
+    let primary_key = definition.windows(2).position(|w| w[0].is("PRIMARY") && w[1].is("KEY"));
+    let descending = matches!(primary_key.and_then(|idx| definition.get(idx + 2)), Some(token) if token.is("DESC"));
+
+    // the literal runs up to the next constraint, DEFAULT NULL included
+    let default = definition.iter().position(|token| token.is("DEFAULT")).and_then(|idx| {
+        let literal = &definition[idx + 1..];
+        let length = literal.iter().skip(1)
+            .position(|token| COLUMN_CONSTRAINTS.iter().any(|keyword| token.is(keyword)))
+            .map_or(literal.len(), |length| length + 1);
+        parse_default(&literal[..length])
+    });
+
+    TableColumn {
+        name: definition[0].text.clone(),
+        is_rowid_alias: declared_type == "INTEGER" && primary_key.is_some() && !descending,
+        declared_type,
+        default,
+    }
+}
+
+/// Whether a CREATE TABLE statement makes a WITHOUT ROWID table, its rows are stored in an index
+/// btree keyed on the PRIMARY KEY.
+pub fn is_without_rowid(sql: &str) -> bool {
+    tokenize(sql).windows(2).any(|w| w[0].is("WITHOUT") && w[1].is("ROWID"))
+}
+
+/// Parse the columns out of a CREATE TABLE statement.
+pub fn parse_create_table(sql: &str) -> Result<Vec<TableColumn>, MyError> {
+    let tokens = tokenize(sql);
+    let definitions = split_definitions(&tokens)
+        .ok_or_else(|| MyError::new(ErrorKind::SchemaParseError(sql.to_string())))?;
+
+    let mut columns = Vec::new();
+    let mut primary_key: Vec<String> = Vec::new();
+    for definition in definitions.iter().filter(|definition| !definition.is_empty()) {
+        if !TABLE_CONSTRAINTS.iter().any(|keyword| definition[0].is(keyword)) {
+            columns.push(parse_column(definition));
+            continue;
+        }
+        // PRIMARY KEY (a) makes a an alias of the rowid too when it is declared INTEGER
+        if let Some(idx) = definition.windows(2).position(|w| w[0].is("PRIMARY") && w[1].is("KEY")) {
+            let names = &definition[idx + 2..];
+            primary_key = split_definitions(names).map_or(Vec::new(), |names| names.iter()
+                .filter_map(|name| name.first().map(|token| token.text.clone()))
+                .collect());
+        }
+    }
+    if primary_key.len() == 1 {
+        for column in columns.iter_mut().filter(|column| column.name.eq_ignore_ascii_case(&primary_key[0])) {
+            column.is_rowid_alias = column.declared_type == "INTEGER";
+        }
+    }
+    if columns.is_empty() {
+        return Err(MyError::new(ErrorKind::SchemaParseError(sql.to_string())));
+    }
+    Ok(columns)
+}
+
+/// A column of a CREATE INDEX statement.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexColumn {
+    pub name: String,
+    pub descending: bool,
+    pub collation: Option<String>,
+}
+
+/// Parse the indexed columns out of a CREATE INDEX statement, indexes on expressions are refused.
+pub fn parse_create_index(sql: &str) -> Result<Vec<IndexColumn>, MyError> {
+    let parse_error = || MyError::new(ErrorKind::SchemaParseError(sql.to_string()));
+    let tokens = tokenize(sql);
+    let definitions = split_definitions(&tokens).ok_or_else(parse_error)?;
+
+    definitions.iter()
+        .map(|definition| parse_index_column(definition).ok_or_else(parse_error))
+        .collect()
+}
+
+// name [COLLATE collation] [ASC|DESC]
+fn parse_index_column(definition: &[Token]) -> Option<IndexColumn> {
+    let (name, rest) = definition.split_first()?;
+    let mut column = IndexColumn {
+        name: name.text.clone(),
+        descending: false,
+        collation: None,
+    };
+    let mut idx = 0;
+    while idx < rest.len() {
+        if rest[idx].is("COLLATE") && idx + 1 < rest.len() {
+            column.collation = Some(rest[idx + 1].text.to_uppercase());
+            idx += 2;
+        } else if rest[idx].is("ASC") || rest[idx].is("DESC") {
+            column.descending = rest[idx].is("DESC");
+            idx += 1;
+        } else {
+            return None;
+        }
+    }
+    Some(column)
+}
+
+// The collation named by a column definition.
+fn collation_of(definition: &[Token]) -> Option<String> {
+    definition.windows(2)
+        .find(|w| w[0].is("COLLATE"))
+        .map(|w| w[1].text.to_uppercase())
+}
+
+/// The columns of a CREATE TABLE statement with the collation they are declared with, an index
+/// on a column sorts with it unless the index names another one.
+pub fn declared_collations(sql: &str) -> Result<Vec<(String, Option<String>)>, MyError> {
+    let tokens = tokenize(sql);
+    let definitions = split_definitions(&tokens).ok_or_else(|| MyError::new(ErrorKind::SchemaParseError(sql.to_string())))?;
+    Ok(definitions.iter()
+        .filter(|definition| !definition.is_empty() && !TABLE_CONSTRAINTS.iter().any(|keyword| definition[0].is(keyword)))
+        .map(|definition| (definition[0].text.clone(), collation_of(definition)))
+        .collect())
+}
+
+/// The columns of the indexes sqlite creates for the UNIQUE and PRIMARY KEY constraints of a
+/// table, in the order their sqlite_autoindex_TABLE_N names are numbered. A column keeps the
+/// collation it is declared with unless the constraint names another one. The PRIMARY KEY of a
+/// WITHOUT ROWID table takes a number too, though its btree is the table itself.
+pub fn parse_unique_constraints(sql: &str) -> Result<Vec<Vec<IndexColumn>>, MyError> {
+    // sqlite doesn't create a second index on the same columns
+    let mut unique: Vec<Vec<IndexColumn>> = Vec::new();
+    for constraint in parse_key_constraints(sql)?.0 {
+        if !unique.contains(&constraint) {
+            unique.push(constraint);
+        }
+    }
+    Ok(unique)
+}
+
+/// The PRIMARY KEY columns, None when the table has none.
+pub fn parse_primary_key(sql: &str) -> Result<Option<Vec<IndexColumn>>, MyError> {
+    Ok(parse_key_constraints(sql)?.1)
+}
+
+/// The positions of the declared columns in the records of a WITHOUT ROWID table: the PRIMARY
+/// KEY columns in key order, then the others in the order they are declared.
+pub fn without_rowid_column_order(sql: &str) -> Result<Vec<usize>, MyError> {
+    let columns = parse_create_table(sql)?;
+    let primary_key = parse_primary_key(sql)?
+        .ok_or_else(|| MyError::new(ErrorKind::SchemaParseError(sql.to_string())))?;
+    let mut order: Vec<usize> = Vec::with_capacity(columns.len());
+    for key_column in primary_key.iter() {
+        let position = columns.iter()
+            .position(|column| column.name.eq_ignore_ascii_case(&key_column.name))
+            .ok_or_else(|| MyError::new(ErrorKind::SchemaParseError(sql.to_string())))?;
+        if !order.contains(&position) {
+            order.push(position);
+        }
+    }
+    order.extend((0..columns.len()).filter(|position| !order.contains(position)).collect::<Vec<usize>>());
+    Ok(order)
+}
+
+// The constraints sqlite numbers indexes for and the PRIMARY KEY.
+type KeyConstraints = (Vec<Vec<IndexColumn>>, Option<Vec<IndexColumn>>);
+
+// The UNIQUE and PRIMARY KEY constraints in the order sqlite numbers their indexes, and the
+// PRIMARY KEY. An INTEGER PRIMARY KEY takes no number, even in a WITHOUT ROWID table.
+fn parse_key_constraints(sql: &str) -> Result<KeyConstraints, MyError> {
+    let parse_error = || MyError::new(ErrorKind::SchemaParseError(sql.to_string()));
+    let columns = parse_create_table(sql)?;
+    let tokens = tokenize(sql);
+    let definitions = split_definitions(&tokens).ok_or_else(parse_error)?;
+    let declared_collations = declared_collations(sql)?;
+
+    let mut constraints: Vec<Vec<IndexColumn>> = Vec::new();
+    let mut primary_key_columns: Option<Vec<IndexColumn>> = None;
+    for definition in definitions.iter().filter(|definition| !definition.is_empty()) {
+        if TABLE_CONSTRAINTS.iter().any(|keyword| definition[0].is(keyword)) {
+            let keyword = definition.iter().position(|token| token.is("UNIQUE") || token.is("PRIMARY"));
+            let (idx, primary_key) = match keyword {
+                Some(idx) if definition[idx].is("PRIMARY") => (idx + 2, true),
+                Some(idx) => (idx + 1, false),
+                None => continue,
+            };
+            let names = split_definitions(definition.get(idx..).unwrap_or_default()).ok_or_else(parse_error)?;
+            let mut index_columns = names.iter()
+                .map(|name| parse_index_column(name).ok_or_else(parse_error))
+                .collect::<Result<Vec<IndexColumn>, MyError>>()?;
+            for index_column in index_columns.iter_mut().filter(|index_column| index_column.collation.is_none()) {
+                index_column.collation = declared_collations.iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(&index_column.name))
+                    .and_then(|(_, collation)| collation.clone());
+            }
+            if primary_key {
+                primary_key_columns = Some(index_columns.clone());
+                // the INTEGER PRIMARY KEY is the rowid itself
+                let rowid_alias = |index_column: &IndexColumn| columns.iter()
+                    .any(|column| column.is_rowid_alias && column.name.eq_ignore_ascii_case(&index_column.name));
+                if index_columns.len() == 1 && rowid_alias(&index_columns[0]) {
+                    continue;
+                }
+            }
+            constraints.push(index_columns);
+        } else {
+            let column = parse_column(definition);
+            for (idx, token) in definition.iter().enumerate().skip(1) {
+                let primary_key = token.is("PRIMARY") && matches!(definition.get(idx + 1), Some(next) if next.is("KEY"));
+                if !primary_key && !token.is("UNIQUE") {
+                    continue;
+                }
+                let index_column = IndexColumn {
+                    name: column.name.clone(),
+                    descending: primary_key && matches!(definition.get(idx + 2), Some(next) if next.is("DESC")),
+                    collation: collation_of(definition),
+                };
+                if primary_key {
+                    primary_key_columns = Some(vec![index_column.clone()]);
+                }
+                if !(primary_key && column.is_rowid_alias) {
+                    constraints.push(vec![index_column]);
+                }
+            }
+        }
+    }
+    Ok((constraints, primary_key_columns))
+}
+
+/// Quote a table or column name for SQL statements.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+impl Table {
+    pub fn name(&self) -> &str {
+        &self.entry.name
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|column| column.name.clone()).collect()
+    }
+
+    /// The values of a row in the column order, the rowid alias gets the rowid and
+    /// columns added after the row was written get their DEFAULT value, NULL without one.
+    pub fn values(&self, row: &TableRow) -> Vec<Column> {
+        self.columns.iter()
+            .enumerate()
+            .map(|(idx, column)| match row.record.columns.get(idx) {
+                Some(Column::NULL) if column.is_rowid_alias => Column::I64(row.row_id),
+                Some(value) => value.clone(),
+                None => column.default.clone().unwrap_or(Column::NULL),
+            })
+            .collect()
+    }
+}
+
+impl Database {
+    /// Look a rowid table up by name, WITHOUT ROWID and virtual tables have no rowid btree to walk.
+    pub fn table(&self, name: &str) -> Result<Table, MyError> {
+        let entry = self.find_schema_entry(name)?;
+        let sql = entry.sql.clone().unwrap_or_default();
+        if !entry.is_table() || entry.root_page == 0 || is_without_rowid(&sql) {
+            return Err(MyError::new(ErrorKind::TableNotFound(name.to_string())));
+        }
+
+        Ok(Table {
+            columns: parse_create_table(&sql)?,
+            entry,
+        })
+    }
+}
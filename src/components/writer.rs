@@ -0,0 +1,679 @@
+use std::cmp::Ordering;
+use std::fs;
+
+use crate::components::cell::Cell;
+use crate::components::database::{lock_byte_page_number, DATABASE_HEADER_LENGTH};
+use crate::components::database_header::{DatabaseHeader, FileFormatVersion, SchemaFormatNumber, TextEncoding, SQLITE_DB_HEADER_STRING};
+use crate::components::page::PageBuilder;
+use crate::components::page_header::PageType;
+use crate::components::record::{Column, Record};
+use crate::components::schema::SQLITE_SCHEMA_ROOT_PAGE;
+use crate::components::table::{declared_collations, is_without_rowid, parse_create_index, parse_create_table, parse_primary_key, parse_unique_constraints, without_rowid_column_order, IndexColumn, TableColumn};
+use crate::utils::convert::ToBytes;
+use crate::utils::error::{ErrorKind, MyError};
+
+// SQLITE_VERSION_NUMBER written to the header, 3.31.1
+const SQLITE_VERSION_NUMBER: u32 = 3_031_001;
+
+/// The shape of the file written.
+#[derive(Debug, Clone)]
+pub struct WriterOptions {
+    // a power of two from 512 to 65536
+    pub page_size: usize,
+    pub reserved_bytes: u8,
+    pub text_encoding: TextEncoding,
+    // fewer cells per page make deeper btrees out of fewer rows
+    pub max_cells_per_page: Option<usize>,
+    // copied to the header as they are
+    pub user_version: u32,
+    pub application_id: u32,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            page_size: 4096,
+            reserved_bytes: 0,
+            text_encoding: TextEncoding::UTF8,
+            max_cells_per_page: None,
+            user_version: 0,
+            application_id: 0,
+        }
+    }
+}
+
+struct TableSpec {
+    name: String,
+    sql: String,
+    // rowid and the columns as stored, NULL for the rowid alias. The rows of a WITHOUT ROWID
+    // table are numbered in the order they were added and their columns are in declared order
+    rows: Vec<(i64, Vec<Column>)>,
+    // the PRIMARY KEY of a WITHOUT ROWID table, its rows make an index btree sorted on it
+    primary_key: Option<Vec<IndexColumn>>,
+}
+
+struct IndexSpec {
+    name: String,
+    table_name: String,
+    // None for the indexes of UNIQUE and PRIMARY KEY constraints
+    sql: Option<String>,
+    columns: Vec<IndexColumn>,
+}
+
+// Views, triggers and virtual tables, they have no btree.
+struct EntrySpec {
+    entry_type: String,
+    name: String,
+    table_name: String,
+    sql: String,
+}
+
+/// Creates a database file from its schema and rows without the sqlite library: every btree is
+/// built bottom-up with its pages packed, the schema is rooted in page 1 and there is no freelist.
+/// The rows added and the whole file are held in memory until it is written.
+pub struct DatabaseWriter {
+    options: WriterOptions,
+    tables: Vec<TableSpec>,
+    indexes: Vec<IndexSpec>,
+    entries: Vec<EntrySpec>,
+}
+
+// A btree cell together with the key its parent divides on, the rowid for tables.
+struct BtreeCell {
+    cell: Cell,
+    row_id: i64,
+}
+
+// Cells with the running total of the space they take on a page, their pointer included.
+struct SizedCells<'c> {
+    cells: Vec<&'c Cell>,
+    ends: Vec<usize>,
+}
+
+impl<'c> SizedCells<'c> {
+    fn new(cells: Vec<&'c Cell>) -> SizedCells<'c> {
+        let mut ends = Vec::with_capacity(cells.len() + 1);
+        ends.push(0);
+        for cell in cells.iter() {
+            ends.push(ends[ends.len() - 1] + cell.to_be_bytes().len() + 2);
+        }
+        SizedCells { cells, ends }
+    }
+
+    fn length(&self, start: usize, end: usize) -> usize {
+        self.ends[end] - self.ends[start]
+    }
+}
+
+// The pages written so far, page_number - 1 indexes them.
+struct PageWriter<'a> {
+    options: &'a WriterOptions,
+    usable_size: usize,
+    pages: Vec<Vec<u8>>,
+}
+
+impl DatabaseWriter {
+    pub fn new(options: WriterOptions) -> Result<DatabaseWriter, MyError> {
+        if !options.page_size.is_power_of_two() || options.page_size < 512 || options.page_size > 65536 {
+            return Err(MyError::new(ErrorKind::InvalidPageSize(options.page_size)));
+        }
+        // the smallest usable size sqlite accepts
+        if options.page_size - (options.reserved_bytes as usize) < 480 {
+            return Err(MyError::new(ErrorKind::InvalidPageSize(options.page_size)));
+        }
+        if let TextEncoding::Unknown(number) = options.text_encoding {
+            return Err(MyError::new(ErrorKind::Unsupported(format!("the text encoding {}", number))));
+        }
+        Ok(DatabaseWriter {
+            options,
+            tables: Vec::new(),
+            indexes: Vec::new(),
+            entries: Vec::new(),
+        })
+    }
+
+    /// Add a rowid table, `rows` pairs the rowid with the record columns. The INTEGER PRIMARY KEY
+    /// column is stored as NULL, sqlite reads the rowid in its place. The indexes of its UNIQUE and
+    /// PRIMARY KEY constraints are added too, those which can't be built are left out and returned
+    /// with the reason.
+    pub fn add_table(&mut self, name: &str, sql: &str, rows: Vec<(i64, Vec<Column>)>) -> Result<Vec<(String, MyError)>, MyError> {
+        if is_without_rowid(sql) {
+            return Err(MyError::new(ErrorKind::Unsupported("rowids for a WITHOUT ROWID table".to_string())));
+        }
+        let mut rows = rows;
+        rows.sort_by_key(|(row_id, _)| *row_id);
+        if let Some(pair) = rows.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(MyError::new(ErrorKind::DuplicateRowId(pair[0].0)));
+        }
+        self.push_table(name, sql, rows, None)
+    }
+
+    /// Add a WITHOUT ROWID table, `rows` holds the columns in the order they are declared. The
+    /// rows are stored in an index btree sorted on the PRIMARY KEY, which no two rows may share.
+    /// The indexes of its UNIQUE constraints are added like for `add_table`.
+    pub fn add_without_rowid_table(&mut self, name: &str, sql: &str, rows: Vec<Vec<Column>>) -> Result<Vec<(String, MyError)>, MyError> {
+        if !is_without_rowid(sql) {
+            return Err(MyError::new(ErrorKind::Unsupported("a rowid table without rowids".to_string())));
+        }
+        let primary_key = parse_primary_key(sql)?.ok_or_else(|| MyError::new(ErrorKind::SchemaParseError(sql.to_string())))?;
+        let rows = rows.into_iter().enumerate().map(|(idx, columns)| (idx as i64 + 1, columns)).collect();
+        self.push_table(name, sql, rows, Some(primary_key))
+    }
+
+    fn push_table(&mut self, name: &str, sql: &str, rows: Vec<(i64, Vec<Column>)>, primary_key: Option<Vec<IndexColumn>>) -> Result<Vec<(String, MyError)>, MyError> {
+        let table_columns = parse_create_table(sql)?;
+        let constraints = parse_unique_constraints(sql)?;
+        let mut left_out = Vec::new();
+        for (idx, columns) in constraints.into_iter().enumerate() {
+            // the PRIMARY KEY of a WITHOUT ROWID table is the table btree itself
+            if primary_key.as_ref() == Some(&columns) {
+                continue;
+            }
+            let index_name = format!("sqlite_autoindex_{}_{}", name, idx + 1);
+            if let Err(e) = check_index_columns(&columns, &table_columns, sql) {
+                left_out.push((index_name, e));
+                continue;
+            }
+            self.indexes.push(IndexSpec {
+                name: index_name,
+                table_name: name.to_string(),
+                sql: None,
+                columns,
+            });
+        }
+        self.tables.push(TableSpec {
+            name: name.to_string(),
+            sql: sql.to_string(),
+            rows,
+            primary_key,
+        });
+        Ok(left_out)
+    }
+
+    /// Add an index on a table added before, its entries are made out of the rows of the table.
+    /// The keys sort with the BINARY, NOCASE and RTRIM collations, other collations are refused.
+    pub fn add_index(&mut self, name: &str, table_name: &str, sql: &str) -> Result<(), MyError> {
+        let mut columns = parse_create_index(sql)?;
+        let table = self.tables.iter()
+            .find(|table| table.name.eq_ignore_ascii_case(table_name))
+            .ok_or_else(|| MyError::new(ErrorKind::TableNotFound(table_name.to_string())))?;
+        let collations = declared_collations(&table.sql)?;
+        for column in columns.iter_mut().filter(|column| column.collation.is_none()) {
+            column.collation = collations.iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&column.name))
+                .and_then(|(_, collation)| collation.clone());
+        }
+        check_index_columns(&columns, &parse_create_table(&table.sql)?, sql)?;
+        self.indexes.push(IndexSpec {
+            name: name.to_string(),
+            table_name: table.name.clone(),
+            sql: Some(sql.to_string()),
+            columns,
+        });
+        Ok(())
+    }
+
+    /// Add a view, a trigger or a virtual table, the schema entries without a btree.
+    pub fn add_schema_entry(&mut self, entry_type: &str, name: &str, table_name: &str, sql: &str) -> Result<(), MyError> {
+        if !matches!(entry_type, "view" | "trigger" | "table") {
+            return Err(MyError::new(ErrorKind::Unsupported(format!("a schema entry of type {}", entry_type))));
+        }
+        self.entries.push(EntrySpec {
+            entry_type: entry_type.to_string(),
+            name: name.to_string(),
+            table_name: table_name.to_string(),
+            sql: sql.to_string(),
+        });
+        Ok(())
+    }
+
+    // Index records hold the indexed columns followed by the rowid, or by the PRIMARY KEY columns
+    // they don't hold already for a WITHOUT ROWID table, sorted the way sqlite compares them.
+    fn index_entries(&self, index: &IndexSpec) -> Result<Vec<Record>, MyError> {
+        let table = self.tables.iter()
+            .find(|table| table.name == index.table_name)
+            .ok_or_else(|| MyError::new(ErrorKind::TableNotFound(index.table_name.clone())))?;
+        let table_columns = parse_create_table(&table.sql)?;
+        let position_of = |name: &str| table_columns.iter().position(|table_column| table_column.name.eq_ignore_ascii_case(name));
+        let mut columns = index.columns.clone();
+        if let Some(primary_key) = &table.primary_key {
+            for key_column in primary_key.iter() {
+                if !columns.iter().any(|column| column.name.eq_ignore_ascii_case(&key_column.name)) {
+                    columns.push(key_column.clone());
+                }
+            }
+        }
+        let positions: Vec<usize> = columns.iter()
+            .filter_map(|column| position_of(&column.name))
+            .collect();
+
+        let mut entries: Vec<Record> = table.rows.iter()
+            .map(|(row_id, row)| {
+                let mut key: Vec<Column> = positions.iter()
+                    .map(|&position| match row.get(position) {
+                        // a WITHOUT ROWID table keeps its INTEGER PRIMARY KEY in the record
+                        Some(Column::NULL) | None if table_columns[position].is_rowid_alias && table.primary_key.is_none() => Column::integer(*row_id),
+                        Some(column) => column.clone(),
+                        None => Column::NULL,
+                    })
+                    .collect();
+                if table.primary_key.is_none() {
+                    key.push(Column::integer(*row_id));
+                }
+                Record::new(key)
+            })
+            .collect();
+        let text_encoding = &self.options.text_encoding;
+        entries.sort_by(|a, b| compare_keys(&a.columns, &b.columns, &columns, text_encoding));
+        Ok(entries)
+    }
+
+    // The records of a WITHOUT ROWID table in key order, the PRIMARY KEY columns first.
+    fn without_rowid_records(&self, table: &TableSpec, primary_key: &[IndexColumn]) -> Result<Vec<Record>, MyError> {
+        let order = without_rowid_column_order(&table.sql)?;
+        let mut records: Vec<Record> = table.rows.iter()
+            .map(|(_, row)| Record::new(order.iter().map(|&position| row.get(position).cloned().unwrap_or(Column::NULL)).collect()))
+            .collect();
+        let text_encoding = &self.options.text_encoding;
+        let key_length = order.len().min(primary_key.len());
+        records.sort_by(|a, b| compare_keys(&a.columns[..key_length], &b.columns[..key_length], primary_key, text_encoding));
+        if records.windows(2).any(|pair| compare_keys(&pair[0].columns[..key_length], &pair[1].columns[..key_length], primary_key, text_encoding) == Ordering::Equal) {
+            return Err(MyError::new(ErrorKind::DuplicateKey(table.name.clone())));
+        }
+        Ok(records)
+    }
+
+    /// The bytes of the whole file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MyError> {
+        let usable_size = self.options.page_size - self.options.reserved_bytes as usize;
+        let mut writer = PageWriter {
+            options: &self.options,
+            usable_size,
+            // page 1 is written last, once the schema is known
+            pages: vec![Vec::new()],
+        };
+
+        let mut schema_rows: Vec<Vec<Column>> = Vec::new();
+        let schema_row = |entry_type: &str, name: &str, table_name: &str, root_page: u32, sql: Option<&str>| vec![
+            Column::STRING(entry_type.to_string()),
+            Column::STRING(name.to_string()),
+            Column::STRING(table_name.to_string()),
+            Column::integer(root_page as i64),
+            sql.map_or(Column::NULL, |sql| Column::STRING(sql.to_string())),
+        ];
+        for table in self.tables.iter() {
+            let root_page = match &table.primary_key {
+                Some(primary_key) => {
+                    let cells = self.without_rowid_records(table, primary_key)?
+                        .into_iter()
+                        .map(|record| writer.index_cell(record))
+                        .collect::<Result<Vec<Cell>, MyError>>()?;
+                    writer.write_index_btree(cells)?
+                },
+                None => {
+                    let cells = table.rows.iter()
+                        .map(|(row_id, columns)| writer.table_leaf_cell(*row_id, Record::new(columns.clone())))
+                        .collect::<Result<Vec<BtreeCell>, MyError>>()?;
+                    writer.write_table_btree(cells, None)?
+                },
+            };
+            schema_rows.push(schema_row("table", &table.name, &table.name, root_page, Some(&table.sql)));
+        }
+        for index in self.indexes.iter() {
+            let cells = self.index_entries(index)?
+                .into_iter()
+                .map(|record| writer.index_cell(record))
+                .collect::<Result<Vec<Cell>, MyError>>()?;
+            let root_page = writer.write_index_btree(cells)?;
+            schema_rows.push(schema_row("index", &index.name, &index.table_name, root_page, index.sql.as_deref()));
+        }
+        for entry in self.entries.iter() {
+            schema_rows.push(schema_row(&entry.entry_type, &entry.name, &entry.table_name, 0, Some(&entry.sql)));
+        }
+
+        let cells = schema_rows.into_iter()
+            .enumerate()
+            .map(|(idx, columns)| writer.table_leaf_cell(idx as i64 + 1, Record::new(columns)))
+            .collect::<Result<Vec<BtreeCell>, MyError>>()?;
+        writer.write_table_btree(cells, Some(SQLITE_SCHEMA_ROOT_PAGE))?;
+
+        let page_count = writer.pages.len();
+        let header = self.database_header(page_count as u32).to_be_bytes();
+        writer.pages[0][..DATABASE_HEADER_LENGTH].copy_from_slice(&header);
+        Ok(writer.pages.concat())
+    }
+
+    pub fn write_to(&self, file_name: &str) -> Result<(), MyError> {
+        fs::write(file_name, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    fn database_header(&self, page_count: u32) -> DatabaseHeader {
+        DatabaseHeader {
+            header_string: SQLITE_DB_HEADER_STRING.to_string(),
+            // 65536 doesn't fit, 1 stands for it
+            page_size: if self.options.page_size == 65536 { 1 } else { self.options.page_size as u16 },
+            file_format_read_version: FileFormatVersion::Legacy,
+            file_format_write_version: FileFormatVersion::Legacy,
+            bytes_at_unused_page_end: self.options.reserved_bytes,
+            max_embedded_payload_fraction: 64,
+            min_embedded_payload_fraction: 32,
+            leaf_payload_fraction: 32,
+            file_change_count: 1,
+            in_header_database_size: page_count,
+            schema_cookie: 1,
+            schema_format_number: SchemaFormatNumber::Four,
+            text_encoding: self.options.text_encoding,
+            user_version: self.options.user_version,
+            application_id: self.options.application_id,
+            version_valid_for_number: 1,
+            sqlite_version_number: SQLITE_VERSION_NUMBER,
+            ..Default::default()
+        }
+    }
+}
+
+impl<'a> PageWriter<'a> {
+    fn allocate(&mut self) -> u32 {
+        // the lock-byte page is left as zeros
+        if self.pages.len() as u32 + 1 == lock_byte_page_number(self.options.page_size) {
+            self.pages.push(vec![0; self.options.page_size]);
+        }
+        self.pages.push(Vec::new());
+        self.pages.len() as u32
+    }
+
+    fn set_page(&mut self, page_number: u32, mut bytes: Vec<u8>) {
+        bytes.resize(self.options.page_size, 0);
+        self.pages[page_number as usize - 1] = bytes;
+    }
+
+    // The part of the payload kept in the cell, the rest goes to a chain of overflow pages.
+    fn spill(&mut self, payload: Vec<u8>, max_local: usize) -> (Vec<u8>, Option<u32>) {
+        let u = self.usable_size;
+        let p = payload.len();
+        if p <= max_local {
+            return (payload, None);
+        }
+        let m = ((u - 12) * 32 / 255) - 23;
+        let k = m + ((p - m) % (u - 4));
+        let local_length = if k <= max_local { k } else { m };
+
+        let chunks: Vec<&[u8]> = payload[local_length..].chunks(u - 4).collect();
+        let page_numbers: Vec<u32> = chunks.iter().map(|_| self.allocate()).collect();
+        for (idx, chunk) in chunks.iter().enumerate() {
+            let next_page_number = page_numbers.get(idx + 1).copied().unwrap_or(0);
+            let mut bytes = next_page_number.to_be_bytes().to_vec();
+            bytes.extend_from_slice(chunk);
+            self.set_page(page_numbers[idx], bytes);
+        }
+        (payload[..local_length].to_vec(), page_numbers.first().copied())
+    }
+
+    fn table_leaf_cell(&mut self, row_id: i64, record: Record) -> Result<BtreeCell, MyError> {
+        let payload = record.to_be_bytes_encoded(&self.options.text_encoding);
+        let payload_length = payload.len();
+        let (local_payload, overflow_page_number) = self.spill(payload, self.usable_size - 35);
+        Ok(BtreeCell {
+            cell: Cell {
+                page_type: PageType::TableLeafBtreePage,
+                row_id: Some(row_id),
+                payload_length: Some(payload_length as u64),
+                local_payload: Some(local_payload),
+                overflow_page_number,
+                ..Default::default()
+            },
+            row_id,
+        })
+    }
+
+    // a leaf cell, it only needs a left child pointer when it moves up to an interior page
+    fn index_cell(&mut self, record: Record) -> Result<Cell, MyError> {
+        let payload = record.to_be_bytes_encoded(&self.options.text_encoding);
+        let payload_length = payload.len();
+        let (local_payload, overflow_page_number) = self.spill(payload, ((self.usable_size - 12) * 64 / 255) - 23);
+        Ok(Cell {
+            page_type: PageType::IndexLeafBtreePage,
+            payload_length: Some(payload_length as u64),
+            local_payload: Some(local_payload),
+            overflow_page_number,
+            ..Default::default()
+        })
+    }
+
+    fn header_start_index(page_number: Option<u32>) -> usize {
+        if page_number == Some(1) { DATABASE_HEADER_LENGTH } else { 0 }
+    }
+
+    // Whether cells start..end fit on one page, interior pages have the longer header.
+    fn fits(&self, page_type: PageType, cells: &SizedCells, start: usize, end: usize, header_start_index: usize) -> bool {
+        if end - start > self.options.max_cells_per_page.unwrap_or(usize::MAX) {
+            return false;
+        }
+        let header_length = match page_type {
+            PageType::IndexInteriorBtreePage | PageType::TableInteriorBtreePage => 12,
+            _ => 8,
+        };
+        header_start_index + header_length + cells.length(start, end) <= self.usable_size
+    }
+
+    fn write_page(&mut self, page_type: PageType, cells: &[&Cell], right_most_pointer: Option<u32>, page_number: Option<u32>) -> Result<u32, MyError> {
+        let page_number = match page_number {
+            Some(page_number) => page_number,
+            None => self.allocate(),
+        };
+        let mut builder = PageBuilder::new(page_type, self.usable_size, Self::header_start_index(Some(page_number)));
+        builder.set_right_most_pointer(right_most_pointer);
+        for cell in cells.iter() {
+            builder.push_cell(cell)?;
+        }
+        self.set_page(page_number, builder.build());
+        Ok(page_number)
+    }
+
+    // Group consecutive items into pages greedily, `fits(start, end)` tells whether items
+    // start..end make a page. Every group but the last is closed at the first item not
+    // fitting, a group needs `min_items` and the last one borrows from the one before.
+    fn split<F>(item_count: usize, min_items: usize, fits: F) -> Vec<(usize, usize)>
+    where F: Fn(usize, usize) -> bool {
+        let mut groups: Vec<(usize, usize)> = Vec::new();
+        let mut start = 0;
+        while start < item_count {
+            let mut end = start + min_items.min(item_count - start);
+            while end < item_count && fits(start, end + 1) {
+                end += 1;
+            }
+            groups.push((start, end));
+            start = end;
+        }
+        if groups.len() == 1 && item_count >= 2 * min_items {
+            // a level which didn't fit in its root needs two pages at least
+            groups = vec![(0, item_count / 2), (item_count / 2, item_count)];
+        }
+        let len = groups.len();
+        if len >= 2 {
+            while groups[len - 1].1 - groups[len - 1].0 < min_items && groups[len - 2].1 - groups[len - 2].0 > min_items {
+                groups[len - 2].1 -= 1;
+                groups[len - 1].0 -= 1;
+            }
+        }
+        groups
+    }
+
+    /// The table btree holding `cells` in rowid order, returns its root page.
+    fn write_table_btree(&mut self, cells: Vec<BtreeCell>, root_page: Option<u32>) -> Result<u32, MyError> {
+        let root_header_start_index = Self::header_start_index(root_page);
+        let leaf_cells = SizedCells::new(cells.iter().map(|cell| &cell.cell).collect());
+        if self.fits(PageType::TableLeafBtreePage, &leaf_cells, 0, cells.len(), root_header_start_index) {
+            return self.write_page(PageType::TableLeafBtreePage, &leaf_cells.cells, None, root_page);
+        }
+
+        // children with the largest rowid of their subtree
+        let mut children: Vec<(u32, i64)> = Vec::new();
+        for (start, end) in Self::split(cells.len(), 1, |start, end| self.fits(PageType::TableLeafBtreePage, &leaf_cells, start, end, 0)) {
+            let page_number = self.write_page(PageType::TableLeafBtreePage, &leaf_cells.cells[start..end], None, None)?;
+            children.push((page_number, cells[end - 1].row_id));
+        }
+
+        loop {
+            let interior_cells: Vec<Cell> = children.iter()
+                .map(|(page_number, row_id)| Cell {
+                    page_type: PageType::TableInteriorBtreePage,
+                    left_child_page_number: Some(*page_number),
+                    row_id: Some(*row_id),
+                    ..Default::default()
+                })
+                .collect();
+            let interior_cells = SizedCells::new(interior_cells.iter().collect());
+            let last = children.len() - 1;
+            if self.fits(PageType::TableInteriorBtreePage, &interior_cells, 0, last, root_header_start_index) {
+                return self.write_page(PageType::TableInteriorBtreePage, &interior_cells.cells[..last], Some(children[last].0), root_page);
+            }
+
+            // the last child of a group is its right most pointer
+            let mut parents: Vec<(u32, i64)> = Vec::new();
+            for (start, end) in Self::split(children.len(), 2, |start, end| self.fits(PageType::TableInteriorBtreePage, &interior_cells, start, end - 1, 0)) {
+                let page_number = self.write_page(PageType::TableInteriorBtreePage, &interior_cells.cells[start..end - 1], Some(children[end - 1].0), None)?;
+                parents.push((page_number, children[end - 1].1));
+            }
+            children = parents;
+        }
+    }
+
+    /// The index btree holding `cells` in key order, returns its root page. Unlike a table btree
+    /// the keys dividing two pages move up to their parent.
+    fn write_index_btree(&mut self, cells: Vec<Cell>) -> Result<u32, MyError> {
+        let leaf_cells = SizedCells::new(cells.iter().collect());
+        if self.fits(PageType::IndexLeafBtreePage, &leaf_cells, 0, cells.len(), 0) {
+            return self.write_page(PageType::IndexLeafBtreePage, &leaf_cells.cells, None, None);
+        }
+
+        // a group takes the key after it as its divider, the last group has none
+        let groups = Self::split(cells.len(), 2, |start, end| {
+            let leaf_end = if end == cells.len() { end } else { end - 1 };
+            self.fits(PageType::IndexLeafBtreePage, &leaf_cells, start, leaf_end, 0)
+        });
+        let mut children: Vec<u32> = Vec::new();
+        let mut dividers: Vec<Cell> = Vec::new();
+        let last_group = groups.len() - 1;
+        for (idx, (start, end)) in groups.into_iter().enumerate() {
+            let end = if idx == last_group { end } else { end - 1 };
+            children.push(self.write_page(PageType::IndexLeafBtreePage, &leaf_cells.cells[start..end], None, None)?);
+            if idx != last_group {
+                dividers.push(Cell {
+                    page_type: PageType::IndexInteriorBtreePage,
+                    ..cells[end].clone()
+                });
+            }
+        }
+
+        loop {
+            let interior_cells: Vec<Cell> = children.iter()
+                .zip(dividers.iter())
+                .map(|(page_number, divider)| Cell {
+                    left_child_page_number: Some(*page_number),
+                    ..divider.clone()
+                })
+                .collect();
+            let interior_cells = SizedCells::new(interior_cells.iter().collect());
+            let last = children.len() - 1;
+            if self.fits(PageType::IndexInteriorBtreePage, &interior_cells, 0, last, 0) {
+                return self.write_page(PageType::IndexInteriorBtreePage, &interior_cells.cells, Some(children[last]), None);
+            }
+
+            // children start..end with the dividers between them, the divider after the group moves up
+            let groups = Self::split(children.len(), 2, |start, end| self.fits(PageType::IndexInteriorBtreePage, &interior_cells, start, end - 1, 0));
+            let mut parents: Vec<u32> = Vec::new();
+            let mut parent_dividers: Vec<Cell> = Vec::new();
+            let last_group = groups.len() - 1;
+            for (idx, (start, end)) in groups.into_iter().enumerate() {
+                let page_number = self.write_page(PageType::IndexInteriorBtreePage, &interior_cells.cells[start..end - 1], Some(children[end - 1]), None)?;
+                parents.push(page_number);
+                if idx != last_group {
+                    parent_dividers.push(dividers[end - 1].clone());
+                }
+            }
+            children = parents;
+            dividers = parent_dividers;
+        }
+    }
+}
+
+// The collations sqlite has built in.
+#[derive(Clone, Copy)]
+enum Collation {
+    Binary,
+    NoCase,
+    RTrim,
+}
+
+impl Collation {
+    fn of(column: &IndexColumn) -> Option<Collation> {
+        match column.collation.as_deref() {
+            None | Some("BINARY") => Some(Collation::Binary),
+            Some("NOCASE") => Some(Collation::NoCase),
+            Some("RTRIM") => Some(Collation::RTrim),
+            Some(_) => None,
+        }
+    }
+}
+
+// The columns must belong to the table and sort with a collation sqlite has built in.
+fn check_index_columns(columns: &[IndexColumn], table_columns: &[TableColumn], sql: &str) -> Result<(), MyError> {
+    if let Some(column) = columns.iter().find(|column| Collation::of(column).is_none()) {
+        return Err(MyError::new(ErrorKind::Unsupported(format!("the collation {} of the index column {}", column.collation.as_deref().unwrap_or_default(), column.name))));
+    }
+    if columns.iter().any(|column| !table_columns.iter().any(|table_column| table_column.name.eq_ignore_ascii_case(&column.name))) {
+        return Err(MyError::new(ErrorKind::SchemaParseError(sql.to_string())));
+    }
+    Ok(())
+}
+
+// NULL sorts first, then numbers, text and blobs. BINARY compares text with memcmp in the
+// database encoding, NOCASE and RTRIM compare its UTF-8 bytes, the ASCII letters folded to
+// lower case or the trailing spaces left out.
+fn compare_columns(a: &Column, b: &Column, collation: Collation, text_encoding: &TextEncoding) -> Ordering {
+    fn class(column: &Column) -> u8 {
+        match column {
+            Column::NULL => 0,
+            Column::STRING(_) => 2,
+            Column::BLOB(_) => 3,
+            _ => 1,
+        }
+    }
+    match (a, b) {
+        (Column::STRING(a), Column::STRING(b)) => match (collation, text_encoding) {
+            (Collation::NoCase, _) => a.bytes().map(|c| c.to_ascii_lowercase()).cmp(b.bytes().map(|c| c.to_ascii_lowercase())),
+            (Collation::RTrim, _) => a.trim_end_matches(' ').as_bytes().cmp(b.trim_end_matches(' ').as_bytes()),
+            (Collation::Binary, TextEncoding::UTF8) | (Collation::Binary, TextEncoding::Unknown(_)) => a.as_bytes().cmp(b.as_bytes()),
+            (Collation::Binary, TextEncoding::UTF16le) => a.encode_utf16().flat_map(|u| u.to_le_bytes()).cmp(b.encode_utf16().flat_map(|u| u.to_le_bytes())),
+            (Collation::Binary, TextEncoding::UTF16be) => a.encode_utf16().flat_map(|u| u.to_be_bytes()).cmp(b.encode_utf16().flat_map(|u| u.to_be_bytes())),
+        },
+        (Column::BLOB(a), Column::BLOB(b)) => a.cmp(b),
+        (Column::F64(_), _) | (_, Column::F64(_)) if class(a) == 1 && class(b) == 1 => {
+            let as_f64 = |column: &Column| match column {
+                Column::F64(v) => *v,
+                column => column.as_i64().unwrap_or(0) as f64,
+            };
+            as_f64(a).partial_cmp(&as_f64(b)).unwrap_or(Ordering::Equal)
+        },
+        _ if class(a) == 1 && class(b) == 1 => a.as_i64().cmp(&b.as_i64()),
+        _ => class(a).cmp(&class(b)),
+    }
+}
+
+// The key columns sort in the order of their index column, the rowid after them ascending.
+fn compare_keys(a: &[Column], b: &[Column], columns: &[IndexColumn], text_encoding: &TextEncoding) -> Ordering {
+    a.iter()
+        .zip(b.iter())
+        .enumerate()
+        .map(|(idx, (a, b))| match columns.get(idx) {
+            Some(column) => {
+                let ordering = compare_columns(a, b, Collation::of(column).unwrap_or(Collation::Binary), text_encoding);
+                if column.descending { ordering.reverse() } else { ordering }
+            },
+            None => compare_columns(a, b, Collation::Binary, text_encoding),
+        })
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
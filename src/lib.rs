@@ -2,3 +2,5 @@
 
 pub mod components;
 pub mod utils;
+pub mod server;
+pub mod tui;
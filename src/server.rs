@@ -0,0 +1,352 @@
+pub mod cache;
+pub mod registry;
+pub mod upload;
+pub mod watch;
+
+use std::sync::{Arc, Mutex};
+
+use actix_multipart::Multipart;
+use actix_web::{get, post, web, App as WebApp, HttpResponse, HttpServer, Responder, ResponseError};
+use actix_web::error::BlockingError;
+use actix_files as fs;
+use futures::StreamExt;
+use lazy_static::*;
+use serde::Serialize;
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use crate::components::database::Database;
+use crate::components::graph::BtreeGraph;
+use crate::components::page::Page;
+use crate::server::cache::{DatabaseCache, DEFAULT_PAGE_CACHE_BYTES};
+use crate::server::registry::DatabaseFile;
+use crate::server::registry::DatabaseRegistry;
+use crate::server::upload::{self as workspace, UploadWorkspace};
+use crate::utils::error::HttpErrorKind;
+use crate::utils::error::HttpError;
+use crate::utils::error::ErrorKind;
+use crate::utils::error::MyError;
+
+lazy_static!{
+    static ref SQLITE_DATABASES: Mutex<DatabaseRegistry> = Mutex::new(DatabaseRegistry::new());
+    static ref UPLOAD_WORKSPACE: Mutex<Option<UploadWorkspace>> = Mutex::new(None);
+    static ref DATABASE_CACHE: Mutex<DatabaseCache> = Mutex::new(DatabaseCache::new(DEFAULT_PAGE_CACHE_BYTES));
+}
+
+/// Bound the parsed pages kept in memory, the pages already cached are dropped.
+pub fn set_page_cache_capacity(capacity: usize) {
+    *DATABASE_CACHE.lock().unwrap() = DatabaseCache::new(capacity);
+}
+
+/// Register database files, or directories of them, before serving.
+pub fn register_databases(paths: &[String]) -> Result<Vec<DatabaseFile>, MyError> {
+    let mut registry = SQLITE_DATABASES.lock().unwrap();
+    let mut registered = Vec::new();
+    for path in paths.iter() {
+        registered.extend(registry.register_path(path)?);
+    }
+    Ok(registered)
+}
+
+/// Accept uploaded database files into `upload_workspace`, uploads are refused until this is called.
+pub fn enable_uploads(upload_workspace: UploadWorkspace) {
+    *UPLOAD_WORKSPACE.lock().unwrap() = Some(upload_workspace);
+}
+
+// Unregister the expired uploads and remove their files from the workspace.
+fn purge_expired_uploads() {
+    let expired = SQLITE_DATABASES.lock().unwrap().remove_expired(workspace::now());
+    for database_file in expired.iter() {
+        DATABASE_CACHE.lock().unwrap().remove(&database_file.id);
+        let _ = std::fs::remove_file(&database_file.path);
+    }
+}
+
+fn error_response(error_kind: HttpErrorKind) -> HttpResponse {
+    let http_error = HttpError::new(error_kind);
+    let r = serde_json::to_string(&http_error).unwrap();
+    HttpResponse::build(http_error.status_code()).body(r)
+}
+
+fn json_response<T: Serialize>(result: Result<T, MyError>) -> HttpResponse {
+    match result {
+        Ok(value) => HttpResponse::Ok().body(serde_json::to_string(&value).unwrap()),
+        Err(e) => error_response(HttpErrorKind::InternalError(e.to_string())),
+    }
+}
+
+fn database_path(id: &str) -> Result<String, HttpResponse> {
+    match SQLITE_DATABASES.lock().unwrap().get(id) {
+        Some(database_file) => Ok(database_file.path.clone()),
+        None => Err(error_response(HttpErrorKind::DatabaseNotFound(id.to_string()))),
+    }
+}
+
+// The database stays open in the cache until its file changes.
+fn open_database(id: &str) -> Result<Arc<Database>, HttpResponse> {
+    let path = database_path(id)?;
+    cache::open_database(&DATABASE_CACHE, id, &path)
+        .map_err(|e| error_response(HttpErrorKind::InternalError(e.to_string())))
+}
+
+// Look the database up by id and answer with the json of `f`.
+fn with_database<T, F>(id: &str, f: F) -> HttpResponse
+where T: Serialize, F: FnOnce(&Database) -> Result<T, MyError> {
+    match open_database(id) {
+        Ok(db) => json_response(f(&db)),
+        Err(response) => response,
+    }
+}
+
+fn upload_error_response(e: MyError) -> HttpResponse {
+    match e.kind() {
+        ErrorKind::UploadTooLarge(max_size) => error_response(HttpErrorKind::PayloadTooLarge(*max_size)),
+        ErrorKind::IoError(_) => error_response(HttpErrorKind::InternalError(e.to_string())),
+        _ => error_response(HttpErrorKind::BadClientData(e.to_string())),
+    }
+}
+
+#[get("/databases")]
+async fn databases() -> impl Responder {
+    purge_expired_uploads();
+    let databases = SQLITE_DATABASES.lock().unwrap().list().to_vec();
+    json_response(Ok(databases))
+}
+
+// Run the file IO of an upload on the blocking thread pool, the workers keep serving meanwhile.
+async fn block_upload<T, F>(f: F) -> Result<T, MyError>
+where T: Send + 'static, F: FnOnce() -> Result<T, MyError> + Send + 'static {
+    web::block(f).await.map_err(|e| match e {
+        BlockingError::Error(e) => e,
+        BlockingError::Canceled => MyError::new(ErrorKind::IoError("the upload was canceled".to_string())),
+    })
+}
+
+// The first file of the multipart form is stored in the workspace and registered until it expires.
+#[post("/databases/upload")]
+async fn upload_database(mut payload: Multipart) -> impl Responder {
+    let upload_workspace = match UPLOAD_WORKSPACE.lock().unwrap().clone() {
+        Some(upload_workspace) => upload_workspace,
+        None => return error_response(HttpErrorKind::BadClientData("uploads are disabled, the server is started with --allow-upload to accept them".to_string())),
+    };
+    if let Err(e) = block_upload(|| {
+        purge_expired_uploads();
+        Ok(())
+    }).await {
+        return upload_error_response(e);
+    }
+
+    while let Some(item) = payload.next().await {
+        let mut field = match item {
+            Ok(field) => field,
+            Err(e) => return error_response(HttpErrorKind::BadClientData(e.to_string())),
+        };
+        let name = match field.content_disposition().and_then(|cd| cd.get_filename().map(workspace::upload_name)) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        let workspace = upload_workspace.clone();
+        let mut upload_file = match block_upload(move || workspace.create_file()).await {
+            Ok(upload_file) => upload_file,
+            Err(e) => return upload_error_response(e),
+        };
+        while let Some(chunk) = field.next().await {
+            let bytes = match chunk {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    let _ = block_upload(move || {
+                        upload_file.discard();
+                        Ok(())
+                    }).await;
+                    return error_response(HttpErrorKind::BadClientData(e.to_string()));
+                },
+            };
+            // the file travels to the thread pool and back with every chunk, it is discarded there on errors
+            upload_file = match block_upload(move || match upload_file.write_chunk(&bytes) {
+                Ok(()) => Ok(upload_file),
+                Err(e) => {
+                    upload_file.discard();
+                    Err(e)
+                },
+            }).await {
+                Ok(upload_file) => upload_file,
+                Err(e) => return upload_error_response(e),
+            };
+        }
+
+        let expires_at = upload_workspace.expires_at();
+        let registered = block_upload(move || {
+            let path = upload_file.finish()?.to_string_lossy().to_string();
+            let registered = SQLITE_DATABASES.lock().unwrap().register_named_file(&path, &name, Some(expires_at));
+            if registered.is_err() {
+                let _ = std::fs::remove_file(&path);
+            }
+            registered
+        }).await;
+        return match registered {
+            Ok(database_file) => json_response(Ok(database_file)),
+            Err(e) => upload_error_response(e),
+        };
+    }
+    error_response(HttpErrorKind::BadClientData("no file in the upload".to_string()))
+}
+
+#[get("/db/{id}/btree_hierachy")]
+async fn btree_hierachy(web::Path(id): web::Path<String>) -> impl Responder {
+    let db = match open_database(&id) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
+    if let Some(page_parents) = DATABASE_CACHE.lock().unwrap().cached_page_parents(&id) {
+        return json_response(Ok(page_parents.as_ref()));
+    }
+
+    // walked without holding the cache, other requests keep being served meanwhile
+    match db.page_parents() {
+        Ok(page_parents) => {
+            let page_parents = Arc::new(page_parents);
+            DATABASE_CACHE.lock().unwrap().set_page_parents(&id, &db, page_parents.clone());
+            json_response(Ok(page_parents.as_ref()))
+        },
+        Err(e) => json_response::<Vec<i32>>(Err(e)),
+    }
+}
+
+#[get("/db/{id}/btree_page_num")]
+async fn btree_page_num(web::Path(id): web::Path<String>) -> impl Responder {
+    with_database(&id, |db| Ok(json!({
+        "page_num": db.page_count()
+    })))
+}
+
+#[get("/db/{id}/database_header")]
+async fn fetch_database_header(web::Path(id): web::Path<String>) -> impl Responder {
+    // read from the file bytes, the header of a file that doesn't open is still shown with its issues
+    let path = match database_path(&id) {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    json_response(Database::read_header(&path).map(|header| {
+        let mut value = serde_json::to_value(&header).unwrap();
+        value["issues"] = serde_json::to_value(header.validate()).unwrap();
+        value["size"] = serde_json::to_value(open_database(&id).ok().map(|db| db.size())).unwrap();
+        value
+    }))
+}
+
+#[get("/db/{id}/btree_page/{page_index}")]
+async fn btree_page(web::Path((id, page_index)): web::Path<(String, usize)>) -> impl Responder {
+    let db = match open_database(&id) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
+
+    if page_index >= db.page_count() as usize {
+        return error_response(HttpErrorKind::PageIndexError(page_index));
+    }
+
+    match cache::read_page(&DATABASE_CACHE, &id, &db, page_index as u32 + 1) {
+        Ok(page) => json_response(Ok(page.as_ref())),
+        Err(e) => json_response::<Page>(Err(e)),
+    }
+}
+
+#[get("/db/{id}/diff/{other_id}")]
+async fn diff(web::Path((id, other_id)): web::Path<(String, String)>) -> impl Responder {
+    let other = match open_database(&other_id) {
+        Ok(other) => other,
+        Err(response) => return response,
+    };
+    with_database(&id, |db| db.diff(&other))
+}
+
+#[derive(Deserialize)]
+struct TableDiffQuery {
+    format: Option<String>,
+}
+
+#[get("/db/{id}/diff/{other_id}/table/{table}")]
+async fn table_diff(web::Path((id, other_id, table)): web::Path<(String, String, String)>, query: web::Query<TableDiffQuery>) -> impl Responder {
+    let (db, other) = match (open_database(&id), open_database(&other_id)) {
+        (Ok(db), Ok(other)) => (db, other),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    let table_diff = match db.table_diff(&other, &table) {
+        Ok(table_diff) => table_diff,
+        Err(e) => return error_response(HttpErrorKind::BadClientData(e.to_string())),
+    };
+    match query.format.as_deref() {
+        Some("sql") => HttpResponse::Ok().content_type("application/sql").body(table_diff.to_sql()),
+        _ => json_response(Ok(table_diff)),
+    }
+}
+
+// Server-sent events naming the pages modified by every transaction on the file or its -wal.
+#[get("/db/{id}/events")]
+async fn events(web::Path(id): web::Path<String>) -> impl Responder {
+    let path = match database_path(&id) {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    match watch::subscribe(&id, &path) {
+        Ok(receiver) => HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .streaming(receiver.map(Ok::<_, actix_web::Error>)),
+        Err(e) => error_response(HttpErrorKind::InternalError(e.to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+struct GraphQuery {
+    format: Option<String>,
+}
+
+#[get("/db/{id}/graph")]
+async fn graph(web::Path(id): web::Path<String>, query: web::Query<GraphQuery>) -> impl Responder {
+    let db = match open_database(&id) {
+        Ok(db) => db,
+        Err(response) => return response,
+    };
+    let graph = match db.btree_graph() {
+        Ok(graph) => graph,
+        Err(e) => return json_response::<BtreeGraph>(Err(e)),
+    };
+    match query.format.as_deref() {
+        Some("dot") => HttpResponse::Ok().content_type("text/vnd.graphviz").body(graph.to_dot()),
+        Some("json") => json_response(Ok(graph)),
+        _ => HttpResponse::Ok().content_type("image/svg+xml").body(graph.to_svg()),
+    }
+}
+
+#[get("/db/{id}/statistics")]
+async fn statistics(web::Path(id): web::Path<String>) -> impl Responder {
+    with_database(&id, |db| db.statistics())
+}
+
+#[get("/db/{id}/space_usage")]
+async fn space_usage(web::Path(id): web::Path<String>) -> impl Responder {
+    with_database(&id, |db| db.space_usage())
+}
+
+pub async fn serve(bind: String) -> std::io::Result<()> {
+    HttpServer::new(|| {
+        WebApp::new()
+            .service(fs::Files::new("/static", "./static").show_files_listing())
+            .service(databases)
+            .service(upload_database)
+            .service(btree_hierachy)
+            .service(btree_page)
+            .service(btree_page_num)
+            .service(fetch_database_header)
+            .service(diff)
+            .service(table_diff)
+            .service(events)
+            .service(graph)
+            .service(statistics)
+            .service(space_usage)
+    })
+    .bind(&bind)?
+    .run()
+    .await
+}
@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::components::database::Database;
+use crate::components::cell::Cell;
+use crate::components::cell_pointer::CellPointer;
+use crate::components::page::Page;
+use crate::components::record::{Column, SerialType};
+use crate::utils::convert::TryFromBytes;
+use crate::utils::error::MyError;
+
+// file_change_count lives at offset 24 of the database header
+const FILE_CHANGE_COUNT_OFFSET: u64 = 24;
+
+pub const DEFAULT_PAGE_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// What tells a cached database apart from the file on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileVersion {
+    pub modified: Option<SystemTime>,
+    pub length: u64,
+    pub file_change_count: u32,
+}
+
+impl FileVersion {
+    pub fn of(path: &str) -> Result<Self, MyError> {
+        let metadata = fs::metadata(path)?;
+        let mut file = File::open(path)?;
+        let mut bytes = [0u8; 4];
+        file.seek(SeekFrom::Start(FILE_CHANGE_COUNT_OFFSET))?;
+        file.read_exact(&mut bytes)?;
+        Ok(FileVersion {
+            modified: metadata.modified().ok(),
+            length: metadata.len(),
+            file_change_count: u32::try_from_be_bytes(&bytes)?,
+        })
+    }
+}
+
+struct CachedPage {
+    page: Arc<Page>,
+    size: usize,
+    last_used: u64,
+}
+
+/// LRU of parsed pages of every served database, bounded by the total weight of the cached pages.
+pub struct PageCache {
+    capacity: usize,
+    used: usize,
+    tick: u64,
+    pages: HashMap<(String, u32), CachedPage>,
+    recency: BTreeMap<u64, (String, u32)>,
+}
+
+impl PageCache {
+    pub fn new(capacity: usize) -> Self {
+        PageCache {
+            capacity,
+            used: 0,
+            tick: 0,
+            pages: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Bytes counted against the capacity.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn get(&mut self, id: &str, page_number: u32) -> Option<Arc<Page>> {
+        self.tick += 1;
+        let cached_page = self.pages.get_mut(&(id.to_string(), page_number))?;
+        self.recency.remove(&cached_page.last_used);
+        cached_page.last_used = self.tick;
+        self.recency.insert(self.tick, (id.to_string(), page_number));
+        Some(cached_page.page.clone())
+    }
+
+    /// Cache a page weighing `size` bytes, evicting the least recently used pages to make room.
+    pub fn insert(&mut self, id: &str, page_number: u32, page: Arc<Page>, size: usize) {
+        if size > self.capacity {
+            return;
+        }
+        self.remove(&(id.to_string(), page_number));
+        while self.used + size > self.capacity {
+            let oldest = match self.recency.values().next() {
+                Some(key) => key.clone(),
+                None => break,
+            };
+            self.remove(&oldest);
+        }
+
+        self.tick += 1;
+        self.used += size;
+        self.recency.insert(self.tick, (id.to_string(), page_number));
+        self.pages.insert((id.to_string(), page_number), CachedPage {
+            page,
+            size,
+            last_used: self.tick,
+        });
+    }
+
+    fn remove(&mut self, key: &(String, u32)) {
+        if let Some(cached_page) = self.pages.remove(key) {
+            self.recency.remove(&cached_page.last_used);
+            self.used -= cached_page.size;
+        }
+    }
+
+    /// Drop every page of the database `id`.
+    pub fn invalidate(&mut self, id: &str) {
+        let keys: Vec<(String, u32)> = self.pages.keys()
+            .filter(|(page_id, _)| page_id == id)
+            .cloned()
+            .collect();
+        for key in keys.iter() {
+            self.remove(key);
+        }
+    }
+}
+
+/// Estimated heap and inline bytes of a parsed page, counted against the page cache capacity:
+/// the cells with their records take several times the bytes they were parsed from.
+pub fn page_weight(page: &Page) -> usize {
+    let cells: usize = page.cells.iter().map(|cell| {
+        let local_payload = cell.local_payload.as_ref().map_or(0, |local_payload| local_payload.capacity());
+        let record = cell.payload.as_ref().map_or(0, |record| {
+            record.serial_types.capacity() * mem::size_of::<SerialType>()
+                + record.columns.capacity() * mem::size_of::<Column>()
+                + record.columns.iter().map(|column| match column {
+                    Column::STRING(s) => s.capacity(),
+                    Column::BLOB(b) => b.len(),
+                    _ => 0,
+                }).sum::<usize>()
+        });
+        mem::size_of::<Cell>() + local_payload + record
+    }).sum();
+    mem::size_of::<Page>() + page.cell_pointers.capacity() * mem::size_of::<CellPointer>() + cells
+}
+
+struct CachedDatabase {
+    version: FileVersion,
+    db: Arc<Database>,
+    page_parents: Option<Arc<Vec<i32>>>,
+}
+
+/// The opened databases of the server with their parsed pages and btree hierarchy,
+/// everything of a database is dropped once its file changes. Files are read and pages parsed
+/// by `open_database` and `read_page` without holding the lock of the cache.
+pub struct DatabaseCache {
+    pages: PageCache,
+    databases: HashMap<String, CachedDatabase>,
+}
+
+impl DatabaseCache {
+    pub fn new(page_cache_capacity: usize) -> Self {
+        DatabaseCache {
+            pages: PageCache::new(page_cache_capacity),
+            databases: HashMap::new(),
+        }
+    }
+
+    pub fn pages(&self) -> &PageCache {
+        &self.pages
+    }
+
+    /// The database `id` opened when its file was at `version`.
+    pub fn get_database(&self, id: &str, version: &FileVersion) -> Option<Arc<Database>> {
+        self.databases.get(id)
+            .filter(|cached_database| cached_database.version == *version)
+            .map(|cached_database| cached_database.db.clone())
+    }
+
+    /// Keep `db` opened at `version` in place of an older one, the database another request
+    /// opened at the same version meanwhile is kept and returned instead.
+    pub fn insert_database(&mut self, id: &str, version: FileVersion, db: Arc<Database>) -> Arc<Database> {
+        if let Some(cached_db) = self.get_database(id, &version) {
+            return cached_db;
+        }
+        self.remove(id);
+        self.databases.insert(id.to_string(), CachedDatabase {
+            version,
+            db: db.clone(),
+            page_parents: None,
+        });
+        db
+    }
+
+    fn is_current(&self, id: &str, db: &Arc<Database>) -> bool {
+        self.databases.get(id).is_some_and(|cached_database| Arc::ptr_eq(&cached_database.db, db))
+    }
+
+    /// The cached page of `db`, none once the database was reopened.
+    pub fn get_page(&mut self, id: &str, db: &Arc<Database>, page_number: u32) -> Option<Arc<Page>> {
+        if !self.is_current(id, db) {
+            return None;
+        }
+        self.pages.get(id, page_number)
+    }
+
+    /// Keep a page parsed from `db`, unless the database was reopened in the meantime.
+    pub fn insert_page(&mut self, id: &str, db: &Arc<Database>, page_number: u32, page: Arc<Page>) {
+        if self.is_current(id, db) {
+            let weight = page_weight(&page);
+            self.pages.insert(id, page_number, page, weight);
+        }
+    }
+
+    pub fn cached_page_parents(&self, id: &str) -> Option<Arc<Vec<i32>>> {
+        self.databases.get(id).and_then(|cached_database| cached_database.page_parents.clone())
+    }
+
+    /// Keep the hierarchy computed from `db`, unless the database was reopened in the meantime.
+    pub fn set_page_parents(&mut self, id: &str, db: &Arc<Database>, page_parents: Arc<Vec<i32>>) {
+        if let Some(cached_database) = self.databases.get_mut(id) {
+            if Arc::ptr_eq(&cached_database.db, db) {
+                cached_database.page_parents = Some(page_parents);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.databases.remove(id);
+        self.pages.invalidate(id);
+    }
+}
+
+/// The database `id` from the cache, reopened when the file changed since it was cached. The
+/// version of the file is read once and the database opened while other requests use the cache.
+pub fn open_database(cache: &Mutex<DatabaseCache>, id: &str, path: &str) -> Result<Arc<Database>, MyError> {
+    let version = FileVersion::of(path)?;
+    if let Some(db) = cache.lock().unwrap().get_database(id, &version) {
+        return Ok(db);
+    }
+    let db = Arc::new(Database::open(path)?);
+    Ok(cache.lock().unwrap().insert_database(id, version, db))
+}
+
+/// The parsed page of `db` from the cache, `page_number` starts from 1. The page is parsed
+/// while other requests use the cache.
+pub fn read_page(cache: &Mutex<DatabaseCache>, id: &str, db: &Arc<Database>, page_number: u32) -> Result<Arc<Page>, MyError> {
+    if let Some(page) = cache.lock().unwrap().get_page(id, db, page_number) {
+        return Ok(page);
+    }
+    let page = Arc::new(db.read_page(page_number)?);
+    cache.lock().unwrap().insert_page(id, db, page_number, page.clone());
+    Ok(page)
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::Path;
+
+use serde_derive::Serialize;
+
+use crate::components::database::Database;
+use crate::components::database_header::SQLITE_DB_HEADER_STRING;
+use crate::utils::error::MyError;
+
+/// A database file the server can dissect, addressed by `id` in the routes.
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseFile {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub page_size: usize,
+    pub page_count: u32,
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct DatabaseRegistry {
+    databases: Vec<DatabaseFile>,
+}
+
+// "Chinook.db.4.analyze" => "chinook-db-4-analyze"
+fn slug(name: &str) -> String {
+    let slug: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let slug = slug.trim_matches('-').to_string();
+    if slug.is_empty() { "database".to_string() } else { slug }
+}
+
+impl DatabaseRegistry {
+    pub fn new() -> Self {
+        DatabaseRegistry {
+            databases: Vec::new(),
+        }
+    }
+
+    fn unique_id(&self, name: &str) -> String {
+        let base = slug(name);
+        let mut id = base.clone();
+        let mut suffix = 2;
+        while self.get(&id).is_some() {
+            id = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        id
+    }
+
+    /// Register one database file, it must open as a sqlite database.
+    pub fn register_file(&mut self, path: &str) -> Result<DatabaseFile, MyError> {
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        self.register_named_file(path, &name, None)
+    }
+
+    /// Register a database file under a display name, `expires_at` is a unix timestamp in seconds.
+    pub fn register_named_file(&mut self, path: &str, name: &str, expires_at: Option<u64>) -> Result<DatabaseFile, MyError> {
+        // a sqlite file whose header keeps it from opening is still registered, its header shows why
+        let (page_size, page_count) = match Database::open(path) {
+            Ok(db) => (db.page_size(), db.page_count()),
+            Err(e) => match Database::read_header(path) {
+                Ok(header) if header.header_string == SQLITE_DB_HEADER_STRING => (header.page_size_in_bytes() as usize, header.in_header_database_size),
+                _ => return Err(e),
+            },
+        };
+
+        let database_file = DatabaseFile {
+            id: self.unique_id(name),
+            name: name.to_string(),
+            path: path.to_string(),
+            page_size,
+            page_count,
+            expires_at,
+        };
+        self.databases.push(database_file.clone());
+        Ok(database_file)
+    }
+
+    /// Register a database file, or every sqlite database file directly inside a directory.
+    pub fn register_path(&mut self, path: &str) -> Result<Vec<DatabaseFile>, MyError> {
+        if !Path::new(path).is_dir() {
+            return Ok(vec![self.register_file(path)?]);
+        }
+
+        let mut paths: Vec<String> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        paths.sort();
+
+        // other files living in the directory are skipped
+        Ok(paths.iter()
+            .filter_map(|path| self.register_file(path).ok())
+            .collect())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&DatabaseFile> {
+        self.databases.iter().find(|database_file| database_file.id == id)
+    }
+
+    pub fn list(&self) -> &[DatabaseFile] {
+        &self.databases
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<DatabaseFile> {
+        let idx = self.databases.iter().position(|database_file| database_file.id == id)?;
+        Some(self.databases.remove(idx))
+    }
+
+    /// Unregister the databases expired at `now`, they are returned so their files can be removed.
+    pub fn remove_expired(&mut self, now: u64) -> Vec<DatabaseFile> {
+        let (expired, databases) = self.databases.drain(..)
+            .partition(|database_file| matches!(database_file.expires_at, Some(expires_at) if expires_at <= now));
+        self.databases = databases;
+        expired
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::components::database_header::has_sqlite_header_string;
+use crate::utils::error::{ErrorKind, MyError};
+
+// the magic is the first 16 bytes of the database header
+const SQLITE_HEADER_STRING_LENGTH: usize = 16;
+
+static UPLOAD_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Unix timestamp in seconds, used for the expiry of uploads.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// "../../x/Chinook.db" => "Chinook.db"
+pub fn upload_name(file_name: &str) -> String {
+    file_name.rsplit(['/', '\\'])
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("upload")
+        .to_string()
+}
+
+/// The directory uploaded databases are written to, with the limits of the uploads.
+#[derive(Debug, Clone)]
+pub struct UploadWorkspace {
+    pub directory: PathBuf,
+    pub max_size: usize,
+    pub ttl: u64,
+}
+
+impl UploadWorkspace {
+    pub fn new(directory: &Path, max_size: usize, ttl: u64) -> Result<Self, MyError> {
+        fs::create_dir_all(directory)?;
+        Ok(UploadWorkspace {
+            directory: directory.to_path_buf(),
+            max_size,
+            ttl,
+        })
+    }
+
+    pub fn create_file(&self) -> Result<UploadFile, MyError> {
+        let path = self.directory.join(format!("upload-{}-{}-{}.db",
+            std::process::id(), now(), UPLOAD_COUNTER.fetch_add(1, Ordering::SeqCst)));
+        Ok(UploadFile {
+            file: File::create(&path)?,
+            path,
+            size: 0,
+            max_size: self.max_size,
+            head: Vec::with_capacity(SQLITE_HEADER_STRING_LENGTH),
+        })
+    }
+
+    pub fn expires_at(&self) -> u64 {
+        now() + self.ttl
+    }
+}
+
+/// A database file being uploaded, written chunk by chunk.
+pub struct UploadFile {
+    file: File,
+    path: PathBuf,
+    size: usize,
+    max_size: usize,
+    head: Vec<u8>,
+}
+
+impl UploadFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a chunk, failing as soon as the upload is too large or doesn't start with the sqlite magic.
+    pub fn write_chunk(&mut self, chunk: &[u8]) -> Result<(), MyError> {
+        self.size += chunk.len();
+        if self.size > self.max_size {
+            return Err(MyError::new(ErrorKind::UploadTooLarge(self.max_size)));
+        }
+
+        if self.head.len() < SQLITE_HEADER_STRING_LENGTH {
+            let missing = SQLITE_HEADER_STRING_LENGTH - self.head.len();
+            self.head.extend_from_slice(&chunk[..missing.min(chunk.len())]);
+            if self.head.len() == SQLITE_HEADER_STRING_LENGTH && !has_sqlite_header_string(&self.head) {
+                return Err(MyError::new(ErrorKind::NotDatabaseFile));
+            }
+        }
+
+        self.file.write_all(chunk)?;
+        Ok(())
+    }
+
+    /// Flush the upload and return its path, an upload shorter than the magic is discarded.
+    pub fn finish(mut self) -> Result<PathBuf, MyError> {
+        if !has_sqlite_header_string(&self.head) {
+            self.discard();
+            return Err(MyError::new(ErrorKind::NotDatabaseFile));
+        }
+        self.file.flush()?;
+        Ok(self.path)
+    }
+
+    /// Drop the partial upload from the workspace.
+    pub fn discard(self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
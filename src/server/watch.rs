@@ -0,0 +1,282 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use actix_web::web::Bytes;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use lazy_static::*;
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use serde_derive::Serialize;
+
+use crate::components::database::Database;
+use crate::server::cache::FileVersion;
+use crate::utils::convert::TryFromBytes;
+use crate::utils::error::{ErrorKind, MyError};
+
+const WAL_HEADER_LENGTH: usize = 32;
+const WAL_FRAME_HEADER_LENGTH: usize = 24;
+
+// events of a few writes in a row are delivered once
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+// how often a watcher without file events looks for subscribers left
+const WATCH_IDLE_CHECK: Duration = Duration::from_secs(10);
+
+/// Pages modified by one transaction, either written to the database file or appended to its `-wal`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangeNotice {
+    pub source: String,
+    pub file_change_count: u32,
+    pub page_count: u32,
+    pub pages: Vec<u32>,
+}
+
+fn page_digests(db: &Database) -> Result<Vec<u64>, MyError> {
+    let mut digests = Vec::with_capacity(db.page_count() as usize);
+    for page_number in 1..=db.page_count() {
+        let mut hasher = DefaultHasher::new();
+        db.page_bytes(page_number)?.hash(&mut hasher);
+        digests.push(hasher.finish());
+    }
+    Ok(digests)
+}
+
+pub fn wal_path(path: &str) -> String {
+    format!("{}-wal", path)
+}
+
+/// Remembers the database file and its `-wal` as last seen, to tell which pages each transaction modified.
+pub struct ChangeTracker {
+    path: String,
+    version: FileVersion,
+    digests: Vec<u64>,
+    wal_salts: Option<Vec<u8>>,
+    wal_offset: usize,
+}
+
+impl ChangeTracker {
+    /// Start from the current content, only transactions made after are reported.
+    pub fn new(path: &str) -> Result<Self, MyError> {
+        let db = Database::open(path)?;
+        let mut tracker = ChangeTracker {
+            path: path.to_string(),
+            version: FileVersion::of(path)?,
+            digests: page_digests(&db)?,
+            wal_salts: None,
+            wal_offset: WAL_HEADER_LENGTH,
+        };
+        tracker.wal_transactions()?;
+        Ok(tracker)
+    }
+
+    /// The transactions made since the last poll, the database file is compared page by page
+    /// once its version changed, the `-wal` is read from the last commit frame seen.
+    pub fn poll(&mut self) -> Result<Vec<ChangeNotice>, MyError> {
+        let mut notices = Vec::new();
+
+        let version = FileVersion::of(&self.path)?;
+        if version != self.version {
+            let db = Database::open(&self.path)?;
+            let digests = page_digests(&db)?;
+            let pages: Vec<u32> = (0..digests.len().max(self.digests.len()))
+                .filter(|&idx| digests.get(idx) != self.digests.get(idx))
+                .map(|idx| idx as u32 + 1)
+                .collect();
+            notices.push(ChangeNotice {
+                source: "database".to_string(),
+                file_change_count: version.file_change_count,
+                page_count: db.page_count(),
+                pages,
+            });
+            self.version = version;
+            self.digests = digests;
+        }
+
+        for pages in self.wal_transactions()? {
+            notices.push(ChangeNotice {
+                source: "wal".to_string(),
+                file_change_count: self.version.file_change_count,
+                page_count: self.digests.len() as u32,
+                pages,
+            });
+        }
+        Ok(notices)
+    }
+
+    fn reset_wal(&mut self) {
+        self.wal_salts = None;
+        self.wal_offset = WAL_HEADER_LENGTH;
+    }
+
+    // Frames of the current wal generation share the salts of the wal header,
+    // a checkpoint restarts the wal with new salts. Only the headers of the frames appended
+    // since the last commit frame are read, their pages are skipped.
+    fn wal_transactions(&mut self) -> Result<Vec<Vec<u32>>, MyError> {
+        // no -wal means no transaction pending a checkpoint
+        let mut file = match File::open(wal_path(&self.path)) {
+            Ok(file) => file,
+            Err(_) => {
+                self.reset_wal();
+                return Ok(Vec::new());
+            },
+        };
+        let length = file.metadata()?.len() as usize;
+        let mut header = [0u8; WAL_HEADER_LENGTH];
+        if length < WAL_HEADER_LENGTH || file.read_exact(&mut header).is_err() {
+            self.reset_wal();
+            return Ok(Vec::new());
+        }
+
+        let salts = header[16..24].to_vec();
+        if self.wal_salts.as_ref() != Some(&salts) || self.wal_offset > length {
+            self.wal_salts = Some(salts.clone());
+            self.wal_offset = WAL_HEADER_LENGTH;
+        }
+        let page_size = u32::try_from_be_bytes(&header[8..12])? as usize;
+        let page_size = if page_size == 1 { 65536 } else { page_size };
+        if !page_size.is_power_of_two() || !(512..=65536).contains(&page_size) {
+            return Ok(Vec::new());
+        }
+        let frame_length = WAL_FRAME_HEADER_LENGTH + page_size;
+
+        let mut transactions = Vec::new();
+        let mut pages = Vec::new();
+        let mut offset = self.wal_offset;
+        let mut frame_header = [0u8; WAL_FRAME_HEADER_LENGTH];
+        while offset + frame_length <= length {
+            file.seek(SeekFrom::Start(offset as u64))?;
+            file.read_exact(&mut frame_header)?;
+            if frame_header[8..16] != salts[..] {
+                break;
+            }
+            let page_number = u32::try_from_be_bytes(&frame_header[0..4])?;
+            if !pages.contains(&page_number) {
+                pages.push(page_number);
+            }
+            offset += frame_length;
+            // the database size is only set on the commit frame of a transaction
+            if u32::try_from_be_bytes(&frame_header[4..8])? != 0 {
+                pages.sort_unstable();
+                transactions.push(pages);
+                pages = Vec::new();
+                self.wal_offset = offset;
+            }
+        }
+        Ok(transactions)
+    }
+}
+
+// The event streams of a database and the watcher sending to them, a new watcher started after
+// the last subscriber left has another generation.
+struct Subscription {
+    generation: usize,
+    senders: Vec<UnboundedSender<Bytes>>,
+}
+
+static WATCH_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static!{
+    static ref SUBSCRIBERS: Mutex<HashMap<String, Subscription>> = Mutex::new(HashMap::new());
+}
+
+/// Receive the server-sent events of the database `id`, its file gets watched on the first
+/// subscription and until the last event stream is closed.
+pub fn subscribe(id: &str, path: &str) -> Result<UnboundedReceiver<Bytes>, MyError> {
+    let (sender, receiver) = unbounded();
+    if let Some(subscription) = SUBSCRIBERS.lock().unwrap().get_mut(id) {
+        subscription.senders.push(sender);
+        return Ok(receiver);
+    }
+
+    // the pages are hashed before taking the lock, the other databases keep publishing meanwhile
+    let tracker = ChangeTracker::new(path)?;
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    match subscribers.get_mut(id) {
+        // another subscription started watching in the meantime
+        Some(subscription) => subscription.senders.push(sender),
+        None => {
+            let generation = WATCH_GENERATION.fetch_add(1, Ordering::SeqCst);
+            watch(id, path, tracker, generation)?;
+            subscribers.insert(id.to_string(), Subscription {
+                generation,
+                senders: vec![sender],
+            });
+        },
+    }
+    Ok(receiver)
+}
+
+/// Whether the file of the database `id` is watched for its subscribers.
+pub fn is_watched(id: &str) -> bool {
+    SUBSCRIBERS.lock().unwrap().contains_key(id)
+}
+
+// Send the notices to the subscribers of `id` and drop the closed event streams. False once
+// none is left or another watcher took over, the watcher of `generation` stops then.
+fn publish(id: &str, generation: usize, notices: &[ChangeNotice]) -> bool {
+    let events: Vec<Bytes> = notices.iter()
+        .map(|notice| Bytes::from(format!("event: change\ndata: {}\n\n", serde_json::to_string(notice).unwrap())))
+        .collect();
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    let subscription = match subscribers.get_mut(id) {
+        Some(subscription) if subscription.generation == generation => subscription,
+        _ => return false,
+    };
+    subscription.senders.retain(|sender| !sender.is_closed() && events.iter().all(|event| sender.unbounded_send(event.clone()).is_ok()));
+    if subscription.senders.is_empty() {
+        subscribers.remove(id);
+        return false;
+    }
+    true
+}
+
+fn touches(event: &DebouncedEvent, watched_paths: &[PathBuf]) -> bool {
+    match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Remove(path) => watched_paths.contains(path),
+        DebouncedEvent::Rename(from, to) => watched_paths.contains(from) || watched_paths.contains(to),
+        DebouncedEvent::Rescan => true,
+        _ => false,
+    }
+}
+
+// The directory is watched rather than the files, the -wal comes and goes with the connections.
+fn watch(id: &str, path: &str, mut tracker: ChangeTracker, generation: usize) -> Result<(), MyError> {
+    let file_path = PathBuf::from(path).canonicalize()?;
+    let directory = file_path.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."));
+    let watched_paths = [PathBuf::from(wal_path(&file_path.to_string_lossy())), file_path];
+
+    let (sender, receiver) = channel();
+    let mut watcher = watcher(sender, WATCH_DEBOUNCE)
+        .map_err(|e| MyError::new(ErrorKind::IoError(e.to_string())))?;
+    watcher.watch(&directory, RecursiveMode::NonRecursive)
+        .map_err(|e| MyError::new(ErrorKind::IoError(e.to_string())))?;
+
+    let id = id.to_string();
+    thread::spawn(move || {
+        // the watcher stops when dropped
+        let _watcher = watcher;
+        loop {
+            let notices = match receiver.recv_timeout(WATCH_IDLE_CHECK) {
+                Ok(event) if touches(&event, &watched_paths) => tracker.poll().unwrap_or_else(|e| {
+                    // the file may be caught in the middle of a write, the next event tells the rest
+                    eprintln!("error: {} can't be read: {}", id, e);
+                    Vec::new()
+                }),
+                // the subscribers may all be gone without a change to tell them
+                Ok(_) | Err(RecvTimeoutError::Timeout) => Vec::new(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if !publish(&id, generation, &notices) {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
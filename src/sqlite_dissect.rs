@@ -0,0 +1,859 @@
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::{Arg, App, AppSettings, SubCommand, ArgMatches};
+use serde::Serialize;
+use serde_json::json;
+use serde_json::value::Value;
+use tinytemplate::TinyTemplate;
+
+use sqlite_database_file_dissect::components::btree::BtreeNode;
+use sqlite_database_file_dissect::components::database::Database;
+use sqlite_database_file_dissect::components::database_header::{patch_header_bytes, DatabaseHeader, Severity};
+use sqlite_database_file_dissect::components::diff::diff_headers;
+use sqlite_database_file_dissect::components::export::{BlobEncoding, CsvOptions, Quoting};
+use sqlite_database_file_dissect::components::page::Page;
+use sqlite_database_file_dissect::components::schema::SQLITE_SCHEMA_ROOT_PAGE;
+use sqlite_database_file_dissect::server;
+use sqlite_database_file_dissect::server::upload::UploadWorkspace;
+use sqlite_database_file_dissect::utils::convert::TryFromBytes;
+use sqlite_database_file_dissect::utils::error::{MyError, ErrorKind};
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_CHECK_FAILED: i32 = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+}
+
+fn file_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("FILE")
+        .help("sqlite database file name")
+        .required(true)
+        .index(1)
+}
+
+fn format_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .possible_values(&["text", "json"])
+        .default_value("text")
+        .help("output format")
+}
+
+fn format_of(matches: &ArgMatches) -> Format {
+    match matches.value_of("format") {
+        Some("json") => Format::Json,
+        _ => Format::Text,
+    }
+}
+
+fn open_database(matches: &ArgMatches) -> Result<Database, MyError> {
+    Database::open(matches.value_of("FILE").unwrap())
+}
+
+// Render any serializable value as indented "key: value" lines.
+fn value_to_text(value: &Value, indent: usize, text: &mut String) {
+    let scalar = |v: &Value| match v {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    };
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter() {
+                match v {
+                    Value::Object(_) | Value::Array(_) => {
+                        text.push_str(&format!("{:indent$}{}:\n", "", key, indent = indent));
+                        value_to_text(v, indent + 2, text);
+                    },
+                    v => text.push_str(&format!("{:indent$}{}: {}\n", "", key, scalar(v), indent = indent)),
+                }
+            }
+        },
+        Value::Array(items) => {
+            for (idx, v) in items.iter().enumerate() {
+                match v {
+                    Value::Object(_) | Value::Array(_) => {
+                        text.push_str(&format!("{:indent$}[{}]\n", "", idx, indent = indent));
+                        value_to_text(v, indent + 2, text);
+                    },
+                    v => text.push_str(&format!("{:indent$}[{}] {}\n", "", idx, scalar(v), indent = indent)),
+                }
+            }
+        },
+        v => text.push_str(&format!("{:indent$}{}\n", "", scalar(v), indent = indent)),
+    }
+}
+
+fn print_output<T: Serialize>(format: Format, value: &T) {
+    let value = serde_json::to_value(value).unwrap();
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&value).unwrap()),
+        Format::Text => {
+            let mut text = String::new();
+            value_to_text(&value, 0, &mut text);
+            print!("{}", text);
+        },
+    }
+}
+
+fn tree_to_text(node: &BtreeNode, indent: usize, text: &mut String) {
+    text.push_str(&format!("{:indent$}page {} {:?} ({} cells)\n", "", node.page_number, node.page_type, node.cell_number, indent = indent));
+    for child in node.children.iter() {
+        tree_to_text(child, indent + 2, text);
+    }
+}
+
+fn header(matches: &ArgMatches) -> Result<i32, MyError> {
+    if let Some(set_matches) = matches.subcommand_matches("set") {
+        return header_set(set_matches);
+    }
+    // the header is read from the file bytes, a file that doesn't open still has its header validated
+    let file_name = matches.value_of("FILE").unwrap();
+    let header = Database::read_header(file_name)?;
+    let issues = header.validate();
+    let size = Database::open(file_name).ok().map(|db| db.size());
+    match format_of(matches) {
+        Format::Json => {
+            let mut value = serde_json::to_value(&header).unwrap();
+            value["issues"] = serde_json::to_value(&issues).unwrap();
+            value["size"] = serde_json::to_value(&size).unwrap();
+            print_output(Format::Json, &value);
+        },
+        Format::Text => {
+            print_output(Format::Text, &header);
+            if let Some(size) = size.as_ref() {
+                println!("effective page count: {}", size.effective_page_count);
+            }
+            for issue in issues.iter() {
+                println!("{:?} {}: {}", issue.severity, issue.field, issue.message);
+            }
+            for problem in size.iter().flat_map(|size| size.problems.iter()) {
+                println!("Warning size: {}", problem);
+            }
+        },
+    }
+    Ok(if issues.iter().any(|issue| issue.severity == Severity::Error) { EXIT_CHECK_FAILED } else { EXIT_SUCCESS })
+}
+
+fn header_set(matches: &ArgMatches) -> Result<i32, MyError> {
+    let file_name = matches.value_of("FILE").unwrap();
+    let field = matches.value_of("FIELD").unwrap();
+    // the header bytes are patched as they are read, the file doesn't have to open as a database
+    let mut bytes = Database::read_header_bytes(file_name)?;
+    let before = DatabaseHeader::try_from_be_bytes(&bytes)?;
+    let mut after = before.clone();
+    if let Err(e) = after.set_field(field, matches.value_of("VALUE").unwrap()) {
+        eprintln!("error: {}", e);
+        if let ErrorKind::UnknownHeaderField(_) = e.kind() {
+            eprintln!("the fields are {}", DatabaseHeader::FIELDS.join(", "));
+        }
+        return Ok(EXIT_USAGE);
+    }
+    // a value sqlite rejects can't be what the pages were written with, replacing it is a repair
+    let repairs = before.validate().iter().any(|issue| issue.severity == Severity::Error && issue.field == field);
+    if !matches.is_present("force") && !repairs && DatabaseHeader::PAGE_LAYOUT_FIELDS.contains(&field) && !diff_headers(&before, &after).is_empty() {
+        eprintln!("error: {} is changed without rewriting the pages, they no longer read as they were written; --force changes it anyway", field);
+        return Ok(EXIT_USAGE);
+    }
+    patch_header_bytes(&mut bytes, &before, &after);
+    let after = DatabaseHeader::try_from_be_bytes(&bytes)?;
+    let issues = after.validate();
+
+    // the original file is only written with --in-place, after it was copied to the backup
+    let target = match (matches.value_of("output"), matches.is_present("in-place")) {
+        (Some(output), _) => {
+            if Path::new(output).exists() {
+                eprintln!("{} already exists", output);
+                return Ok(EXIT_USAGE);
+            }
+            std::fs::copy(file_name, output)?;
+            Some(output.to_string())
+        },
+        (None, true) => {
+            let backup = matches.value_of("backup").map_or(format!("{}.bak", file_name), |backup| backup.to_string());
+            if Path::new(&backup).exists() {
+                eprintln!("the backup {} already exists, --backup names another one", backup);
+                return Ok(EXIT_USAGE);
+            }
+            std::fs::copy(file_name, &backup)?;
+            eprintln!("{} backed up to {}", file_name, backup);
+            if Path::new(&format!("{}-wal", file_name)).exists() {
+                eprintln!("warning: {}-wal exists, a checkpoint may write the header again", file_name);
+            }
+            if Path::new(&format!("{}-journal", file_name)).exists() {
+                eprintln!("warning: {}-journal exists, rolling it back may write the header again", file_name);
+            }
+            Some(file_name.to_string())
+        },
+        (None, false) => None,
+    };
+    if let Some(target) = &target {
+        let mut file = std::fs::OpenOptions::new().write(true).open(target)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+    }
+
+    let changes = diff_headers(&before, &after);
+    match format_of(matches) {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&json!({ "written_to": target, "header": changes, "issues": issues })).unwrap()),
+        Format::Text => {
+            if changes.is_empty() {
+                println!("{} is unchanged", field);
+            }
+            for change in changes.iter() {
+                println!("header {}: {} -> {}", change.field, change.before, change.after);
+            }
+            for issue in issues.iter() {
+                println!("{:?} {}: {}", issue.severity, issue.field, issue.message);
+            }
+            match &target {
+                Some(target) => println!("written to {}", target),
+                None => println!("nothing written, --output writes a copy and --in-place the file itself"),
+            }
+        },
+    }
+    Ok(if issues.iter().any(|issue| issue.severity == Severity::Error) { EXIT_CHECK_FAILED } else { EXIT_SUCCESS })
+}
+
+fn page(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    let page_number: u32 = match matches.value_of("PAGE").unwrap().parse() {
+        Ok(page_number) => page_number,
+        Err(_) => {
+            eprintln!("error: PAGE must be a page number starting from 1");
+            return Ok(EXIT_USAGE);
+        },
+    };
+    print_output(format_of(matches), &db.read_page(page_number)?);
+    Ok(EXIT_SUCCESS)
+}
+
+fn tree(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    let roots = std::iter::once(("table".to_string(), "sqlite_schema".to_string(), SQLITE_SCHEMA_ROOT_PAGE))
+        .chain(db.schema()?.into_iter()
+               .filter(|entry| entry.root_page > 0)
+               .map(|entry| (entry.entry_type, entry.name, entry.root_page)));
+
+    let mut btrees = Vec::new();
+    for (entry_type, name, root_page) in roots {
+        btrees.push((entry_type, name, db.btree_node(root_page)?));
+    }
+
+    match format_of(matches) {
+        Format::Json => {
+            let btrees: Vec<Value> = btrees.iter()
+                .map(|(entry_type, name, root)| json!({"type": entry_type, "name": name, "root": root}))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&btrees).unwrap());
+        },
+        Format::Text => {
+            let mut text = String::new();
+            for (entry_type, name, root) in btrees.iter() {
+                text.push_str(&format!("{} {}\n", entry_type, name));
+                tree_to_text(root, 2, &mut text);
+            }
+            print!("{}", text);
+        },
+    }
+
+    let problems = db.btree_traversal()?.problems;
+    for problem in problems.iter() {
+        eprintln!("{}", problem);
+    }
+    Ok(if problems.is_empty() { EXIT_SUCCESS } else { EXIT_CHECK_FAILED })
+}
+
+fn page_to_json(page_number: u32, page: Result<Page, MyError>) -> Value {
+    match page {
+        Ok(page) => json!({"page_number": page_number, "page": page}),
+        Err(e) => json!({"page_number": page_number, "error": e.to_string()}),
+    }
+}
+
+fn dump(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+
+    if let Some(template_file) = matches.value_of("template") {
+        let mut template = String::new();
+        File::open(template_file)?.read_to_string(&mut template)?;
+
+        let mut tt = TinyTemplate::new();
+        tt.add_template("dump", &template)
+            .map_err(|e| MyError::new(ErrorKind::IoError(e.to_string())))?;
+        tt.add_formatter("serial_type", |v, r| {
+            match v {
+                Value::Object(m) => write!(r, "{:?}", m)?,
+                Value::Number(n) => write!(r, "{:?}", n)?,
+                Value::Null => write!(r, "{:?}", "None")?,
+                Value::Bool(b) => write!(r, "{:?}", b)?,
+                Value::String(s) => write!(r, "{:?}", s)?,
+                Value::Array(v) => write!(r, "{:?}", v)?,
+            };
+            Ok(())
+        });
+        // the template sees every page at once, it can't be streamed
+        let pages: Vec<Value> = db.pages()
+            .filter_map(|(_, page)| page.ok())
+            .map(|page| serde_json::to_value(page).unwrap())
+            .collect();
+        let context = json!({
+            "page_parents": db.page_parents()?,
+            "pages": pages,
+        });
+        let rendered = tt.render("dump", &context)
+            .map_err(|e| MyError::new(ErrorKind::IoError(e.to_string())))?;
+        println!("{}", rendered);
+        return Ok(EXIT_SUCCESS);
+    }
+
+    // pages are written as they are parsed, the output is the same as printing them all at once
+    let format = format_of(matches);
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut page_written = false;
+    {
+        let mut write_page = |page_number: u32, page: Result<Page, MyError>| -> Result<(), MyError> {
+            let value = page_to_json(page_number, page);
+            match format {
+                Format::Json => {
+                    let json = serde_json::to_string_pretty(&value).unwrap();
+                    write!(out, "{}  {}", if page_written { ",\n" } else { "[\n" }, json.replace('\n', "\n  "))?;
+                },
+                Format::Text => {
+                    let mut text = format!("[{}]\n", page_number - 1);
+                    value_to_text(&value, 2, &mut text);
+                    out.write_all(text.as_bytes())?;
+                },
+            }
+            page_written = true;
+            Ok(())
+        };
+        if matches.is_present("parallel") {
+            db.for_each_page_parallel(&mut write_page)?;
+        } else {
+            for (page_number, page) in db.pages() {
+                write_page(page_number, page)?;
+            }
+        }
+    }
+    if format == Format::Json {
+        writeln!(out, "{}", if page_written { "\n]" } else { "[]" })?;
+    }
+    out.flush()?;
+    Ok(EXIT_SUCCESS)
+}
+
+fn schema(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    print_output(format_of(matches), &db.schema()?);
+    Ok(EXIT_SUCCESS)
+}
+
+fn statistics(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    print_output(format_of(matches), &db.statistics()?);
+    Ok(EXIT_SUCCESS)
+}
+
+fn space_usage(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    let report = db.space_usage()?;
+
+    match format_of(matches) {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        Format::Text => print!("{}", report.to_text()),
+    }
+    Ok(if report.is_ok() { EXIT_SUCCESS } else { EXIT_CHECK_FAILED })
+}
+
+fn check(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    let report = db.check();
+
+    match format_of(matches) {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        Format::Text if report.is_ok() => println!("ok"),
+        Format::Text => {
+            for problem in report.problems.iter() {
+                println!("{}", problem);
+            }
+        },
+    }
+    Ok(if report.is_ok() { EXIT_SUCCESS } else { EXIT_CHECK_FAILED })
+}
+
+fn diff(matches: &ArgMatches) -> Result<i32, MyError> {
+    let before = Database::open(matches.value_of("OLD").unwrap())?;
+    let after = Database::open(matches.value_of("NEW").unwrap())?;
+    let database_diff = before.diff(&after)?;
+
+    match format_of(matches) {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&database_diff).unwrap()),
+        Format::Text => print!("{}", database_diff.to_text()),
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+fn row_diff(matches: &ArgMatches) -> Result<i32, MyError> {
+    let before = Database::open(matches.value_of("OLD").unwrap())?;
+    let after = Database::open(matches.value_of("NEW").unwrap())?;
+    let table_diff = before.table_diff(&after, matches.value_of("TABLE").unwrap())?;
+
+    match matches.value_of("format") {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&table_diff).unwrap()),
+        Some("sql") => print!("{}", table_diff.to_sql()),
+        _ => print!("{}", table_diff.to_text()),
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+fn export(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    let table = matches.value_of("TABLE").unwrap();
+    let blob_encoding = match matches.value_of("blob") {
+        Some("base64") => BlobEncoding::Base64,
+        _ => BlobEncoding::Hex,
+    };
+
+    let mut out: Box<dyn Write> = match matches.value_of("output") {
+        Some(output) => Box::new(BufWriter::new(File::create(output)?)),
+        None => Box::new(BufWriter::new(std::io::stdout())),
+    };
+    let rows = match matches.value_of("format") {
+        Some("jsonl") => db.export_json_lines(table, blob_encoding, &mut out)?,
+        _ => {
+            let delimiter = matches.value_of("delimiter").unwrap();
+            let delimiter = match delimiter {
+                "\\t" | "tab" => '\t',
+                delimiter if delimiter.chars().count() == 1 => delimiter.chars().next().unwrap(),
+                _ => {
+                    eprintln!("error: the delimiter should be a single character");
+                    return Ok(EXIT_USAGE);
+                },
+            };
+            let options = CsvOptions {
+                delimiter,
+                quoting: match matches.value_of("quote") {
+                    Some("all") => Quoting::All,
+                    Some("non-numeric") => Quoting::NonNumeric,
+                    Some("never") => Quoting::Never,
+                    _ => Quoting::Minimal,
+                },
+                blob_encoding,
+                header: !matches.is_present("no-header"),
+            };
+            db.export_csv(table, &options, &mut out)?
+        },
+    };
+    out.flush()?;
+
+    if let Some(output) = matches.value_of("output") {
+        println!("{} rows of {} written to {}", rows, table, output);
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+fn sqldump(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    let mut out: Box<dyn Write> = match matches.value_of("output") {
+        Some(output) => Box::new(BufWriter::new(File::create(output)?)),
+        None => Box::new(BufWriter::new(std::io::stdout())),
+    };
+    let report = db.sql_dump(&mut out)?;
+    out.flush()?;
+
+    eprintln!("{} tables, {} rows dumped", report.tables, report.rows);
+    for problem in report.problems.iter() {
+        eprintln!("{}", problem);
+    }
+    Ok(if report.is_ok() { EXIT_SUCCESS } else { EXIT_CHECK_FAILED })
+}
+
+fn repair(matches: &ArgMatches) -> Result<i32, MyError> {
+    let output = matches.value_of("OUTPUT").unwrap();
+    if Path::new(output).exists() && !matches.is_present("force") {
+        eprintln!("{} already exists, --force overwrites it", output);
+        return Ok(EXIT_USAGE);
+    }
+    let db = open_database(matches)?;
+    let (writer, report) = db.repair()?;
+    writer.write_to(output)?;
+
+    match format_of(matches) {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        Format::Text => {
+            println!("{} tables, {} indexes and {} rows written to {}, {} rows read from orphan pages",
+                report.tables, report.indexes, report.rows, output, report.orphan_rows);
+            for lost in report.lost_pages.iter() {
+                let row_ids = match (lost.min_row_id, lost.max_row_id) {
+                    (None, None) => String::new(),
+                    (min_row_id, max_row_id) => format!(" (rowids {} to {})",
+                        min_row_id.map_or("-".to_string(), |row_id| row_id.to_string()),
+                        max_row_id.map_or("-".to_string(), |row_id| row_id.to_string())),
+                };
+                println!("lost page {} of {}{}: {}", lost.page_number, lost.table, row_ids, lost.reason);
+            }
+            for lost in report.lost_rows.iter() {
+                let row_id = lost.row_id.map_or("a row".to_string(), |row_id| format!("row {}", row_id));
+                println!("lost {} of {} on page {}: {}", row_id, lost.table, lost.page_number, lost.reason);
+            }
+            for problem in report.problems.iter() {
+                println!("{}", problem);
+            }
+        },
+    }
+    Ok(if report.is_ok() { EXIT_SUCCESS } else { EXIT_CHECK_FAILED })
+}
+
+fn graph(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    let graph = db.btree_graph()?;
+    let output = match matches.value_of("format") {
+        Some("json") => serde_json::to_string_pretty(&graph).unwrap(),
+        Some("svg") => graph.to_svg(),
+        _ => graph.to_dot(),
+    };
+
+    match matches.value_of("output") {
+        Some(file_name) => File::create(file_name)?.write_all(output.as_bytes())?,
+        None => print!("{}", output),
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+fn tui(matches: &ArgMatches) -> Result<i32, MyError> {
+    let db = open_database(matches)?;
+    sqlite_database_file_dissect::tui::run(&db)?;
+    Ok(EXIT_SUCCESS)
+}
+
+fn serve(matches: &ArgMatches) -> Result<i32, MyError> {
+    let paths: Vec<String> = matches.values_of("PATH").into_iter().flatten()
+        .chain(matches.values_of("file").into_iter().flatten())
+        .map(|path| path.to_string())
+        .collect();
+    let bind = matches.value_of("bind").unwrap();
+
+    // fail early on files the server could not dissect
+    let databases = server::register_databases(&paths)?;
+    if databases.is_empty() && !paths.is_empty() {
+        eprintln!("error: no sqlite database file found in {}", paths.join(", "));
+        return Ok(EXIT_FAILURE);
+    }
+    for database in databases.iter() {
+        println!("serving {} as /db/{}", database.path, database.id);
+    }
+
+    match matches.value_of("page-cache-size").unwrap().parse::<usize>() {
+        Ok(page_cache_size) => server::set_page_cache_capacity(page_cache_size),
+        Err(_) => {
+            eprintln!("error: the page cache size should be a number of bytes");
+            return Ok(EXIT_USAGE);
+        },
+    }
+
+    // uploads write the files of any client to the disk, they are only accepted when asked for
+    let allow_upload = matches.is_present("allow-upload") || matches.is_present("upload-dir");
+    if paths.is_empty() && !allow_upload {
+        eprintln!("error: nothing to serve, give a database file or accept uploads with --allow-upload");
+        return Ok(EXIT_USAGE);
+    }
+    if allow_upload {
+        let upload_directory = match matches.value_of("upload-dir") {
+            Some(upload_directory) => PathBuf::from(upload_directory),
+            None => std::env::temp_dir().join("sqlite-dissect-uploads"),
+        };
+        let max_upload_size = match matches.value_of("max-upload-size").unwrap().parse::<usize>() {
+            Ok(max_upload_size) => max_upload_size,
+            Err(_) => {
+                eprintln!("error: the max upload size should be a number of bytes");
+                return Ok(EXIT_USAGE);
+            },
+        };
+        let upload_ttl = match matches.value_of("upload-ttl").unwrap().parse::<u64>() {
+            Ok(upload_ttl) => upload_ttl,
+            Err(_) => {
+                eprintln!("error: the upload ttl should be a number of seconds");
+                return Ok(EXIT_USAGE);
+            },
+        };
+        let upload_workspace = UploadWorkspace::new(&upload_directory, max_upload_size, upload_ttl)?;
+        println!("accepting uploads into {}", upload_directory.display());
+        server::enable_uploads(upload_workspace);
+    }
+
+    actix_web::rt::System::new("sqlite-dissect").block_on(server::serve(bind.to_string()))?;
+    Ok(EXIT_SUCCESS)
+}
+
+fn main() {
+    let matches = App::new("Sqlite Database File Dissect")
+                    .version("1.0")
+                    .author("Xiaoji Tian")
+                    .about("Dissect sqlite database file")
+                    .setting(AppSettings::SubcommandRequiredElseHelp)
+                    .subcommand(SubCommand::with_name("header")
+                                .about("Show the database header and what is wrong with it, exits with 3 on errors")
+                                .setting(AppSettings::SubcommandsNegateReqs)
+                                .setting(AppSettings::ArgsNegateSubcommands)
+                                .arg(file_arg())
+                                .arg(format_arg())
+                                .subcommand(SubCommand::with_name("set")
+                                            .about("Set a header field, without --output or --in-place the change is only shown")
+                                            .setting(AppSettings::AllowNegativeNumbers)
+                                            .arg(file_arg())
+                                            .arg(Arg::with_name("FIELD")
+                                                 .help("header field, as named by the header command")
+                                                 .required(true)
+                                                 .index(2))
+                                            .arg(Arg::with_name("VALUE")
+                                                 .help("new value, enums take the name or the number of their variant")
+                                                 .required(true)
+                                                 .index(3))
+                                            .arg(Arg::with_name("output")
+                                                 .short("o")
+                                                 .long("output")
+                                                 .value_name("FILE")
+                                                 .conflicts_with("in-place")
+                                                 .help("copy the database to FILE and change the copy"))
+                                            .arg(Arg::with_name("in-place")
+                                                 .long("in-place")
+                                                 .help("change the database itself once it is copied to the backup"))
+                                            .arg(Arg::with_name("backup")
+                                                 .long("backup")
+                                                 .value_name("FILE")
+                                                 .requires("in-place")
+                                                 .help("backup written by --in-place, FILE.bak by default"))
+                                            .arg(Arg::with_name("force")
+                                                 .long("force")
+                                                 .help("change page_size, bytes_at_unused_page_end or text_encoding, which the pages are not rewritten for"))
+                                            .arg(format_arg())))
+                    .subcommand(SubCommand::with_name("page")
+                                .about("Show one btree page")
+                                .arg(file_arg())
+                                .arg(Arg::with_name("PAGE")
+                                     .help("page number, starting from 1")
+                                     .required(true)
+                                     .index(2))
+                                .arg(format_arg()))
+                    .subcommand(SubCommand::with_name("tree")
+                                .about("Show the page hierarchy of every btree, cycles and bad child pointers are reported and exit with 3")
+                                .arg(file_arg())
+                                .arg(format_arg()))
+                    .subcommand(SubCommand::with_name("dump")
+                                .about("Dump every page of the file")
+                                .arg(file_arg())
+                                .arg(format_arg())
+                                .arg(Arg::with_name("template")
+                                     .long("template")
+                                     .value_name("TEMPLATE")
+                                     .help("render the pages with a TinyTemplate file, e.g. templates/page_navigation.tt"))
+                                .arg(Arg::with_name("parallel")
+                                     .long("parallel")
+                                     .help("parse the pages on every core, they are still written in page order")))
+                    .subcommand(SubCommand::with_name("schema")
+                                .about("Show the sqlite_schema table")
+                                .arg(file_arg())
+                                .arg(format_arg()))
+                    .subcommand(SubCommand::with_name("stats")
+                                .about("Decode sqlite_stat1 and sqlite_stat4 and compare them with the btrees")
+                                .arg(file_arg())
+                                .arg(format_arg()))
+                    .subcommand(SubCommand::with_name("space-usage")
+                                .about("Report the space used by every table and index, like sqlite3_analyzer")
+                                .arg(file_arg())
+                                .arg(format_arg()))
+                    .subcommand(SubCommand::with_name("check")
+                                .about("Check every page is used exactly once, exits with 3 when problems are found")
+                                .arg(file_arg())
+                                .arg(format_arg()))
+                    .subcommand(SubCommand::with_name("diff")
+                                .about("Compare two database files page by page, and the cells of modified btree pages")
+                                .arg(Arg::with_name("OLD")
+                                     .help("sqlite database file before the change")
+                                     .required(true)
+                                     .index(1))
+                                .arg(Arg::with_name("NEW")
+                                     .help("sqlite database file after the change")
+                                     .required(true)
+                                     .index(2))
+                                .arg(format_arg()))
+                    .subcommand(SubCommand::with_name("row-diff")
+                                .about("Compare the rows of a table in two database files")
+                                .arg(Arg::with_name("OLD")
+                                     .help("sqlite database file before the change")
+                                     .required(true)
+                                     .index(1))
+                                .arg(Arg::with_name("NEW")
+                                     .help("sqlite database file after the change")
+                                     .required(true)
+                                     .index(2))
+                                .arg(Arg::with_name("TABLE")
+                                     .help("table name")
+                                     .required(true)
+                                     .index(3))
+                                .arg(format_arg().possible_values(&["text", "json", "sql"])
+                                     .help("output format, sql prints the statements turning OLD into NEW")))
+                    .subcommand(SubCommand::with_name("export")
+                                .about("Export the rows of a table as CSV or JSON Lines")
+                                .arg(file_arg())
+                                .arg(Arg::with_name("TABLE")
+                                     .help("table name")
+                                     .required(true)
+                                     .index(2))
+                                .arg(Arg::with_name("format")
+                                     .long("format")
+                                     .value_name("FORMAT")
+                                     .possible_values(&["csv", "jsonl"])
+                                     .default_value("csv")
+                                     .help("output format"))
+                                .arg(Arg::with_name("output")
+                                     .short("o")
+                                     .long("output")
+                                     .value_name("FILE")
+                                     .help("file written instead of the standard output"))
+                                .arg(Arg::with_name("delimiter")
+                                     .long("delimiter")
+                                     .value_name("CHAR")
+                                     .default_value(",")
+                                     .help("csv field delimiter, tab or \\t for tabs"))
+                                .arg(Arg::with_name("quote")
+                                     .long("quote")
+                                     .value_name("QUOTING")
+                                     .possible_values(&["minimal", "all", "non-numeric", "never"])
+                                     .default_value("minimal")
+                                     .help("csv fields to quote, minimal quotes the fields holding a delimiter, a quote or a line break"))
+                                .arg(Arg::with_name("blob")
+                                     .long("blob")
+                                     .value_name("ENCODING")
+                                     .possible_values(&["hex", "base64"])
+                                     .default_value("hex")
+                                     .help("text encoding of BLOB values"))
+                                .arg(Arg::with_name("no-header")
+                                     .long("no-header")
+                                     .help("leave the column names out of the csv")))
+                    .subcommand(SubCommand::with_name("sqldump")
+                                .about("Dump the schema and every readable row as SQL like sqlite3 .dump, exits with 3 when pages were skipped")
+                                .arg(file_arg())
+                                .arg(Arg::with_name("output")
+                                     .short("o")
+                                     .long("output")
+                                     .value_name("FILE")
+                                     .help("file written instead of the standard output")))
+                    .subcommand(SubCommand::with_name("repair")
+                                .about("Write every recoverable row into a new database with the same schema, exits with 3 when pages or rows were lost")
+                                .arg(file_arg())
+                                .arg(Arg::with_name("OUTPUT")
+                                     .help("database file written")
+                                     .required(true)
+                                     .index(2))
+                                .arg(Arg::with_name("force")
+                                     .long("force")
+                                     .help("overwrite OUTPUT when it exists"))
+                                .arg(format_arg()))
+                    .subcommand(SubCommand::with_name("graph")
+                                .about("Export the btrees with their child pointers and overflow chains as Graphviz DOT or SVG")
+                                .arg(file_arg())
+                                .arg(Arg::with_name("format")
+                                     .long("format")
+                                     .value_name("FORMAT")
+                                     .possible_values(&["dot", "svg", "json"])
+                                     .default_value("dot")
+                                     .help("output format, svg is laid out without graphviz"))
+                                .arg(Arg::with_name("output")
+                                     .short("o")
+                                     .long("output")
+                                     .value_name("FILE")
+                                     .help("file written instead of the standard output")))
+                    .subcommand(SubCommand::with_name("tui")
+                                .about("Browse the btrees, pages and their bytes in the terminal")
+                                .arg(file_arg()))
+                    .subcommand(SubCommand::with_name("serve")
+                                .about("Serve the web visualization of database files, or directories of them")
+                                .arg(Arg::with_name("PATH")
+                                     .help("sqlite database files or directories")
+                                     .multiple(true)
+                                     .index(1))
+                                .arg(Arg::with_name("file")
+                                     .short("f")
+                                     .long("file")
+                                     .value_name("FILE")
+                                     .multiple(true)
+                                     .number_of_values(1)
+                                     .help("sqlite database file name, may be repeated"))
+                                .arg(Arg::with_name("bind")
+                                     .long("bind")
+                                     .value_name("ADDRESS")
+                                     .default_value("127.0.0.1:8080")
+                                     .help("address the HTTP server listens on"))
+                                .arg(Arg::with_name("page-cache-size")
+                                     .long("page-cache-size")
+                                     .value_name("BYTES")
+                                     .default_value("67108864")
+                                     .help("memory kept for parsed pages, shared by every served database"))
+                                .arg(Arg::with_name("allow-upload")
+                                     .long("allow-upload")
+                                     .help("accept database uploads, they are refused by default"))
+                                .arg(Arg::with_name("upload-dir")
+                                     .long("upload-dir")
+                                     .value_name("DIR")
+                                     .help("accept database uploads and write them to DIR, a temporary directory with --allow-upload"))
+                                .arg(Arg::with_name("max-upload-size")
+                                     .long("max-upload-size")
+                                     .value_name("BYTES")
+                                     .default_value("268435456")
+                                     .help("largest database file accepted by the upload"))
+                                .arg(Arg::with_name("upload-ttl")
+                                     .long("upload-ttl")
+                                     .value_name("SECONDS")
+                                     .default_value("3600")
+                                     .help("seconds an uploaded database stays available")))
+                    .get_matches_safe()
+                    .unwrap_or_else(|e| {
+                        if e.use_stderr() {
+                            eprintln!("{}", e.message);
+                            process::exit(EXIT_USAGE);
+                        }
+                        println!("{}", e.message);
+                        process::exit(EXIT_SUCCESS);
+                    });
+
+    let result = match matches.subcommand() {
+        ("header", Some(sub_matches)) => header(sub_matches),
+        ("page", Some(sub_matches)) => page(sub_matches),
+        ("tree", Some(sub_matches)) => tree(sub_matches),
+        ("dump", Some(sub_matches)) => dump(sub_matches),
+        ("schema", Some(sub_matches)) => schema(sub_matches),
+        ("stats", Some(sub_matches)) => statistics(sub_matches),
+        ("space-usage", Some(sub_matches)) => space_usage(sub_matches),
+        ("check", Some(sub_matches)) => check(sub_matches),
+        ("diff", Some(sub_matches)) => diff(sub_matches),
+        ("row-diff", Some(sub_matches)) => row_diff(sub_matches),
+        ("export", Some(sub_matches)) => export(sub_matches),
+        ("sqldump", Some(sub_matches)) => sqldump(sub_matches),
+        ("repair", Some(sub_matches)) => repair(sub_matches),
+        ("graph", Some(sub_matches)) => graph(sub_matches),
+        ("tui", Some(sub_matches)) => tui(sub_matches),
+        ("serve", Some(sub_matches)) => serve(sub_matches),
+        _ => Ok(EXIT_USAGE),
+    };
+
+    match result {
+        Ok(exit_code) => process::exit(exit_code),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(EXIT_FAILURE);
+        },
+    }
+}
@@ -0,0 +1,162 @@
+pub mod app;
+
+use std::io::{self, Stdout};
+use std::panic;
+use std::sync::Arc;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::cursor::Show;
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use crate::components::database::Database;
+use crate::tui::app::{App, Key, Pane};
+use crate::utils::error::MyError;
+
+const HELP: &str = " tab pane  enter follow/expand  h back  [ ] prev/next page  f freelist  q quit ";
+
+fn pane_block(title: String, focused: bool) -> Block<'static> {
+    let style = if focused { Style::default().fg(Color::Yellow) } else { Style::default() };
+    Block::default().borders(Borders::ALL).border_style(style).title(title)
+}
+
+fn highlight_style() -> Style {
+    Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+}
+
+/// Tree pane on the left, the page above its hex dump on the right.
+pub fn draw<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let outer = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .split(f.size());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(outer[0]);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(columns[1]);
+
+    draw_tree(f, app, columns[0]);
+    draw_page(f, app, right[0]);
+    draw_hex(f, app, right[1]);
+    f.render_widget(Paragraph::new(HELP).style(Style::default().add_modifier(Modifier::REVERSED)), outer[1]);
+}
+
+fn draw_tree<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app.tree.iter()
+        .map(|row| {
+            let marker = match (row.children.is_empty(), row.expanded) {
+                (true, _) => " ",
+                (false, true) => "-",
+                (false, false) => "+",
+            };
+            ListItem::new(format!("{:indent$}{} {}", "", marker, row.label, indent = row.depth * 2))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.tree_selected));
+    let list = List::new(items)
+        .block(pane_block("btrees".to_string(), app.focus == Pane::Tree))
+        .highlight_style(highlight_style());
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_page<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let block = pane_block(app.view.title.clone(), app.focus == Pane::Page);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(app.view.header.len() as u16), Constraint::Min(0)])
+        .split(inner);
+    f.render_widget(Paragraph::new(app.view.header.join("\n")), parts[0]);
+
+    let items: Vec<ListItem> = app.view.items.iter()
+        .map(|item| ListItem::new(item.text.clone()))
+        .collect();
+    let mut state = ListState::default();
+    if !items.is_empty() {
+        state.select(Some(app.item_selected));
+    }
+    f.render_stateful_widget(List::new(items).highlight_style(highlight_style()), parts[1], &mut state);
+}
+
+fn draw_hex<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let rows = app.hex_rows();
+    let visible = rows.iter()
+        .skip(app.hex_scroll)
+        .take(area.height as usize)
+        .cloned()
+        .collect::<Vec<String>>()
+        .join("\n");
+    let title = format!("hex, {} bytes", app.page_bytes().len());
+    f.render_widget(Paragraph::new(visible).block(pane_block(title, app.focus == Pane::Hex)), area);
+}
+
+fn key_of(code: KeyCode) -> Option<Key> {
+    match code {
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::PageUp => Some(Key::PageUp),
+        KeyCode::PageDown => Some(Key::PageDown),
+        KeyCode::Enter | KeyCode::Right => Some(Key::Enter),
+        KeyCode::Backspace | KeyCode::Left => Some(Key::Back),
+        KeyCode::Tab => Some(Key::Tab),
+        KeyCode::Esc => Some(Key::Char('q')),
+        KeyCode::Char(c) => Some(Key::Char(c)),
+        _ => None,
+    }
+}
+
+fn event_loop(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App) -> Result<(), MyError> {
+    while !app.quit {
+        terminal.draw(|f| draw(f, app))?;
+        if let Event::Key(key) = event::read()? {
+            // some terminals report the release of a key as well
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(key) = key_of(key.code) {
+                app.handle_key(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Browse the file in the terminal until q is pressed, the terminal is restored on errors and
+/// panics too.
+pub fn run(db: &Database) -> Result<(), MyError> {
+    let mut app = App::new(db)?;
+
+    // leave raw mode and the alternate screen before the previous hook prints a panic, otherwise
+    // the message is lost with the screen and the shell is left in raw mode
+    let previous_hook = Arc::new(panic::take_hook());
+    let hook = previous_hook.clone();
+    panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+        hook(info);
+    }));
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = event_loop(&mut terminal, &mut app);
+    panic::set_hook(Box::new(move |info| previous_hook(info)));
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
@@ -0,0 +1,406 @@
+use std::collections::HashSet;
+
+use crate::components::database::Database;
+use crate::components::graph::GraphNodeKind;
+use crate::components::record::Column;
+use crate::components::schema::SQLITE_SCHEMA_ROOT_PAGE;
+use crate::utils::convert::TryFromBytes;
+use crate::utils::error::MyError;
+
+const HEX_ROW_LENGTH: usize = 16;
+// longest record preview shown for a cell
+const PREVIEW_LENGTH: usize = 60;
+
+/// What a page is used for, found by walking the btrees and the freelist once.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageRole {
+    Btree(String),
+    Overflow(String),
+    FreelistTrunk,
+    FreelistLeaf,
+    LockByte,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pane {
+    Tree,
+    Page,
+    Hex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Enter,
+    Back,
+    Tab,
+    Char(char),
+}
+
+/// A line of the tree pane, a btree page indented by its depth.
+#[derive(Debug, Clone)]
+pub struct TreeRow {
+    pub depth: usize,
+    pub page_number: u32,
+    pub label: String,
+    pub children: Vec<u32>,
+    pub expanded: bool,
+}
+
+/// A line of the page pane, enter follows its target page.
+#[derive(Debug, Clone)]
+pub struct PageItem {
+    pub text: String,
+    pub target: Option<u32>,
+    // where the item starts in the page, the hex pane scrolls there
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PageView {
+    pub title: String,
+    pub header: Vec<String>,
+    pub items: Vec<PageItem>,
+}
+
+/// The state of the terminal browser, kept apart from the drawing so it can be driven without a terminal.
+pub struct App<'a> {
+    db: &'a Database,
+    roles: Vec<PageRole>,
+    history: Vec<u32>,
+    pub tree: Vec<TreeRow>,
+    pub tree_selected: usize,
+    pub page_number: u32,
+    pub view: PageView,
+    pub item_selected: usize,
+    pub hex_scroll: usize,
+    pub focus: Pane,
+    pub quit: bool,
+}
+
+fn page_roles(db: &Database) -> Result<Vec<PageRole>, MyError> {
+    // indexed by page number, 0 is unused
+    let mut roles = vec![PageRole::Unknown; db.page_count() as usize + 1];
+    for node in db.btree_graph()?.nodes {
+        if let Some(role) = roles.get_mut(node.page_number as usize) {
+            *role = match node.kind {
+                GraphNodeKind::Overflow => PageRole::Overflow(node.btree),
+                _ => PageRole::Btree(node.btree),
+            };
+        }
+    }
+
+    let mut visited = HashSet::new();
+    let mut trunk_page_number = db.header().first_freelist_trunk_page_number;
+    while trunk_page_number != 0 && visited.insert(trunk_page_number) {
+        let (next, leaves) = match freelist_trunk(db, trunk_page_number) {
+            Ok(trunk) => trunk,
+            Err(_) => break,
+        };
+        if let Some(role) = roles.get_mut(trunk_page_number as usize) {
+            *role = PageRole::FreelistTrunk;
+        }
+        for leaf in leaves {
+            if let Some(role) = roles.get_mut(leaf as usize) {
+                *role = PageRole::FreelistLeaf;
+            }
+        }
+        trunk_page_number = next;
+    }
+
+    if let Some(page_number) = db.lock_byte_page() {
+        roles[page_number as usize] = PageRole::LockByte;
+    }
+    Ok(roles)
+}
+
+// The next trunk page and the leaf pages listed by a freelist trunk page.
+fn freelist_trunk(db: &Database, page_number: u32) -> Result<(u32, Vec<u32>), MyError> {
+    let bytes = db.page_bytes(page_number)?;
+    let next = u32::try_from_be_bytes(&bytes[0..4])?;
+    let leaf_number = u32::try_from_be_bytes(&bytes[4..8])? as usize;
+    let leaves = (0..leaf_number.min((bytes.len() - 8) / 4))
+        .map(|i| u32::try_from_be_bytes(&bytes[8 + i * 4..12 + i * 4]))
+        .collect::<Result<Vec<u32>, MyError>>()?;
+    Ok((next, leaves))
+}
+
+fn preview(columns: &[Column]) -> String {
+    let text = columns.iter().map(|column| column.to_sql_literal()).collect::<Vec<String>>().join(", ");
+    match text.char_indices().nth(PREVIEW_LENGTH) {
+        Some((idx, _)) => format!("{}...", &text[..idx]),
+        None => text,
+    }
+}
+
+impl<'a> App<'a> {
+    pub fn new(db: &'a Database) -> Result<Self, MyError> {
+        let mut tree = vec![TreeRow {
+            depth: 0,
+            page_number: SQLITE_SCHEMA_ROOT_PAGE,
+            label: "table sqlite_schema".to_string(),
+            children: Vec::new(),
+            expanded: false,
+        }];
+        tree.extend(db.schema()?.into_iter()
+            .filter(|entry| entry.root_page > 0)
+            .map(|entry| TreeRow {
+                depth: 0,
+                page_number: entry.root_page,
+                label: format!("{} {}", entry.entry_type, entry.name),
+                children: Vec::new(),
+                expanded: false,
+            }));
+
+        let mut app = App {
+            db,
+            roles: page_roles(db)?,
+            history: Vec::new(),
+            tree,
+            tree_selected: 0,
+            page_number: SQLITE_SCHEMA_ROOT_PAGE,
+            view: PageView::default(),
+            item_selected: 0,
+            hex_scroll: 0,
+            focus: Pane::Tree,
+            quit: false,
+        };
+        for row in app.tree.iter_mut() {
+            row.children = child_pages(db, row.page_number);
+        }
+        app.show_page(SQLITE_SCHEMA_ROOT_PAGE);
+        Ok(app)
+    }
+
+    pub fn role(&self, page_number: u32) -> PageRole {
+        self.roles.get(page_number as usize).cloned().unwrap_or(PageRole::Unknown)
+    }
+
+    pub fn page_bytes(&self) -> &[u8] {
+        self.db.page_bytes(self.page_number).unwrap_or(&[])
+    }
+
+    /// The hex pane, 16 bytes a row with their offset in the page.
+    pub fn hex_rows(&self) -> Vec<String> {
+        self.page_bytes().chunks(HEX_ROW_LENGTH)
+            .enumerate()
+            .map(|(idx, chunk)| {
+                let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+                let ascii: String = chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+                format!("{:04x}  {:<47}  {}", idx * HEX_ROW_LENGTH, hex.join(" "), ascii)
+            })
+            .collect()
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        match (key, self.focus) {
+            (Key::Char('q'), _) => self.quit = true,
+            (Key::Tab, Pane::Tree) => self.focus = Pane::Page,
+            (Key::Tab, Pane::Page) => self.focus = Pane::Hex,
+            (Key::Tab, Pane::Hex) => self.focus = Pane::Tree,
+            (Key::Back, _) | (Key::Char('h'), _) => self.back(),
+            (Key::Char('f'), _) => {
+                let trunk_page_number = self.db.header().first_freelist_trunk_page_number;
+                if trunk_page_number != 0 {
+                    self.open_page(trunk_page_number);
+                }
+            },
+            (Key::Char('['), _) if self.page_number > 1 => self.open_page(self.page_number - 1),
+            (Key::Char(']'), _) if self.page_number < self.db.page_count() => self.open_page(self.page_number + 1),
+
+            (Key::Up, Pane::Tree) | (Key::Char('k'), Pane::Tree) => self.select_tree_row(self.tree_selected.saturating_sub(1)),
+            (Key::Down, Pane::Tree) | (Key::Char('j'), Pane::Tree) => self.select_tree_row(self.tree_selected + 1),
+            (Key::Enter, Pane::Tree) => self.toggle_tree_row(),
+
+            (Key::Up, Pane::Page) | (Key::Char('k'), Pane::Page) => self.select_item(self.item_selected.saturating_sub(1)),
+            (Key::Down, Pane::Page) | (Key::Char('j'), Pane::Page) => self.select_item(self.item_selected + 1),
+            (Key::PageUp, Pane::Page) => self.select_item(self.item_selected.saturating_sub(10)),
+            (Key::PageDown, Pane::Page) => self.select_item(self.item_selected + 10),
+            (Key::Enter, Pane::Page) => self.follow(),
+
+            (Key::Up, Pane::Hex) | (Key::Char('k'), Pane::Hex) => self.hex_scroll = self.hex_scroll.saturating_sub(1),
+            (Key::Down, Pane::Hex) | (Key::Char('j'), Pane::Hex) => self.scroll_hex(1),
+            (Key::PageUp, Pane::Hex) => self.hex_scroll = self.hex_scroll.saturating_sub(16),
+            (Key::PageDown, Pane::Hex) => self.scroll_hex(16),
+            _ => {},
+        }
+    }
+
+    /// Show a page and remember the current one for `back`.
+    pub fn open_page(&mut self, page_number: u32) {
+        if page_number != self.page_number {
+            self.history.push(self.page_number);
+        }
+        self.show_page(page_number);
+    }
+
+    pub fn back(&mut self) {
+        if let Some(page_number) = self.history.pop() {
+            self.show_page(page_number);
+        }
+    }
+
+    /// Follow the child pointer, overflow page or freelist link of the selected item.
+    pub fn follow(&mut self) {
+        if let Some(target) = self.view.items.get(self.item_selected).and_then(|item| item.target) {
+            self.open_page(target);
+            self.focus = Pane::Page;
+        }
+    }
+
+    fn scroll_hex(&mut self, rows: usize) {
+        let last_row = self.page_bytes().len().saturating_sub(1) / HEX_ROW_LENGTH;
+        self.hex_scroll = (self.hex_scroll + rows).min(last_row);
+    }
+
+    fn select_item(&mut self, idx: usize) {
+        if self.view.items.is_empty() {
+            return;
+        }
+        self.item_selected = idx.min(self.view.items.len() - 1);
+        if let Some(offset) = self.view.items[self.item_selected].offset {
+            self.hex_scroll = offset / HEX_ROW_LENGTH;
+        }
+    }
+
+    fn select_tree_row(&mut self, idx: usize) {
+        if idx < self.tree.len() {
+            // moving in the tree starts a new trail of followed pages
+            self.tree_selected = idx;
+            self.history.clear();
+            self.show_page(self.tree[idx].page_number);
+        }
+    }
+
+    // Expanding a row lists the child pages under it, collapsing drops its whole subtree.
+    fn toggle_tree_row(&mut self) {
+        let idx = self.tree_selected;
+        let depth = self.tree[idx].depth;
+        if self.tree[idx].expanded {
+            let end = self.tree[idx + 1..].iter()
+                .position(|row| row.depth <= depth)
+                .map_or(self.tree.len(), |position| idx + 1 + position);
+            self.tree.drain(idx + 1..end);
+            self.tree[idx].expanded = false;
+            return;
+        }
+
+        let rows: Vec<TreeRow> = self.tree[idx].children.iter()
+            .map(|&page_number| {
+                let children = child_pages(self.db, page_number);
+                let label = match self.db.read_page(page_number) {
+                    Ok(page) => format!("page {} {:?} ({} cells)", page_number, page.header.page_type, page.header.cell_number),
+                    Err(_) => format!("page {} unreadable", page_number),
+                };
+                TreeRow { depth: depth + 1, page_number, label, children, expanded: false }
+            })
+            .collect();
+        self.tree[idx].expanded = true;
+        self.tree.splice(idx + 1..idx + 1, rows);
+    }
+
+    fn show_page(&mut self, page_number: u32) {
+        self.page_number = page_number;
+        self.view = self.page_view(page_number);
+        self.item_selected = 0;
+        self.hex_scroll = 0;
+    }
+
+    fn page_view(&self, page_number: u32) -> PageView {
+        let role = self.role(page_number);
+        let mut view = PageView {
+            title: format!("page {} of {}", page_number, self.db.page_count()),
+            ..Default::default()
+        };
+
+        match role {
+            PageRole::Btree(name) => {
+                view.title = format!("{}, {}", view.title, name);
+                let page = match self.db.read_page(page_number) {
+                    Ok(page) => page,
+                    Err(e) => {
+                        view.header.push(format!("can't be parsed: {}", e));
+                        return view;
+                    },
+                };
+                let header = &page.header;
+                view.header.push(format!("page type {:?}, {} cells", header.page_type, header.cell_number));
+                view.header.push(format!("cell content area at {}, first freeblock at {}, {} fragmented bytes",
+                    header.cell_content_area_offset, header.first_free_block_offset, header.fragmented_free_bytes));
+                for (idx, (cell, pointer)) in page.cells.iter().zip(page.cell_pointers.iter()).enumerate() {
+                    let mut text = format!("cell {} @{}", idx, pointer.offset);
+                    if let Some(left_child) = cell.left_child_page_number {
+                        text.push_str(&format!(" -> page {}", left_child));
+                    }
+                    if let Some(row_id) = cell.row_id {
+                        text.push_str(&format!(" rowid {}", row_id));
+                    }
+                    if let Some(overflow_page_number) = cell.overflow_page_number {
+                        text.push_str(&format!(" overflow -> page {}", overflow_page_number));
+                    }
+                    if let Ok(Some(record)) = self.db.read_record(cell) {
+                        text.push_str(&format!(" ({})", preview(&record.columns)));
+                    }
+                    view.items.push(PageItem {
+                        text,
+                        target: cell.left_child_page_number.or(cell.overflow_page_number),
+                        offset: Some(pointer.offset as usize),
+                    });
+                }
+                if let Some(right_most_pointer) = header.right_most_pointer {
+                    view.items.push(PageItem {
+                        text: format!("right most pointer -> page {}", right_most_pointer),
+                        target: Some(right_most_pointer),
+                        offset: None,
+                    });
+                }
+            },
+            PageRole::Overflow(name) => {
+                view.title = format!("{}, overflow of {}", view.title, name);
+                let next = self.db.page_bytes(page_number).ok()
+                    .and_then(|bytes| u32::try_from_be_bytes(&bytes[0..4]).ok())
+                    .unwrap_or(0);
+                view.items.push(PageItem {
+                    text: if next == 0 { "last page of the chain".to_string() } else { format!("next overflow page -> page {}", next) },
+                    target: if next == 0 { None } else { Some(next) },
+                    offset: Some(0),
+                });
+            },
+            PageRole::FreelistTrunk => {
+                view.title = format!("{}, freelist trunk", view.title);
+                if let Ok((next, leaves)) = freelist_trunk(self.db, page_number) {
+                    view.header.push(format!("{} leaf pages", leaves.len()));
+                    view.items.push(PageItem {
+                        text: if next == 0 { "last trunk page".to_string() } else { format!("next trunk -> page {}", next) },
+                        target: if next == 0 { None } else { Some(next) },
+                        offset: Some(0),
+                    });
+                    view.items.extend(leaves.iter().enumerate().map(|(idx, &leaf)| PageItem {
+                        text: format!("leaf {} -> page {}", idx, leaf),
+                        target: Some(leaf),
+                        offset: Some(8 + idx * 4),
+                    }));
+                }
+            },
+            PageRole::FreelistLeaf => view.title = format!("{}, freelist leaf", view.title),
+            PageRole::LockByte => view.title = format!("{}, lock-byte page", view.title),
+            PageRole::Unknown => view.title = format!("{}, not referenced", view.title),
+        }
+        view
+    }
+}
+
+// The child pages of a btree page in key order, none for leaves and unreadable pages.
+fn child_pages(db: &Database, page_number: u32) -> Vec<u32> {
+    match db.read_page(page_number) {
+        Ok(page) => page.cells.iter()
+            .filter_map(|cell| cell.left_child_page_number)
+            .chain(page.header.right_most_pointer)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
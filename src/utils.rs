@@ -2,3 +2,4 @@ pub mod convert;
 pub mod string;
 pub mod error;
 pub mod varint;
+pub mod json;
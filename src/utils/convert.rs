@@ -50,3 +50,8 @@ impl<T: TryFromBytes> TryFromBytes for Vec<T>
        Ok(result)
     }
 }
+
+/// The inverse of `TryFromBytes`, the file format is big-endian only.
+pub trait ToBytes {
+    fn to_be_bytes(&self) -> Vec<u8>;
+}
@@ -1,4 +1,4 @@
-use std::{error::Error, fmt::write};
+use std::error::Error;
 use std::fmt;
 
 use serde_derive::Serialize;
@@ -13,6 +13,23 @@ pub enum ErrorKind {
     NotImplemented,
     InvalidVarInt,
     UnreachableCode,
+    IoError(String/*io error message*/),
+    PageNumberOutOfRange(u32/*page number*/, u32/*page count*/),
+    TableNotFound(String/*table name*/),
+    NotDatabaseFile,
+    UploadTooLarge(usize/*max upload size*/),
+    SchemaParseError(String/*sql*/),
+    BtreeCorrupted(String/*problem*/),
+    CellIndexOutOfRange(usize/*cell index*/, usize/*cell count*/),
+    PageFull(usize/*bytes needed*/, usize/*bytes free*/),
+    InvalidPageSize(usize/*page size*/),
+    DuplicateRowId(i64/*rowid*/),
+    DuplicateKey(String/*table name*/),
+    Unsupported(String/*what isn't supported*/),
+    UnknownHeaderField(String/*field*/),
+    InvalidHeaderValue(String/*field*/, String/*value*/, String/*expected*/),
+    LockBytePage(u32/*page number*/),
+    PayloadTooLarge(u64/*payload length*/),
 }
 
 #[derive(Debug, Serialize)]
@@ -29,6 +46,23 @@ impl fmt::Display for MyError {
             ErrorKind::NotImplemented => write!(f, "Function not implemented."),
             ErrorKind::InvalidVarInt => write!(f, "Invalid Variable-Length Integer."),
             ErrorKind::UnreachableCode => write!(f, "Unreachable code."),
+            ErrorKind::IoError(msg) => write!(f, "IO error: {}", msg),
+            ErrorKind::PageNumberOutOfRange(page_number, page_count) => write!(f, "The page number {} is out of range, the database has {} pages.", page_number, page_count),
+            ErrorKind::TableNotFound(table_name) => write!(f, "The table {} doesn't exist.", table_name),
+            ErrorKind::NotDatabaseFile => write!(f, "The file doesn't start with the sqlite header string."),
+            ErrorKind::UploadTooLarge(max_size) => write!(f, "The upload is larger than {} bytes.", max_size),
+            ErrorKind::SchemaParseError(sql) => write!(f, "The columns can't be parsed out of {}", sql),
+            ErrorKind::BtreeCorrupted(problem) => write!(f, "The btree is corrupted: {}", problem),
+            ErrorKind::CellIndexOutOfRange(index, cell_count) => write!(f, "The cell index {} is out of range, the page has {} cells.", index, cell_count),
+            ErrorKind::PageFull(needed, free) => write!(f, "The cell needs {} bytes but only {} bytes are free on the page.", needed, free),
+            ErrorKind::InvalidPageSize(page_size) => write!(f, "The page size {} is not a power of two between 512 and 65536 leaving 480 usable bytes.", page_size),
+            ErrorKind::DuplicateRowId(row_id) => write!(f, "The rowid {} is used by more than one row.", row_id),
+            ErrorKind::DuplicateKey(table_name) => write!(f, "A PRIMARY KEY of the table {} is used by more than one row.", table_name),
+            ErrorKind::Unsupported(what) => write!(f, "Not supported: {}", what),
+            ErrorKind::UnknownHeaderField(field) => write!(f, "The database header has no field {}.", field),
+            ErrorKind::InvalidHeaderValue(field, value, expected) => write!(f, "The value {} is not valid for {}, expected {}.", value, field, expected),
+            ErrorKind::LockBytePage(page_number) => write!(f, "The page {} holds the lock bytes, it is never a btree page.", page_number),
+            ErrorKind::PayloadTooLarge(payload_length) => write!(f, "The payload length {} doesn't fit in memory.", payload_length),
         }; 
         result
     }
@@ -42,6 +76,16 @@ impl MyError {
             error_kind,
         }
     }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.error_kind
+    }
+}
+
+impl From<std::io::Error> for MyError {
+    fn from(e: std::io::Error) -> Self {
+        MyError::new(ErrorKind::IoError(e.to_string()))
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -50,6 +94,8 @@ pub enum HttpErrorKind{
     BadClientData(String),
     Timeout(String),
     PageIndexError(usize),
+    DatabaseNotFound(String),
+    PayloadTooLarge(usize),
 }
 
 #[derive(Debug, Serialize)]
@@ -64,6 +110,8 @@ impl fmt::Display for HttpError {
             HttpErrorKind::BadClientData(msg) => write!(f, "{}", msg),
             HttpErrorKind::Timeout(msg) => write!(f, "{}", msg),
             HttpErrorKind::PageIndexError(page_idx) => write!(f, "Page index error: {}", page_idx),
+            HttpErrorKind::DatabaseNotFound(id) => write!(f, "Database not found: {}", id),
+            HttpErrorKind::PayloadTooLarge(max_size) => write!(f, "Payload larger than {} bytes", max_size),
         }; 
         result
     }
@@ -91,6 +139,8 @@ impl error::ResponseError for HttpError {
             HttpErrorKind::BadClientData(_) => StatusCode::BAD_REQUEST,
             HttpErrorKind::Timeout(_) => StatusCode::BAD_GATEWAY,
             HttpErrorKind::PageIndexError(_) => StatusCode::BAD_REQUEST,
+            HttpErrorKind::DatabaseNotFound(_) => StatusCode::NOT_FOUND,
+            HttpErrorKind::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
         }
     }
 }
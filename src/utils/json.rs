@@ -0,0 +1,38 @@
+use serde::Serializer;
+use serde_json::Value;
+
+/// The largest integer a javascript number holds exactly, 2^53 - 1.
+pub const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+fn is_safe(magnitude: u64) -> bool {
+    magnitude <= MAX_SAFE_INTEGER as u64
+}
+
+/// A json number when javascript reads it back exactly, a string of its digits otherwise.
+pub fn integer_to_json(value: i64) -> Value {
+    if is_safe(value.unsigned_abs()) { Value::Number(value.into()) } else { Value::String(value.to_string()) }
+}
+
+// the serialize_with functions of the rowid and payload length fields, following integer_to_json
+
+pub fn serialize_i64<S: Serializer>(value: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+    if is_safe(value.unsigned_abs()) { serializer.serialize_i64(*value) } else { serializer.collect_str(value) }
+}
+
+pub fn serialize_u64<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    if is_safe(*value) { serializer.serialize_u64(*value) } else { serializer.collect_str(value) }
+}
+
+pub fn serialize_option_i64<S: Serializer>(value: &Option<i64>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serialize_i64(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn serialize_option_u64<S: Serializer>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serialize_u64(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
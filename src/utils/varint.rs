@@ -1,8 +1,71 @@
-use crate::utils::error::MyError;
+use crate::utils::error::{ErrorKind, MyError};
 
-use sqlite_varint::read_varint;
+// the ninth byte of a varint holds 8 bits, the values above need all nine bytes
+const MAX_EIGHT_BYTE_VALUE: u64 = 0x00ff_ffff_ffff_ffff;
+
+/// The value of the varint at the start of `bytes` and its length. The first 8 bytes hold 7 bits
+/// each, big-endian, until one has its high bit clear; a ninth byte holds 8 bits. A varint cut off
+/// by the end of the slice is `InvalidVarInt`.
+pub fn decode_varint(bytes: &[u8]) -> Result<(u64, usize), MyError> {
+    let mut value: u64 = 0;
+    for (idx, &byte) in bytes.iter().enumerate() {
+        if idx == 8 {
+            return Ok((value << 8 | byte as u64, 9));
+        }
+        value = value << 7 | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok((value, idx + 1));
+        }
+    }
+    Err(MyError::new(ErrorKind::InvalidVarInt))
+}
+
+/// `decode_varint` read as two's complement, the way rowids and record integers are stored.
+pub fn decode_varint_i64(bytes: &[u8]) -> Result<(i64, usize), MyError> {
+    let (value, length) = decode_varint(bytes)?;
+    Ok((value as i64, length))
+}
 
 pub fn decode_varint_to_usize(bytes: &[u8]) -> Result<(usize, usize), MyError> {
-    let (v, l) = read_varint(bytes);
-    Ok((v as usize, l))
+    let (value, length) = decode_varint(bytes)?;
+    Ok((value as usize, length))
+}
+
+/// Shortest varint of `value`, the ninth byte holds 8 bits.
+pub fn encode_varint(value: u64) -> Vec<u8> {
+    if value > MAX_EIGHT_BYTE_VALUE {
+        let mut bytes = vec![0; 9];
+        bytes[8] = value as u8;
+        let mut rest = value >> 8;
+        for byte in bytes[..8].iter_mut().rev() {
+            *byte = 0x80 | (rest & 0x7f) as u8;
+            rest >>= 7;
+        }
+        return bytes;
+    }
+    let mut bytes = Vec::with_capacity(9);
+    let mut rest = value;
+    loop {
+        bytes.push(0x80 | (rest & 0x7f) as u8);
+        rest >>= 7;
+        if rest == 0 {
+            break;
+        }
+    }
+    bytes[0] &= 0x7f;
+    bytes.reverse();
+    bytes
+}
+
+/// Negative values always take the nine bytes.
+pub fn encode_varint_i64(value: i64) -> Vec<u8> {
+    encode_varint(value as u64)
+}
+
+pub fn varint_length(value: u64) -> usize {
+    if value > MAX_EIGHT_BYTE_VALUE {
+        return 9;
+    }
+    let bits = 64 - value.leading_zeros() as usize;
+    bits.div_ceil(7).max(1)
 }
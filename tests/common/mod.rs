@@ -0,0 +1,26 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 4096;
+
+/// Chinook after ANALYZE with sqlite_stat1 and sqlite_stat4, put together in the temp directory
+/// from Chinook and the pages ANALYZE changed: each is its page number followed by its bytes.
+pub fn chinook_with_stat_tables() -> PathBuf {
+    let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+    let pages = fs::read("test-data/Chinook.db.4.analyze.stat-pages").unwrap();
+    for page in pages.chunks(4 + PAGE_SIZE) {
+        let page_number = u32::from_be_bytes(page[..4].try_into().unwrap()) as usize;
+        if bytes.len() < page_number * PAGE_SIZE {
+            bytes.resize(page_number * PAGE_SIZE, 0);
+        }
+        bytes[(page_number - 1) * PAGE_SIZE..page_number * PAGE_SIZE].copy_from_slice(&page[4..]);
+    }
+
+    // tests running at the same time write the same bytes, the rename keeps the file whole
+    let path = std::env::temp_dir().join("Chinook.db.4.analyze.stat");
+    let partial = std::env::temp_dir().join(format!("Chinook.db.4.analyze.stat.{:?}", std::thread::current().id()));
+    fs::write(&partial, &bytes).unwrap();
+    fs::rename(&partial, &path).unwrap();
+    path
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::server::cache::{open_database, page_weight, read_page, DatabaseCache, FileVersion, PageCache};
+
+    #[test]
+    fn test_page_cache_eviction() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let mut cache = PageCache::new(3 * 4096);
+        for page_number in 1..=3 {
+            cache.insert("chinook", page_number, Arc::new(db.read_page(page_number).unwrap()), 4096);
+        }
+        assert_eq!(cache.len(), 3);
+
+        // page 1 becomes the most recently used, page 2 is evicted
+        assert!(cache.get("chinook", 1).is_some());
+        cache.insert("chinook", 4, Arc::new(db.read_page(4).unwrap()), 4096);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.used(), 3 * 4096);
+        assert!(cache.get("chinook", 2).is_none());
+        assert!(cache.get("chinook", 1).is_some());
+        assert!(cache.get("chinook", 3).is_some());
+
+        cache.invalidate("chinook");
+        assert!(cache.is_empty());
+        assert_eq!(cache.used(), 0);
+    }
+
+    #[test]
+    fn test_database_cache_invalidation() {
+        let path = std::env::temp_dir().join("test_database_cache_invalidation.db");
+        fs::copy("test-data/Chinook.db.4.analyze", &path).unwrap();
+        let path = path.to_str().unwrap();
+
+        let cache = Mutex::new(DatabaseCache::new(1 << 20));
+        let db = open_database(&cache, "chinook", path).unwrap();
+        let page = read_page(&cache, "chinook", &db, 2).unwrap();
+        assert!(Arc::ptr_eq(&page, &read_page(&cache, "chinook", &db, 2).unwrap()));
+        // the weight of the page follows its cells, page 2 is an interior page of two cells
+        assert_eq!(cache.lock().unwrap().pages().used(), page_weight(&page));
+        assert!(page_weight(&page) < 4096);
+        cache.lock().unwrap().set_page_parents("chinook", &db, Arc::new(db.page_parents().unwrap()));
+        assert!(cache.lock().unwrap().cached_page_parents("chinook").is_some());
+        assert!(Arc::ptr_eq(&db, &open_database(&cache, "chinook", path).unwrap()));
+
+        // a write transaction bumps the file change counter
+        let mut bytes = fs::read(path).unwrap();
+        let version = FileVersion::of(path).unwrap();
+        bytes[24..28].copy_from_slice(&(version.file_change_count + 1).to_be_bytes());
+        fs::write(path, &bytes).unwrap();
+
+        let reopened = open_database(&cache, "chinook", path).unwrap();
+        assert!(!Arc::ptr_eq(&db, &reopened));
+        assert_eq!(reopened.header().file_change_count, version.file_change_count + 1);
+        let mut cache = cache.lock().unwrap();
+        assert!(cache.cached_page_parents("chinook").is_none());
+        assert!(cache.pages().is_empty());
+
+        // the pages and hierarchy of the stale database are neither served nor kept
+        cache.set_page_parents("chinook", &db, Arc::new(Vec::new()));
+        assert!(cache.cached_page_parents("chinook").is_none());
+        cache.insert_page("chinook", &db, 2, page);
+        assert!(cache.get_page("chinook", &db, 2).is_none());
+        assert!(cache.get_page("chinook", &reopened, 2).is_none());
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sqlite_database_file_dissect::components::database::Database;
+
+    #[test]
+    fn test_check() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let report = db.check();
+        assert_eq!(report.page_count, 224);
+        assert!(report.is_ok(), "{:?}", report.problems);
+    }
+
+    #[test]
+    fn test_check_corrupted_child_pointer() {
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        // the right most pointer of the Track root (page 13) now points to the Album root
+        let offset = 12 * 4096 + 8;
+        bytes[offset..offset + 4].copy_from_slice(&2u32.to_be_bytes());
+        let file_name = std::env::temp_dir().join("test_check_corrupted_child_pointer.db");
+        fs::write(&file_name, &bytes).unwrap();
+
+        let db = Database::open(file_name.to_str().unwrap()).unwrap();
+        let report = db.check();
+        assert!(!report.is_ok());
+        assert!(report.problems.iter().any(|p| p == "page 2 is used by both Album and Track"), "{:?}", report.problems);
+        assert!(report.problems.iter().any(|p| p == "page 113 is never used"), "{:?}", report.problems);
+
+        // the Album root points at itself
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        bytes[4096 + 8..4096 + 12].copy_from_slice(&2u32.to_be_bytes());
+        let file_name = std::env::temp_dir().join("test_check_cycle.db");
+        fs::write(&file_name, &bytes).unwrap();
+        let report = Database::open(file_name.to_str().unwrap()).unwrap().check();
+        assert_eq!(report.problems, vec!["page 2 points back to its ancestor page 2 in Album", "page 30 is never used"]);
+    }
+
+    #[test]
+    fn test_page_parents() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let page_parents = db.page_parents().unwrap();
+        assert_eq!(page_parents.len(), 224);
+        // page 14 and 15 are the leaves of sqlite_schema
+        assert_eq!(page_parents[0], -1);
+        assert_eq!(page_parents[13], 0);
+        assert_eq!(page_parents[14], 0);
+    }
+
+    // Chinook with the right most pointer of the Track root (page 13) replaced
+    fn corrupted_track_root(right_most_pointer: u32, name: &str) -> Database {
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        let offset = 12 * 4096 + 8;
+        bytes[offset..offset + 4].copy_from_slice(&right_most_pointer.to_be_bytes());
+        let file_name = std::env::temp_dir().join(name);
+        fs::write(&file_name, &bytes).unwrap();
+        Database::open(file_name.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_page_parents_corrupted() {
+        // out of range
+        let db = corrupted_track_root(5000, "test_page_parents_out_of_range.db");
+        let page_parents = db.page_parents().unwrap();
+        assert_eq!(page_parents[12], -1);
+        assert!(db.btree_node(13).unwrap().children.iter().all(|child| child.page_number != 5000));
+
+        // a cycle back to the root
+        let db = corrupted_track_root(13, "test_page_parents_cycle.db");
+        let page_parents = db.page_parents().unwrap();
+        assert_eq!(page_parents[12], -1);
+        assert!(db.btree_node(13).unwrap().children.iter().all(|child| child.page_number != 13));
+
+        // a page which isn't a btree page any more doesn't stop the other btrees
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        bytes[199 * 4096] = 0;
+        let file_name = std::env::temp_dir().join("test_page_parents_not_btree.db");
+        fs::write(&file_name, &bytes).unwrap();
+        let db = Database::open(file_name.to_str().unwrap()).unwrap();
+        let page_parents = db.page_parents().unwrap();
+        assert_eq!(page_parents[12], -1);
+        assert_eq!(page_parents[223], Database::open("test-data/Chinook.db.4.analyze").unwrap().page_parents().unwrap()[223]);
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::components::diff::{CellKey, PageChange};
+    use sqlite_database_file_dissect::components::record::Column;
+
+    #[test]
+    fn test_identical() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let database_diff = db.diff(&db).unwrap();
+        assert!(database_diff.is_empty());
+        assert_eq!(database_diff.to_text(), "identical\n");
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        let page_size = 4096;
+        // rename the artist AC/DC on page 26 and drop the last page
+        let page = 25 * page_size..26 * page_size;
+        let idx = bytes[page.clone()].windows(5).position(|w| w == b"AC/DC").unwrap() + page.start;
+        bytes[idx + 1] = b'B';
+        bytes.truncate(223 * page_size);
+        let path = std::env::temp_dir().join("test_diff.db");
+        fs::write(&path, &bytes).unwrap();
+
+        let before = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let after = Database::open(path.to_str().unwrap()).unwrap();
+        let database_diff = before.diff(&after).unwrap();
+        assert_eq!(database_diff.page_count_before, 224);
+        assert_eq!(database_diff.page_count_after, 223);
+        assert!(database_diff.header.is_empty());
+        assert_eq!(database_diff.pages.len(), 2);
+
+        let page_diff = &database_diff.pages[0];
+        assert_eq!(page_diff.page_number, 26);
+        assert_eq!(page_diff.change, PageChange::Modified);
+        let cells = page_diff.cells.as_ref().unwrap();
+        assert!(cells.inserted.is_empty() && cells.deleted.is_empty());
+        assert_eq!(cells.changed.len(), 1);
+        assert_eq!(cells.changed[0].key, CellKey::RowId(1));
+        assert_eq!(cells.changed[0].columns.len(), 1);
+        assert_eq!(cells.changed[0].columns[0].column, 1);
+        assert_eq!(cells.changed[0].columns[0].before, Some(Column::STRING("AC/DC".to_string())));
+        assert_eq!(cells.changed[0].columns[0].after, Some(Column::STRING("AB/DC".to_string())));
+
+        assert_eq!(database_diff.pages[1].page_number, 224);
+        assert_eq!(database_diff.pages[1].change, PageChange::Removed);
+        assert!(database_diff.to_text().contains("column 1: 'AC/DC' -> 'AB/DC'"));
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::components::export::{column_to_csv, column_to_json, BlobEncoding, CsvOptions, Quoting};
+    use sqlite_database_file_dissect::components::record::Column;
+
+    fn output_lines(bytes: &[u8]) -> Vec<String> {
+        String::from_utf8(bytes.to_vec()).unwrap().lines().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let mut out = Vec::new();
+        let rows = db.export_csv("track", &CsvOptions::default(), &mut out).unwrap();
+        assert_eq!(rows, 3503);
+        // csv rows end with CRLF
+        assert!(out.ends_with(b"0.99\r\n"));
+        let lines = output_lines(&out);
+        assert_eq!(lines.len(), 3504);
+        assert_eq!(lines[0], "TrackId,Name,AlbumId,MediaTypeId,GenreId,Composer,Milliseconds,Bytes,UnitPrice");
+        assert_eq!(lines[1], "1,For Those About To Rock (We Salute You),1,1,1,\"Angus Young, Malcolm Young, Brian Johnson\",343719,11170334,0.99");
+        assert_eq!(lines[2], "2,Balls to the Wall,2,2,1,,342562,5510424,0.99");
+
+        let options = CsvOptions {
+            delimiter: ';',
+            quoting: Quoting::NonNumeric,
+            header: false,
+            ..CsvOptions::default()
+        };
+        let mut out = Vec::new();
+        db.export_csv("artist", &options, &mut out).unwrap();
+        assert_eq!(output_lines(&out)[0], "1;\"AC/DC\"");
+
+        assert!(db.export_csv("nope", &options, &mut Vec::new()).is_err());
+
+        assert_eq!(column_to_csv(&Column::F64(f64::INFINITY), &options), "9e999");
+        assert_eq!(column_to_csv(&Column::F64(f64::NEG_INFINITY), &CsvOptions::default()), "-9e999");
+        assert_eq!(column_to_csv(&Column::F64(f64::NAN), &options), "");
+    }
+
+    #[test]
+    fn test_export_json_lines() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let mut out = Vec::new();
+        assert_eq!(db.export_json_lines("genre", BlobEncoding::Hex, &mut out).unwrap(), 25);
+        let lines = output_lines(&out);
+        assert_eq!(lines[0], "{\"GenreId\":1,\"Name\":\"Rock\"}");
+
+        let blob = Column::BLOB(vec![0, 255].into_boxed_slice());
+        assert_eq!(column_to_json(&blob, BlobEncoding::Hex), "00ff");
+        assert_eq!(column_to_json(&blob, BlobEncoding::Base64), "AP8=");
+        assert!(column_to_json(&Column::F64(f64::NAN), BlobEncoding::Hex).is_null());
+    }
+}
@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod tests {
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::components::graph::{GraphEdgeKind, GraphNodeKind};
+    use sqlite_database_file_dissect::components::page_header::PageType;
+
+    #[test]
+    fn test_btree_graph() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let graph = db.btree_graph().unwrap();
+        // no freelist, every page belongs to a btree
+        assert_eq!(graph.nodes.len(), 224);
+        assert_eq!(graph.btrees[0], ("sqlite_schema".to_string(), 1));
+
+        let root = graph.nodes.iter().find(|node| node.page_number == 1).unwrap();
+        assert_eq!(root.kind, GraphNodeKind::Btree(PageType::TableInteriorBtreePage));
+        assert_eq!(root.parent, None);
+        let leaf = graph.nodes.iter().find(|node| node.page_number == 14).unwrap();
+        assert_eq!(leaf.parent, Some(1));
+        assert!(leaf.fill > 0.0 && leaf.fill <= 100.0);
+        assert!(graph.edges.iter().all(|edge| edge.kind == GraphEdgeKind::Child));
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph btrees {\n"));
+        assert!(dot.contains("  p1 -> p14;\n"));
+        assert!(dot.contains("p14 [label=\"page 14\\ntable leaf\\n6 cells, "));
+
+        let svg = graph.to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert_eq!(svg.matches("<g>").count(), 224);
+        assert!(svg.ends_with("</svg>\n"));
+    }
+}
@@ -0,0 +1,198 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::components::database_header::{patch_header_bytes, DatabaseHeader, FileFormatVersion, SchemaFormatNumber, Severity, TextEncoding};
+    use sqlite_database_file_dissect::components::diff::diff_headers;
+    use sqlite_database_file_dissect::utils::convert::{ToBytes, TryFromBytes};
+    use sqlite_database_file_dissect::utils::error::ErrorKind;
+
+    #[test]
+    fn test_set_header_field() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let mut header = db.header().clone();
+        header.set_field("user_version", "-5").unwrap();
+        header.set_field("application_id", "0x4d504244").unwrap();
+        header.set_field("text_encoding", "utf-16be").unwrap();
+        header.set_field("schema_format_number", "1").unwrap();
+        header.set_field("page_size", "65536").unwrap();
+        header.set_field("leaf_payload_fraction", "32").unwrap();
+
+        let header = DatabaseHeader::try_from_be_bytes(&header.to_be_bytes()).unwrap();
+        assert_eq!(header.user_version as i32, -5);
+        assert_eq!(header.application_id, 0x4d50_4244);
+        assert_eq!(header.text_encoding, TextEncoding::UTF16be);
+        assert_eq!(header.page_size, 1);
+        let fields: Vec<String> = diff_headers(db.header(), &header).into_iter().map(|change| change.field).collect();
+        assert_eq!(fields, vec!["application_id", "page_size", "schema_format_number", "text_encoding", "user_version"]);
+    }
+
+    #[test]
+    fn test_set_header_field_domains() {
+        let mut header = Database::open("test-data/Chinook.db.4.analyze").unwrap().header().clone();
+        for (field, value) in [("page_size", "1000"), ("page_size", "256"), ("text_encoding", "4"), ("text_encoding", "latin1"),
+                               ("schema_format_number", "5"), ("max_embedded_payload_fraction", "32"), ("incremental_vacuum_mode", "2"),
+                               ("file_format_write_version", "3"), ("in_header_database_size", "-1"), ("schema_cookie", "4294967296")].iter() {
+            let e = header.set_field(field, value).unwrap_err();
+            assert!(e.to_string().starts_with(&format!("The value {} is not valid for {}", value, field)), "{}", e);
+        }
+        assert_eq!(header.set_field("header_string", "x").unwrap_err().to_string(), "The database header has no field header_string.");
+        header.set_field("bytes_at_unused_page_end", "64").unwrap();
+        // 512 - 64 leaves less than 480 usable bytes
+        assert!(header.set_field("page_size", "512").is_err());
+        assert_eq!(header.page_size, 4096);
+        assert!(DatabaseHeader::FIELDS.iter().all(|field| !header.set_field(field, "x").unwrap_err().to_string().contains("no field")));
+        assert!(DatabaseHeader::PAGE_LAYOUT_FIELDS.iter().all(|field| DatabaseHeader::FIELDS.contains(field)));
+    }
+
+    #[test]
+    fn test_validate_header() {
+        let header = Database::open("test-data/Chinook.db.4.analyze").unwrap().header().clone();
+        assert!(header.validate().is_empty());
+
+        let mut bytes = header.to_be_bytes();
+        bytes[0] = b's';
+        bytes[16..18].copy_from_slice(&3000u16.to_be_bytes());
+        bytes[23] = 33;
+        bytes[75] = 1;
+        bytes[95] ^= 1;
+        let issues = DatabaseHeader::try_from_be_bytes(&bytes).unwrap().validate();
+        let found: Vec<(Severity, &str)> = issues.iter().map(|issue| (issue.severity, issue.field.as_str())).collect();
+        assert_eq!(found, vec![
+            (Severity::Error, "header_string"),
+            (Severity::Error, "page_size"),
+            (Severity::Error, "leaf_payload_fraction"),
+            (Severity::Warning, "version_valid_for_number"),
+            (Severity::Warning, "reserved"),
+        ]);
+        assert_eq!(issues[1].message, "3000 is not a power of two between 512 and 65536");
+
+        // 1 stands for 65536, which has room for 255 reserved bytes while 512 has not
+        bytes[16..18].copy_from_slice(&1u16.to_be_bytes());
+        bytes[20] = 255;
+        assert!(DatabaseHeader::try_from_be_bytes(&bytes).unwrap().validate().iter().all(|issue| issue.field != "page_size" && issue.field != "bytes_at_unused_page_end"));
+        bytes[16..18].copy_from_slice(&512u16.to_be_bytes());
+        assert!(DatabaseHeader::try_from_be_bytes(&bytes).unwrap().validate().iter().any(|issue| issue.field == "bytes_at_unused_page_end"));
+    }
+
+    #[test]
+    fn test_patch_header_bytes() {
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap()[..100].to_vec();
+        bytes[16..18].copy_from_slice(&3000u16.to_be_bytes());
+        bytes[23] = 0;
+        // a magic string that doesn't parse as utf-8 is kept as it is
+        bytes[1] = 0xff;
+        let before = DatabaseHeader::try_from_be_bytes(&bytes).unwrap();
+        let mut after = before.clone();
+        after.set_field("page_size", "4096").unwrap();
+        after.set_field("leaf_payload_fraction", "32").unwrap();
+        patch_header_bytes(&mut bytes, &before, &after);
+
+        assert_eq!(bytes[1], 0xff);
+        assert_eq!(&bytes[16..18], &4096u16.to_be_bytes());
+        assert_eq!(bytes[23], 32);
+        let fields: Vec<String> = DatabaseHeader::try_from_be_bytes(&bytes).unwrap().validate().into_iter().map(|issue| issue.field).collect();
+        assert_eq!(fields, vec!["header_string"]);
+    }
+
+    #[test]
+    fn test_validate_unknown_variants() {
+        let bytes = Database::open("test-data/Chinook.db.4.analyze").unwrap().header().to_be_bytes();
+        let with = |offset: usize, value: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[offset..offset + value.len()].copy_from_slice(value);
+            DatabaseHeader::try_from_be_bytes(&bytes).unwrap()
+        };
+        let errors = |header: &DatabaseHeader| header.validate().into_iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .map(|issue| issue.field)
+            .collect::<Vec<String>>();
+
+        let header = with(18, &[9]);
+        assert_eq!(header.file_format_read_version, FileFormatVersion::Unknown(9));
+        assert_eq!(errors(&header), vec!["file_format_read_version"]);
+        assert_eq!(header.to_be_bytes()[18], 9);
+        let header = with(19, &[0]);
+        assert_eq!(header.file_format_write_version, FileFormatVersion::Unknown(0));
+        assert_eq!(errors(&header), vec!["file_format_write_version"]);
+        let header = with(44, &7u32.to_be_bytes());
+        assert_eq!(header.schema_format_number, SchemaFormatNumber::Unknown(7));
+        assert_eq!(errors(&header), vec!["schema_format_number"]);
+        let header = with(56, &7u32.to_be_bytes());
+        assert_eq!(header.text_encoding, TextEncoding::Unknown(7));
+        assert_eq!(errors(&header), vec!["text_encoding"]);
+        assert_eq!(header.validate()[0].message, "7 is not one of 1 (UTF8), 2 (UTF16le), 3 (UTF16be), the strings of the file can't be decoded");
+        assert_eq!(header.to_be_bytes()[56..60], 7u32.to_be_bytes());
+
+        // sqlite leaves both at 0 until the first table is created
+        let mut header = with(44, &[0; 16]);
+        assert!(header.validate().is_empty(), "{:?}", header.validate());
+        header.set_field("text_encoding", "2").unwrap();
+        assert_eq!(header.text_encoding, TextEncoding::UTF16le);
+        assert!(header.set_field("text_encoding", "unknown").is_err());
+        assert!(DatabaseHeader::try_from_be_bytes(&bytes[..99]).is_err());
+    }
+
+    fn open_variant(bytes: &[u8], name: &str) -> Database {
+        let file_name = std::env::temp_dir().join(name);
+        fs::write(&file_name, bytes).unwrap();
+        Database::open(file_name.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_database_size() {
+        let bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        let size = Database::open("test-data/Chinook.db.4.analyze").unwrap().size();
+        assert!(size.is_ok(), "{:?}", size.problems);
+        assert_eq!((size.file_pages, size.trailing_bytes, size.effective_page_count), (224, 0, 224));
+        assert!(size.in_header_size_valid);
+
+        // truncated in the middle of page 201
+        let db = open_variant(&bytes[..200 * 4096 + 1000], "test_database_size_truncated.db");
+        let size = db.size();
+        assert_eq!((size.file_pages, size.trailing_bytes, size.effective_page_count), (200, 1000, 224));
+        assert_eq!(size.problems, vec![
+            "the file ends with a partial page of 1000 bytes after its 200 whole pages",
+            "the header counts 224 pages but the file holds 201, it is truncated",
+        ]);
+        assert_eq!(db.page_count(), 200);
+        assert!(db.check().problems.iter().any(|problem| problem.ends_with("it is truncated")));
+
+        // the pages after the ones the header counts are left alone
+        let mut longer = bytes.clone();
+        longer.extend_from_slice(&[0; 2 * 4096]);
+        let db = open_variant(&longer, "test_database_size_longer.db");
+        assert_eq!(db.page_count(), 224);
+        assert_eq!(db.size().problems, vec!["the file holds 226 pages, the 2 pages after the 224 the header counts are ignored"]);
+
+        // a stale header size gives way to the file length
+        longer[92..96].copy_from_slice(&5u32.to_be_bytes());
+        let db = open_variant(&longer, "test_database_size_stale.db");
+        assert!(!db.size().in_header_size_valid);
+        assert_eq!(db.page_count(), 226);
+    }
+
+    #[test]
+    fn test_open_invalid_page_size() {
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        for (page_size, reserved) in [(0u16, 0u8), (1000, 0), (512, 64)].iter() {
+            bytes[16..18].copy_from_slice(&page_size.to_be_bytes());
+            bytes[20] = *reserved;
+            let file_name = std::env::temp_dir().join(format!("test_open_page_size_{}_{}.db", page_size, reserved));
+            fs::write(&file_name, &bytes).unwrap();
+            let e = Database::open(file_name.to_str().unwrap()).err().unwrap();
+            assert!(matches!(e.kind(), ErrorKind::InvalidPageSize(size) if *size == *page_size as usize), "{}", e);
+
+            // the header is still read from the bytes, and validate tells why the file doesn't open
+            let header = Database::read_header(file_name.to_str().unwrap()).unwrap();
+            assert_eq!(header.page_size, *page_size);
+            let field = if *reserved == 0 { "page_size" } else { "bytes_at_unused_page_end" };
+            assert!(header.validate().iter().any(|issue| issue.severity == Severity::Error && issue.field == field));
+        }
+        let file_name = std::env::temp_dir().join("test_read_header_short.db");
+        fs::write(&file_name, &bytes[..60]).unwrap();
+        let e = Database::read_header(file_name.to_str().unwrap()).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::SliceLengthError(100, 60)), "{}", e);
+    }
+}
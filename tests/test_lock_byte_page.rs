@@ -0,0 +1,146 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::{Seek, SeekFrom, Write};
+    use std::path::PathBuf;
+
+    use sqlite_database_file_dissect::components::cell::Cell;
+    use sqlite_database_file_dissect::components::database::{lock_byte_page_number, Database};
+    use sqlite_database_file_dissect::components::database_header::{DatabaseHeader, SchemaFormatNumber, SQLITE_DB_HEADER_STRING};
+    use sqlite_database_file_dissect::components::page::PageBuilder;
+    use sqlite_database_file_dissect::components::page_header::PageType;
+    use sqlite_database_file_dissect::components::record::{Column, Record};
+    use sqlite_database_file_dissect::tui::app::{App, PageRole};
+    use sqlite_database_file_dissect::utils::convert::ToBytes;
+
+    const PAGE_SIZE: usize = 4096;
+    // the leaves sqlite puts on a freelist trunk page
+    const TRUNK_LEAVES: u32 = (PAGE_SIZE / 4 - 8) as u32;
+
+    fn leaf_page(header_start_index: usize, rows: Vec<(i64, Vec<Column>)>) -> Vec<u8> {
+        let mut builder = PageBuilder::new(PageType::TableLeafBtreePage, PAGE_SIZE, header_start_index);
+        for (row_id, columns) in rows {
+            let record = Record::new(columns);
+            builder.push_cell(&Cell {
+                page_type: PageType::TableLeafBtreePage,
+                row_id: Some(row_id),
+                payload_length: Some(record.to_be_bytes().len() as u64),
+                payload: Some(record),
+                ..Default::default()
+            }).unwrap();
+        }
+        builder.build()
+    }
+
+    fn write_page(file: &mut File, page_number: u32, bytes: &[u8]) {
+        file.seek(SeekFrom::Start((page_number as u64 - 1) * PAGE_SIZE as u64)).unwrap();
+        file.write_all(bytes).unwrap();
+    }
+
+    // A sparse file of 1 GiB and a few pages: table t has one row on page 2 and one on the last
+    // page, past the lock-byte page, and every page between them is on the freelist.
+    fn large_database(name: &str) -> PathBuf {
+        let lock_page = lock_byte_page_number(PAGE_SIZE);
+        let root_page = lock_page + 1;
+        let page_count = lock_page + 2;
+        let path = std::env::temp_dir().join(name);
+        let mut file = File::create(&path).unwrap();
+        file.set_len(page_count as u64 * PAGE_SIZE as u64).unwrap();
+
+        let mut first_page = leaf_page(100, vec![(1, vec![
+            Column::STRING("table".to_string()),
+            Column::STRING("t".to_string()),
+            Column::STRING("t".to_string()),
+            Column::integer(root_page as i64),
+            Column::STRING("CREATE TABLE t(a)".to_string()),
+        ])]);
+        let header = DatabaseHeader {
+            header_string: SQLITE_DB_HEADER_STRING.to_string(),
+            page_size: PAGE_SIZE as u16,
+            max_embedded_payload_fraction: 64,
+            min_embedded_payload_fraction: 32,
+            leaf_payload_fraction: 32,
+            file_change_count: 1,
+            in_header_database_size: page_count,
+            first_freelist_trunk_page_number: 3,
+            total_freelist_page_number: lock_page - 3,
+            schema_cookie: 1,
+            schema_format_number: SchemaFormatNumber::Four,
+            version_valid_for_number: 1,
+            ..Default::default()
+        };
+        first_page[..100].copy_from_slice(&header.to_be_bytes());
+        write_page(&mut file, 1, &first_page);
+        write_page(&mut file, 2, &leaf_page(0, vec![(1, vec![Column::STRING("before the lock page".to_string())])]));
+        write_page(&mut file, page_count, &leaf_page(0, vec![(2, vec![Column::STRING("after the lock page".to_string())])]));
+
+        let mut interior = PageBuilder::new(PageType::TableInteriorBtreePage, PAGE_SIZE, 0);
+        interior.push_cell(&Cell {
+            page_type: PageType::TableInteriorBtreePage,
+            left_child_page_number: Some(2),
+            row_id: Some(1),
+            ..Default::default()
+        }).unwrap();
+        interior.set_right_most_pointer(Some(page_count));
+        write_page(&mut file, root_page, &interior.build());
+
+        // pages 3 up to the lock-byte page, each trunk followed by its leaves
+        let mut trunk = 3;
+        while trunk < lock_page {
+            let leaves = TRUNK_LEAVES.min(lock_page - trunk - 1);
+            let next = if trunk + leaves + 1 < lock_page { trunk + leaves + 1 } else { 0 };
+            let mut bytes = Vec::with_capacity(8 + leaves as usize * 4);
+            bytes.extend_from_slice(&next.to_be_bytes());
+            bytes.extend_from_slice(&leaves.to_be_bytes());
+            for leaf in trunk + 1..=trunk + leaves {
+                bytes.extend_from_slice(&leaf.to_be_bytes());
+            }
+            write_page(&mut file, trunk, &bytes);
+            trunk += leaves + 1;
+        }
+        path
+    }
+
+    #[test]
+    fn test_lock_byte_page() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        assert_eq!(lock_byte_page_number(4096), 262145);
+        assert_eq!(lock_byte_page_number(65536), 16385);
+        assert_eq!(db.lock_byte_page(), None);
+        assert!(!db.is_lock_byte_page(262145));
+    }
+
+    #[test]
+    fn test_large_file() {
+        let path = large_database("sqlite-dissect-lock-byte-page.db");
+        let db = Database::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(db.page_count(), 262147);
+        assert!(db.file_length() > 1 << 30);
+        assert_eq!(db.lock_byte_page(), Some(262145));
+        assert!(db.is_lock_byte_page(262145));
+        assert!(db.read_page(262145).unwrap_err().to_string().contains("lock bytes"));
+        assert_eq!(db.page_bytes(262145).unwrap().len(), PAGE_SIZE);
+
+        let check = db.check();
+        assert!(check.is_ok(), "{:?}", check.problems);
+        let traversal = db.btree_traversal().unwrap();
+        assert!(traversal.problems.is_empty(), "{:?}", traversal.problems);
+        assert!(!traversal.is_visited(262145));
+
+        let rows = db.table_rows(262146).unwrap();
+        assert_eq!(rows.iter().map(|row| row.row_id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(rows[1].record.columns[0], Column::STRING("after the lock page".to_string()));
+
+        let app = App::new(&db).unwrap();
+        assert_eq!(app.role(262145), PageRole::LockByte);
+        assert_eq!(app.role(262147), PageRole::Btree("t".to_string()));
+        assert_eq!(app.role(262144), PageRole::FreelistLeaf);
+
+        let (_, report) = db.repair().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.rows, 2);
+
+        drop(db);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::components::record::{Column, ColumnRef};
+
+    #[test]
+    fn test_page_ref() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        for page_number in 1..=db.page_count() {
+            let page = match db.read_page(page_number) {
+                Ok(page) => page,
+                Err(_) => continue,
+            };
+            let page_ref = db.page_ref(page_number).unwrap();
+            assert_eq!(page_ref.cell_count(), page.cells.len());
+            for (cell_ref, cell) in page_ref.cells().zip(page.cells.iter()) {
+                let cell_ref = cell_ref.unwrap();
+                assert_eq!(cell_ref.row_id().unwrap(), cell.row_id);
+                assert_eq!(cell_ref.left_child_page_number().unwrap(), cell.left_child_page_number);
+                assert_eq!(cell_ref.payload_length().unwrap(), cell.payload_length);
+                let record = cell_ref.record().unwrap().map(|record| record.to_record().unwrap());
+                assert_eq!(record, cell.payload);
+            }
+        }
+
+        let track_leaf = db.page_ref(14).unwrap();
+        assert!(track_leaf.cell(track_leaf.cell_count()).is_err());
+    }
+
+    #[test]
+    fn test_for_each_table_row_ref() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let track = db.table("Track").unwrap();
+        let rows = db.table_rows(track.entry.root_page).unwrap();
+        let mut index = 0;
+        let count = db.for_each_table_row_ref(track.entry.root_page, |row_id, record| {
+            assert_eq!(row_id, rows[index].row_id);
+            let columns: Vec<Column> = record.columns().map(|column| column.unwrap().to_column()).collect();
+            assert_eq!(columns, rows[index].record.columns);
+            index += 1;
+            Ok(())
+        }).unwrap();
+        assert_eq!(count, 3503);
+
+        db.for_each_table_row_ref(track.entry.root_page, |row_id, record| {
+            if row_id == 1 {
+                // the name is read straight out of the page
+                let name = record.column(1).unwrap().unwrap();
+                assert!(matches!(name.as_str(), Some(Cow::Borrowed("For Those About To Rock (We Salute You)"))));
+                assert_eq!(record.column(0).unwrap(), Some(ColumnRef::NULL));
+                assert_eq!(record.column(9).unwrap(), None);
+            }
+            Ok(())
+        }).unwrap();
+    }
+}
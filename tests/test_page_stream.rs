@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod tests {
+    use sqlite_database_file_dissect::components::database::Database;
+
+    #[test]
+    fn test_pages() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let pages = db.pages();
+        assert_eq!(pages.size_hint(), (224, Some(224)));
+        let page_numbers: Vec<u32> = pages.map(|(page_number, _)| page_number).collect();
+        assert_eq!(page_numbers, (1..=224).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_for_each_page_parallel() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let mut parallel = Vec::new();
+        db.for_each_page_parallel(|page_number, page| {
+            parallel.push((page_number, page.map(|page| page.cells.len()).map_err(|e| e.to_string())));
+            Ok(())
+        }).unwrap();
+        let sequential: Vec<_> = db.pages()
+            .map(|(page_number, page)| (page_number, page.map(|page| page.cells.len()).map_err(|e| e.to_string())))
+            .collect();
+        assert_eq!(parallel, sequential);
+    }
+}
@@ -0,0 +1,95 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sqlite_database_file_dissect::server::registry::DatabaseRegistry;
+    use sqlite_database_file_dissect::server::upload::{upload_name, UploadWorkspace};
+    use sqlite_database_file_dissect::utils::error::ErrorKind;
+
+    use crate::common::chinook_with_stat_tables;
+
+    #[test]
+    fn test_register_file() {
+        let mut registry = DatabaseRegistry::new();
+        let first = registry.register_file("test-data/Chinook.db.4.analyze").unwrap();
+        let second = registry.register_file("test-data/Chinook.db.4.analyze").unwrap();
+        assert_eq!(first.id, "chinook-db-4-analyze");
+        assert_eq!(second.id, "chinook-db-4-analyze-2");
+        assert_eq!(first.page_count, 224);
+        assert_eq!(registry.list().len(), 2);
+
+        assert!(registry.remove(&first.id).is_some());
+        assert!(registry.get(&first.id).is_none());
+        assert!(registry.get(&second.id).is_some());
+    }
+
+    #[test]
+    fn test_register_directory() {
+        let directory = std::env::temp_dir().join("test_register_directory");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        fs::copy("test-data/Chinook.db.4.analyze", directory.join("a.db")).unwrap();
+        fs::copy(chinook_with_stat_tables(), directory.join("b.db")).unwrap();
+        fs::write(directory.join("notes.txt"), "not a database").unwrap();
+
+        let mut registry = DatabaseRegistry::new();
+        let registered = registry.register_path(directory.to_str().unwrap()).unwrap();
+        let ids: Vec<&str> = registered.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["a-db", "b-db"]);
+
+        assert!(registry.register_file(directory.join("notes.txt").to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_upload() {
+        let directory = std::env::temp_dir().join("test_upload");
+        let _ = fs::remove_dir_all(&directory);
+        let workspace = UploadWorkspace::new(&directory, 1 << 20, 60).unwrap();
+
+        let bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        let mut upload_file = workspace.create_file().unwrap();
+        for chunk in bytes.chunks(7) {
+            upload_file.write_chunk(chunk).unwrap();
+        }
+        let path = upload_file.finish().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+
+        let mut registry = DatabaseRegistry::new();
+        registry.register_file("test-data/Chinook.db.4.analyze").unwrap();
+        let uploaded = registry.register_named_file(path.to_str().unwrap(), &upload_name("C:\\tmp\\Chinook.db"), Some(100)).unwrap();
+        assert_eq!(uploaded.id, "chinook-db");
+        assert_eq!(uploaded.expires_at, Some(100));
+
+        assert!(registry.remove_expired(99).is_empty());
+        let expired = registry.remove_expired(100);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, "chinook-db");
+        assert_eq!(registry.list().len(), 1);
+    }
+
+    #[test]
+    fn test_upload_rejected() {
+        let directory = std::env::temp_dir().join("test_upload_rejected");
+        let _ = fs::remove_dir_all(&directory);
+        let workspace = UploadWorkspace::new(&directory, 4096, 60).unwrap();
+
+        let mut upload_file = workspace.create_file().unwrap();
+        let e = upload_file.write_chunk(b"SQLite format 2\0").unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::NotDatabaseFile));
+        upload_file.discard();
+
+        let mut upload_file = workspace.create_file().unwrap();
+        upload_file.write_chunk(b"SQLite").unwrap();
+        assert!(upload_file.finish().is_err());
+
+        let bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        let mut upload_file = workspace.create_file().unwrap();
+        let e = upload_file.write_chunk(&bytes).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::UploadTooLarge(4096)));
+        upload_file.discard();
+
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+    }
+}
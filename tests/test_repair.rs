@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::components::record::Column;
+    use sqlite_database_file_dissect::components::repair::RepairReport;
+    use sqlite_database_file_dissect::components::writer::{DatabaseWriter, WriterOptions};
+
+    fn repair(bytes: &[u8], name: &str) -> (Database, RepairReport) {
+        let file_name = std::env::temp_dir().join(format!("{}.db", name));
+        fs::write(&file_name, bytes).unwrap();
+        let (writer, report) = Database::open(file_name.to_str().unwrap()).unwrap().repair().unwrap();
+        let output = std::env::temp_dir().join(format!("{}.repaired.db", name));
+        writer.write_to(output.to_str().unwrap()).unwrap();
+        let db = Database::open(output.to_str().unwrap()).unwrap();
+        assert!(db.check().is_ok(), "{:?}", db.check().problems);
+        (db, report)
+    }
+
+    fn row_count(db: &Database, table: &str) -> usize {
+        db.count_entries(db.find_schema_entry(table).unwrap().root_page).unwrap()
+    }
+
+    #[test]
+    fn test_repair() {
+        let bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        let (db, report) = repair(&bytes, "test_repair");
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!((report.tables, report.indexes, report.rows, report.orphan_rows), (11, 10, 15607, 0));
+        // with the index of the PRIMARY KEY of PlaylistTrack
+        assert_eq!(db.schema().unwrap().len(), 22);
+        assert_eq!(row_count(&db, "sqlite_autoindex_PlaylistTrack_1"), 8715);
+        assert_eq!(row_count(&db, "Track"), 3503);
+        assert_eq!(row_count(&db, "IFK_TrackAlbumId"), 3503);
+        assert_eq!(db.table("Artist").unwrap().values(&db.table_rows(db.find_schema_entry("Artist").unwrap().root_page).unwrap()[0])[1].to_sql_literal(), "'AC/DC'");
+    }
+
+    #[test]
+    fn test_repair_loses_damaged_leaves() {
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        // page 37 gets an unknown page type, page 38 more cells than it can hold, both are leaves of Track
+        bytes[36 * 4096] = 7;
+        bytes[37 * 4096 + 3..37 * 4096 + 5].copy_from_slice(&[0xff, 0xff]);
+        let (db, report) = repair(&bytes, "test_repair_loses_damaged_leaves");
+        assert_eq!(report.lost_pages.len(), 2, "{:?}", report);
+        assert_eq!((report.lost_pages[0].page_number, report.lost_pages[0].table.as_str()), (37, "Track"));
+        assert_eq!((report.lost_pages[0].min_row_id, report.lost_pages[0].max_row_id), (Some(381), Some(448)));
+        assert_eq!((report.lost_pages[1].min_row_id, report.lost_pages[1].max_row_id), (Some(449), Some(507)));
+        assert!(report.lost_rows.is_empty() && report.problems.is_empty());
+        assert_eq!(row_count(&db, "Track"), 3503 - (507 - 381 + 1));
+    }
+
+    #[test]
+    fn test_repair_adopts_orphan_leaves() {
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        // the roots of sqlite_schema, Album, Artist and Track are lost, their leaves are orphans
+        bytes[100] = 7;
+        for page_number in [2, 3, 13].iter() {
+            bytes[(page_number - 1) * 4096] = 7;
+        }
+        let (db, report) = repair(&bytes, "test_repair_adopts_orphan_leaves");
+        let lost: Vec<u32> = report.lost_pages.iter().map(|lost| lost.page_number).collect();
+        assert_eq!(lost, vec![1, 2, 3, 13]);
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        assert_eq!((report.tables, report.rows), (11, 15607));
+        assert_eq!(row_count(&db, "Album"), 347);
+        assert_eq!(row_count(&db, "Artist"), 275);
+        assert_eq!(row_count(&db, "Track"), 3503);
+
+        // the right most pointer of the Album root points at the root itself, the leaf it cut off is given back
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        bytes[4096 + 8..4096 + 12].copy_from_slice(&2u32.to_be_bytes());
+        let (db, report) = repair(&bytes, "test_repair_cycle");
+        assert_eq!(report.lost_pages.len(), 1);
+        assert_eq!(report.lost_pages[0].reason, "page 2 points back to its ancestor page 2");
+        assert_eq!(report.orphan_rows, 70);
+        assert_eq!(row_count(&db, "Album"), 347);
+    }
+
+    #[test]
+    fn test_repair_collated_indexes() {
+        let mut writer = DatabaseWriter::new(WriterOptions { user_version: 7, application_id: 0x0f0f0f0f, ..Default::default() }).unwrap();
+        let emails = ["b@x", "A@x", "c@x", "B@y"];
+        let names = ["x ", "x", "w", "y"];
+        let rows = emails.iter().zip(names.iter()).enumerate()
+            .map(|(idx, (email, name))| (idx as i64 + 1, vec![Column::integer(idx as i64 * 10), Column::STRING(email.to_string()), Column::STRING(name.to_string())]))
+            .collect();
+        let sql = "CREATE TABLE users (id INTEGER PRIMARY KEY DESC, email TEXT COLLATE NOCASE UNIQUE, name TEXT COLLATE RTRIM)";
+        assert!(writer.add_table("users", sql, rows).unwrap().is_empty());
+        writer.add_index("users_name", "users", "CREATE INDEX users_name ON users (name)").unwrap();
+        let (db, report) = repair(&writer.to_bytes().unwrap(), "test_repair_collated_indexes");
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!((report.tables, report.indexes, report.rows), (1, 1, 4));
+        assert_eq!((db.header().user_version, db.header().application_id), (7, 0x0f0f0f0f));
+
+        // binary order would be A@x B@y b@x c@x and w x x y
+        let keys = |name: &str| db.index_entries(db.find_schema_entry(name).unwrap().root_page).unwrap()
+            .iter()
+            .map(|entry| entry.columns[0].clone())
+            .collect::<Vec<Column>>();
+        assert_eq!(keys("sqlite_autoindex_users_1"), [30, 20, 10, 0].iter().map(|&id| Column::integer(id)).collect::<Vec<Column>>());
+        assert_eq!(keys("sqlite_autoindex_users_2"), ["A@x", "b@x", "B@y", "c@x"].iter().map(|email| Column::STRING(email.to_string())).collect::<Vec<Column>>());
+        assert_eq!(keys("users_name"), ["w", "x ", "x", "y"].iter().map(|name| Column::STRING(name.to_string())).collect::<Vec<Column>>());
+    }
+
+    #[test]
+    fn test_repair_without_rowid_tables() {
+        let text = |text: &str| Column::STRING(text.to_string());
+        let mut writer = DatabaseWriter::new(WriterOptions::default()).unwrap();
+        let rows = vec![vec![text("b"), Column::integer(1)], vec![text("a"), Column::integer(2)], vec![text("c"), text("t")]];
+        writer.add_without_rowid_table("k", "CREATE TABLE k (a TEXT PRIMARY KEY, b) WITHOUT ROWID", rows).unwrap();
+        writer.add_index("kb", "k", "CREATE INDEX kb ON k (b)").unwrap();
+        let rows = vec![
+            vec![Column::integer(1), text("p"), Column::integer(3)],
+            vec![Column::integer(2), text("p"), Column::integer(4)],
+            vec![Column::integer(1), text("q"), Column::integer(5)],
+        ];
+        writer.add_without_rowid_table("m", "CREATE TABLE m (x, y, z UNIQUE, PRIMARY KEY (y DESC, x)) WITHOUT ROWID", rows).unwrap();
+        let (db, report) = repair(&writer.to_bytes().unwrap(), "test_repair_without_rowid_tables");
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!((report.tables, report.indexes, report.rows), (2, 1, 6));
+        let names: Vec<String> = db.schema().unwrap().into_iter().map(|entry| entry.name).collect();
+        assert_eq!(names, vec!["k", "m", "sqlite_autoindex_m_1", "kb"]);
+
+        // the records hold the PRIMARY KEY first, secondary indexes end with it instead of a rowid
+        let entries = |name: &str| db.index_entries(db.find_schema_entry(name).unwrap().root_page).unwrap()
+            .into_iter()
+            .map(|entry| entry.columns)
+            .collect::<Vec<Vec<Column>>>();
+        assert_eq!(entries("k"), vec![vec![text("a"), Column::integer(2)], vec![text("b"), Column::integer(1)], vec![text("c"), text("t")]]);
+        assert_eq!(entries("kb"), vec![vec![Column::integer(1), text("b")], vec![Column::integer(2), text("a")], vec![text("t"), text("c")]]);
+        assert_eq!(entries("m")[0], vec![text("q"), Column::integer(1), Column::integer(5)]);
+        assert_eq!(entries("sqlite_autoindex_m_1")[2], vec![Column::integer(5), text("q"), Column::integer(1)]);
+
+        let mut writer = DatabaseWriter::new(WriterOptions::default()).unwrap();
+        writer.add_without_rowid_table("k", "CREATE TABLE k (a TEXT PRIMARY KEY, b) WITHOUT ROWID", vec![vec![text("a")], vec![text("a")]]).unwrap();
+        assert_eq!(writer.to_bytes().unwrap_err().to_string(), "A PRIMARY KEY of the table k is used by more than one row.");
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use sqlite_database_file_dissect::components::database::{Database, DATABASE_HEADER_LENGTH};
+    use sqlite_database_file_dissect::components::page::{Page, PageBuilder};
+    use sqlite_database_file_dissect::components::page_header::PageType;
+    use sqlite_database_file_dissect::components::record::{Column, Record};
+    use sqlite_database_file_dissect::utils::convert::{ToBytes, TryFromBytes};
+    use sqlite_database_file_dissect::utils::varint::{decode_varint_to_usize, encode_varint, varint_length};
+
+    #[test]
+    fn test_varint_round_trip() {
+        for value in [0u64, 127, 128, 240, 16383, 16384, 0x00ff_ffff_ffff_ffff, 0x0100_0000_0000_0000, i64::MAX as u64] {
+            let bytes = encode_varint(value);
+            assert_eq!(bytes.len(), varint_length(value));
+            assert_eq!(decode_varint_to_usize(&bytes).unwrap(), (value as usize, bytes.len()));
+        }
+        assert_eq!(encode_varint(128), vec![0x81, 0x00]);
+    }
+
+    #[test]
+    fn test_record_to_bytes() {
+        let record = Record::new(vec![Column::NULL, Column::I8(-2), Column::STRING("abc".to_string()), Column::I1]);
+        let bytes = record.to_be_bytes();
+        assert_eq!(bytes, vec![5, 0, 1, 19, 9, 0xfe, b'a', b'b', b'c']);
+        assert_eq!(Record::try_from_be_bytes(&bytes).unwrap(), record);
+    }
+
+    #[test]
+    fn test_record_negative_integers() {
+        // the 3 and 6 byte integers have no type of their own to take the sign from
+        let values = [-1, -(1 << 23), (1 << 23) - 1, -(1 << 47), (1 << 47) - 1, -70000, -5_000_000_000, i64::MIN, i64::MAX];
+        let record = Record::new(values.iter().map(|&value| Column::integer(value)).collect());
+        assert_eq!(record.columns[1], Column::I24(-(1 << 23)));
+        assert_eq!(record.columns[3], Column::I48(-(1 << 47)));
+        let decoded = Record::try_from_be_bytes(&record.to_be_bytes()).unwrap();
+        assert_eq!(decoded, record);
+        assert_eq!(decoded.columns.iter().map(|column| column.as_i64().unwrap()).collect::<Vec<i64>>(), values);
+    }
+
+    // Every page is written back as it was read, freeblocks and unallocated bytes included.
+    #[test]
+    fn test_page_round_trip() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let file = std::fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        assert_eq!(db.header().to_be_bytes(), file[..DATABASE_HEADER_LENGTH].to_vec());
+
+        let usable_size = db.usable_size();
+        let mut pages_with_free_bytes = 0;
+        for page_number in 1..=db.page_count() {
+            let original = &db.page_bytes(page_number).unwrap()[..usable_size];
+            let header_start_index = if page_number == 1 { DATABASE_HEADER_LENGTH } else { 0 };
+            let page = db.read_page(page_number).unwrap();
+
+            for (cell_pointer, cell) in page.cell_pointers.iter().zip(page.cells.iter()) {
+                let bytes = cell.to_be_bytes();
+                let offset = cell_pointer.offset as usize;
+                assert_eq!(bytes, original[offset..offset + bytes.len()].to_vec(), "page {} cell at {}", page_number, offset);
+            }
+
+            let bytes = page.to_be_bytes(original, header_start_index).unwrap();
+            assert!(bytes[header_start_index..] == original[header_start_index..], "page {}", page_number);
+            if !db.page_ref(page_number).unwrap().free_space_bytes().unwrap().is_empty() {
+                pages_with_free_bytes += 1;
+            }
+
+            // the builder packs the cells again, without the free space in between
+            let rebuilt = PageBuilder::from_page(&page, usable_size, header_start_index).unwrap().build();
+            let reparsed = Page::try_from_be_bytes(&rebuilt, Some(header_start_index)).unwrap();
+            assert_eq!(serde_json::to_value(&reparsed.cells).unwrap(), serde_json::to_value(&page.cells).unwrap());
+        }
+        // pages with freeblocks or bytes of deleted cells are among them
+        assert!(pages_with_free_bytes > 0);
+    }
+
+    #[test]
+    fn test_page_builder() {
+        let mut builder = PageBuilder::new(PageType::TableLeafBtreePage, 512, 0);
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let page = db.read_page(14).unwrap();
+        let mut pushed = 0;
+        for cell in page.cells.iter() {
+            if builder.push_cell(cell).is_err() {
+                break;
+            }
+            pushed += 1;
+        }
+        assert!(pushed > 0 && pushed < page.cells.len());
+        assert!(builder.free_space() < page.cells[pushed].to_be_bytes().len() + 2);
+
+        let bytes = builder.build();
+        let built = Page::try_from_be_bytes(&bytes, None).unwrap();
+        assert_eq!(built.cells.len(), pushed);
+        assert_eq!(built.cell_pointers[0].offset as usize + page.cells[0].to_be_bytes().len(), 512);
+        assert_eq!(built.header.cell_content_area_offset, built.cell_pointers[pushed - 1].offset);
+    }
+}
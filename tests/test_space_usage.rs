@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sqlite_database_file_dissect::components::database::Database;
+
+    // expected values come from the dbstat virtual table of sqlite3
+    #[test]
+    fn test_space_usage() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let report = db.space_usage().unwrap();
+        assert_eq!(report.page_size, 4096);
+        assert_eq!(report.page_count, 224);
+        assert_eq!(report.objects.len(), 23);
+
+        let expected = [
+            // name, interior, leaf, unused, payload, max payload
+            ("sqlite_schema", 1, 2, 6506, 5548, 701),
+            ("Genre", 0, 1, 3689, 299, 21),
+            ("Track", 1, 57, 7087, 212150, 237),
+            ("IFK_TrackAlbumId", 1, 9, 7538, 22797, 7),
+        ];
+        for (name, interior_pages, leaf_pages, unused_bytes, payload_bytes, max_payload) in expected.iter() {
+            let usage = report.objects.iter().find(|o| o.name == *name).unwrap();
+            assert_eq!(usage.interior_pages, *interior_pages, "{}", name);
+            assert_eq!(usage.leaf_pages, *leaf_pages, "{}", name);
+            assert_eq!(usage.overflow_pages, 0, "{}", name);
+            assert_eq!(usage.unused_bytes, *unused_bytes, "{}", name);
+            assert_eq!(usage.payload_bytes, *payload_bytes, "{}", name);
+            assert_eq!(usage.max_payload, *max_payload, "{}", name);
+            assert_eq!(usage.used_bytes + usage.unused_bytes, usage.total_bytes, "{}", name);
+        }
+
+        let track = report.objects.iter().find(|o| o.name == "Track").unwrap();
+        assert_eq!(track.entries, 3503);
+        assert_eq!(track.depth, 2);
+        assert_eq!(track.average_fanout, 57.0);
+
+        // every btree page of the file belongs to exactly one object
+        let total_pages: usize = report.objects.iter().map(|o| o.total_pages).sum();
+        assert_eq!(total_pages as u32 + report.freelist_pages, report.page_count);
+    }
+
+    #[test]
+    fn test_space_usage_cycle() {
+        // the right most pointer of page 2, the Album root, points at itself
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        let offset = 4096 + 8;
+        bytes[offset..offset + 4].copy_from_slice(&2u32.to_be_bytes());
+        let file_name = std::env::temp_dir().join("test_space_usage_cycle.db");
+        fs::write(&file_name, &bytes).unwrap();
+        let db = Database::open(file_name.to_str().unwrap()).unwrap();
+
+        let report = db.space_usage().unwrap();
+        assert!(!report.is_ok());
+        let album = report.objects.iter().find(|o| o.name == "Album").unwrap();
+        assert_eq!(album.problems, vec!["page 2 points back to its ancestor page 2"]);
+        assert_eq!(album.interior_pages, 1);
+        assert!(report.objects.iter().filter(|o| o.name != "Album").all(|o| o.problems.is_empty()));
+    }
+}
@@ -0,0 +1,76 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::components::database_header::TextEncoding;
+    use sqlite_database_file_dissect::components::record::Column;
+    use sqlite_database_file_dissect::components::writer::{DatabaseWriter, WriterOptions};
+
+    use crate::common::chinook_with_stat_tables;
+
+    #[test]
+    fn test_sql_dump() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let mut out = Vec::new();
+        let report = db.sql_dump(&mut out).unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(report.tables, 11);
+        assert_eq!(report.rows, 15607);
+
+        let sql = String::from_utf8(out).unwrap();
+        assert!(sql.starts_with("PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\n"));
+        assert!(sql.ends_with("COMMIT;\n"));
+        assert!(sql.contains("INSERT INTO \"Artist\" VALUES(1,'AC/DC');\n"));
+
+        // sqlite_stat1 is created by ANALYZE, not by CREATE TABLE
+        let db = Database::open(chinook_with_stat_tables().to_str().unwrap()).unwrap();
+        let mut out = Vec::new();
+        let report = db.sql_dump(&mut out).unwrap();
+        assert_eq!(report.tables, 12);
+        let sql = String::from_utf8(out).unwrap();
+        assert!(sql.contains("ANALYZE sqlite_schema;\nINSERT INTO \"sqlite_stat1\" VALUES("));
+        assert!(!sql.contains("CREATE TABLE sqlite_stat1"));
+    }
+
+    #[test]
+    fn test_sql_dump_skips_damaged_pages() {
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        // page 37 gets an unknown page type, page 38 more cells than it can hold, both are leaves of Track
+        bytes[36 * 4096] = 7;
+        bytes[37 * 4096 + 3..37 * 4096 + 5].copy_from_slice(&[0xff, 0xff]);
+        let file_name = std::env::temp_dir().join("test_sql_dump_skips_damaged_pages.db");
+        fs::write(&file_name, &bytes).unwrap();
+
+        let db = Database::open(file_name.to_str().unwrap()).unwrap();
+        let mut out = Vec::new();
+        let report = db.sql_dump(&mut out).unwrap();
+        assert_eq!(report.problems.len(), 2, "{:?}", report.problems);
+        assert!(report.problems[0].starts_with("page 37 of Track can't be read"));
+        assert!(report.problems[1].starts_with("page 38 of Track can't be read"));
+
+        let sql = String::from_utf8(out).unwrap();
+        assert!(sql.contains("-- page 37 of Track can't be read"));
+        assert!(sql.ends_with("COMMIT;\n"));
+    }
+
+    #[test]
+    fn test_sql_dump_utf16() {
+        for text_encoding in [TextEncoding::UTF16le, TextEncoding::UTF16be].iter() {
+            let mut writer = DatabaseWriter::new(WriterOptions { text_encoding: *text_encoding, ..Default::default() }).unwrap();
+            let rows = vec![(1, vec![Column::STRING("Brücke ā".to_string())]), (2, vec![Column::STRING("日本".to_string())])];
+            writer.add_table("straße", "CREATE TABLE straße (name TEXT)", rows).unwrap();
+            let file_name = std::env::temp_dir().join(format!("test_sql_dump_{:?}.db", text_encoding));
+            writer.write_to(file_name.to_str().unwrap()).unwrap();
+
+            let db = Database::open(file_name.to_str().unwrap()).unwrap();
+            let mut out = Vec::new();
+            let report = db.sql_dump(&mut out).unwrap();
+            assert!(report.is_ok(), "{:?}", report.problems);
+            let sql = String::from_utf8(out).unwrap();
+            assert!(sql.contains("CREATE TABLE straße (name TEXT);\nINSERT INTO \"straße\" VALUES('Brücke ā');\nINSERT INTO \"straße\" VALUES('日本');\n"), "{}", sql);
+        }
+    }
+}
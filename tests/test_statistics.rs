@@ -0,0 +1,73 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use sqlite_database_file_dissect::components::database::Database;
+
+    use crate::common::chinook_with_stat_tables;
+
+    #[test]
+    fn test_schema() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let schema = db.schema().unwrap();
+        assert_eq!(schema.len(), 22);
+        let track = db.find_schema_entry("Track").unwrap();
+        assert!(track.is_table());
+        assert_eq!(track.root_page, 13);
+        assert!(db.find_schema_entry("NoSuchTable").is_err());
+    }
+
+    #[test]
+    fn test_statistics_without_stat_tables() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let statistics = db.statistics().unwrap();
+        assert!(statistics.stat1.is_empty());
+        assert!(statistics.stat4.is_empty());
+        assert!(statistics.comparisons.is_empty());
+    }
+
+    #[test]
+    fn test_stat1() {
+        let db = Database::open(chinook_with_stat_tables().to_str().unwrap()).unwrap();
+        let statistics = db.statistics().unwrap();
+        assert_eq!(statistics.stat1.len(), 15);
+
+        let playlist_track = statistics.stat1.iter()
+            .find(|e| e.index.as_deref() == Some("sqlite_autoindex_PlaylistTrack_1"))
+            .unwrap();
+        assert_eq!(playlist_track.table, "PlaylistTrack");
+        assert_eq!(playlist_track.row_estimate, 8715);
+        assert_eq!(playlist_track.rows_per_key, vec![623, 1]);
+
+        // ANALYZE was run on this very file, so every estimate is exact
+        for comparison in statistics.comparisons.iter() {
+            assert_eq!(Some(comparison.estimated_rows), comparison.actual_rows, "{:?}", comparison);
+            if comparison.index.is_some() {
+                assert_eq!(Some(&comparison.estimated_rows_per_key), comparison.actual_rows_per_key.as_ref(), "{:?}", comparison);
+            }
+        }
+
+        let genre = statistics.comparisons.iter().find(|c| c.table == "Genre").unwrap();
+        assert_eq!(genre.index, None);
+        assert_eq!(genre.actual_rows, Some(25));
+    }
+
+    #[test]
+    fn test_stat4() {
+        let db = Database::open(chinook_with_stat_tables().to_str().unwrap()).unwrap();
+        let statistics = db.statistics().unwrap();
+        assert_eq!(statistics.stat4.len(), 79);
+
+        for sample in statistics.stat4.iter() {
+            let record = sample.sample.as_ref().unwrap();
+            assert_eq!(record.columns.len(), 2);
+            assert_eq!(sample.neq.len(), 2);
+            assert_eq!(Some(&sample.neq), sample.actual_neq.as_ref(), "{:?}", sample);
+        }
+
+        let first = &statistics.stat4[0];
+        assert_eq!(first.index, "IFK_AlbumArtistId");
+        assert_eq!(first.sample.as_ref().unwrap().columns[0].as_i64(), Some(1));
+        assert_eq!(first.nlt, vec![0, 0]);
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::components::record::Column;
+    use sqlite_database_file_dissect::components::row_diff::RowChangeKind;
+    use sqlite_database_file_dissect::components::table::{parse_create_table, parse_unique_constraints};
+
+    #[test]
+    fn test_parse_create_table() {
+        let columns = parse_create_table("CREATE TABLE \"a \"\"b\"\"\" (id integer primary key, [first name] varchar(20, 2) NOT NULL DEFAULT 'x,y', `n` -- comment\n, data BLOB, CONSTRAINT c UNIQUE (n))").unwrap();
+        let names: Vec<&str> = columns.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "first name", "n", "data"]);
+        assert_eq!(columns[1].declared_type, "VARCHAR(20, 2)");
+        assert_eq!(columns[2].declared_type, "");
+        assert!(columns[0].is_rowid_alias);
+        assert!(!columns[1].is_rowid_alias);
+
+        let columns = parse_create_table("CREATE TABLE t(a INT PRIMARY KEY, b)").unwrap();
+        assert!(!columns[0].is_rowid_alias);
+        let columns = parse_create_table("CREATE TABLE t(a INTEGER PRIMARY KEY DESC, b)").unwrap();
+        assert!(!columns[0].is_rowid_alias);
+        let columns = parse_create_table("CREATE TABLE t(a, b INTEGER, PRIMARY KEY(b))").unwrap();
+        assert!(columns[1].is_rowid_alias);
+        assert!(parse_create_table("CREATE VIRTUAL TABLE t USING fts5").is_err());
+    }
+
+    #[test]
+    fn test_parse_column_defaults() {
+        let columns = parse_create_table("CREATE TABLE t(a DEFAULT 'x,y' NOT NULL, b INT DEFAULT -5, c DEFAULT (1.5), d DEFAULT - 0x10, \
+            e DEFAULT x'00ff', f DEFAULT NULL, g DEFAULT CURRENT_TIMESTAMP, h DEFAULT TRUE, i DEFAULT -9223372036854775808, j)").unwrap();
+        let defaults: Vec<Option<Column>> = columns.into_iter().map(|column| column.default).collect();
+        assert_eq!(defaults, vec![
+            Some(Column::STRING("x,y".to_string())),
+            Some(Column::integer(-5)),
+            Some(Column::F64(1.5)),
+            Some(Column::integer(-16)),
+            Some(Column::BLOB(vec![0, 255].into_boxed_slice())),
+            None,
+            None,
+            Some(Column::I1),
+            Some(Column::integer(i64::MIN)),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn test_parse_unique_constraints() {
+        let names = |sql: &str| parse_unique_constraints(sql).unwrap().iter()
+            .map(|columns| columns.iter().map(|column| column.name.clone()).collect::<Vec<String>>().join(","))
+            .collect::<Vec<String>>();
+        assert!(names("CREATE TABLE t (id INTEGER PRIMARY KEY, a TEXT)").is_empty());
+        assert!(names("CREATE TABLE t (id INTEGER, a TEXT, PRIMARY KEY (id))").is_empty());
+        assert_eq!(names("CREATE TABLE t (id INTEGER PRIMARY KEY DESC, a TEXT UNIQUE, b, UNIQUE (a, b), CONSTRAINT c UNIQUE (b, a), UNIQUE (a))"),
+            vec!["id", "a", "a,b", "b,a"]);
+        assert_eq!(names("CREATE TABLE t (a TEXT, b INT, PRIMARY KEY (a, b))"), vec!["a,b"]);
+
+        let columns = parse_unique_constraints("CREATE TABLE t (a TEXT COLLATE NOCASE, b, UNIQUE (a, b COLLATE RTRIM DESC))").unwrap();
+        assert_eq!(columns[0][0].collation.as_deref(), Some("NOCASE"));
+        assert_eq!(columns[0][1].collation.as_deref(), Some("RTRIM"));
+        assert!(columns[0][1].descending);
+        assert!(parse_unique_constraints("CREATE TABLE t (a, UNIQUE (a + 1))").is_err());
+    }
+
+    #[test]
+    fn test_table() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let table = db.table("artist").unwrap();
+        assert_eq!(table.name(), "Artist");
+        assert_eq!(table.column_names(), vec!["ArtistId", "Name"]);
+        assert!(table.columns[0].is_rowid_alias);
+
+        let rows = db.table_rows(table.entry.root_page).unwrap();
+        assert_eq!(table.values(&rows[0]), vec![Column::I64(1), Column::STRING("AC/DC".to_string())]);
+
+        // the rows written before ALTER TABLE ADD COLUMN read the default of the new columns
+        let mut altered = table.clone();
+        altered.columns = parse_create_table("CREATE TABLE Artist(ArtistId INTEGER PRIMARY KEY, Name, Rating INT DEFAULT 3, Note)").unwrap();
+        assert_eq!(altered.values(&rows[0])[2..], [Column::integer(3), Column::NULL]);
+        assert!(db.table("IPK_Artist").is_err());
+    }
+
+    #[test]
+    fn test_table_diff() {
+        let before = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let page_size = before.page_size();
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        // rename AC/DC, rowid 1, and move the last artist from rowid 275 to 276
+        let page = 25 * page_size..26 * page_size;
+        let idx = bytes[page.clone()].windows(5).position(|w| w == b"AC/DC").unwrap() + page.start;
+        bytes[idx + 1] = b'B';
+        let leaf = (1..=before.page_count())
+            .find(|&page_number| matches!(before.read_page(page_number), Ok(page) if page.cells.iter().any(|cell| cell.row_id == Some(275))))
+            .unwrap();
+        let page = before.read_page(leaf).unwrap();
+        let cell_idx = page.cells.iter().position(|cell| cell.row_id == Some(275)).unwrap();
+        let offset = (leaf as usize - 1) * page_size + page.cell_pointers[cell_idx].offset as usize;
+        // one byte of payload length then the rowid varint 0x82 0x13
+        assert_eq!(&bytes[offset + 1..offset + 3], &[0x82, 0x13]);
+        bytes[offset + 2] = 0x14;
+        let path = std::env::temp_dir().join("test_table_diff.db");
+        fs::write(&path, &bytes).unwrap();
+        let after = Database::open(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(before.table_diff(&before, "Artist").unwrap().rows.len(), 0);
+        let table_diff = before.table_diff(&after, "Artist").unwrap();
+        assert_eq!((table_diff.inserted, table_diff.deleted, table_diff.updated), (1, 1, 1));
+        let changes: Vec<(i64, RowChangeKind)> = table_diff.rows.iter().map(|row| (row.row_id, row.change)).collect();
+        assert_eq!(changes, vec![(1, RowChangeKind::Updated), (275, RowChangeKind::Deleted), (276, RowChangeKind::Inserted)]);
+        assert_eq!(table_diff.rows[0].columns.len(), 1);
+        assert_eq!(table_diff.rows[0].columns[0].column, "Name");
+        assert_eq!(table_diff.rows[2].columns[0].after, Some(Column::I64(276)));
+        assert_eq!(table_diff.to_sql(), "BEGIN TRANSACTION;\n\
+            UPDATE \"Artist\" SET \"Name\" = 'AB/DC' WHERE rowid = 1;\n\
+            DELETE FROM \"Artist\" WHERE rowid = 275;\n\
+            INSERT INTO \"Artist\"(rowid, \"ArtistId\", \"Name\") VALUES(276, 276, 'Philip Glass Ensemble');\n\
+            COMMIT;\n");
+    }
+}
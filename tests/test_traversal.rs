@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sqlite_database_file_dissect::components::btree::{TraversalProblem, PAGE_IS_ROOT};
+    use sqlite_database_file_dissect::components::database::Database;
+
+    // Chinook with the right most pointer of the Track root (page 13) replaced
+    fn corrupted_track_root(right_most_pointer: u32, name: &str) -> Database {
+        let mut bytes = fs::read("test-data/Chinook.db.4.analyze").unwrap();
+        let offset = 12 * 4096 + 8;
+        bytes[offset..offset + 4].copy_from_slice(&right_most_pointer.to_be_bytes());
+        let file_name = std::env::temp_dir().join(name);
+        fs::write(&file_name, &bytes).unwrap();
+        Database::open(file_name.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_btree_traversal() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let traversal = db.btree_traversal().unwrap();
+        assert!(traversal.problems.is_empty(), "{:?}", traversal.problems);
+        let page_parents = traversal.page_parents();
+        assert_eq!(page_parents.len(), 224);
+        assert_eq!(page_parents[12], PAGE_IS_ROOT);
+        assert_eq!(page_parents[112], 12);
+
+        let track = db.btree_node(13).unwrap();
+        assert_eq!(track.children.last().unwrap().page_number, 113);
+    }
+
+    #[test]
+    fn test_btree_traversal_cycle() {
+        let db = corrupted_track_root(13, "test_btree_traversal_cycle.db");
+        let traversal = db.btree_traversal().unwrap();
+        assert_eq!(traversal.problems, vec![TraversalProblem::Cycle { page_number: 13, parent: 13 }]);
+        // the page cut off by the bad pointer shows up as a root of its own
+        assert_eq!(traversal.page_parents()[112], PAGE_IS_ROOT);
+
+        assert!(db.btree_node(13).unwrap().children.iter().all(|child| child.page_number != 13));
+        let e = db.table_rows(13).unwrap_err();
+        assert_eq!(e.to_string(), "The btree is corrupted: page 13 points back to its ancestor page 13");
+    }
+
+    #[test]
+    fn test_btree_traversal_bad_pointers() {
+        let db = corrupted_track_root(100000, "test_btree_traversal_dangling_pointer.db");
+        let traversal = db.btree_traversal().unwrap();
+        assert_eq!(traversal.problems, vec![TraversalProblem::DanglingPointer { page_number: 100000, parent: 13 }]);
+        assert!(db.count_entries(13).is_err());
+
+        let db = corrupted_track_root(2, "test_btree_traversal_multiple_parents.db");
+        let traversal = db.btree_traversal().unwrap();
+        assert_eq!(traversal.problems, vec![TraversalProblem::MultipleParents { page_number: 2, first_parent: 0, parent: 13 }]);
+        assert_eq!(traversal.problems[0].to_string(), "page 2 is referenced by both the schema and page 13");
+    }
+}
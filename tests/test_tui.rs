@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::tui;
+    use sqlite_database_file_dissect::tui::app::{App, Key, Pane, PageRole};
+
+    #[test]
+    fn test_tui_navigation() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let mut app = App::new(&db).unwrap();
+        assert_eq!(app.page_number, 1);
+        assert_eq!(app.role(14), PageRole::Btree("sqlite_schema".to_string()));
+        assert_eq!(app.tree[0].label, "table sqlite_schema");
+
+        // page 1 holds the two leaves of sqlite_schema
+        app.handle_key(Key::Enter);
+        assert!(app.tree[0].expanded);
+        assert_eq!(app.tree[1].page_number, 14);
+        assert_eq!(app.tree[2].page_number, 15);
+        app.handle_key(Key::Enter);
+        assert!(!app.tree[0].expanded);
+        assert_ne!(app.tree[1].page_number, 14);
+
+        app.handle_key(Key::Tab);
+        assert_eq!(app.focus, Pane::Page);
+        assert_eq!(app.view.items[0].target, Some(14));
+        app.handle_key(Key::Down);
+        assert_eq!(app.view.items[1].text, "right most pointer -> page 15");
+        app.handle_key(Key::Enter);
+        assert_eq!(app.page_number, 15);
+        assert!(app.view.items[0].text.starts_with("cell 0 @"));
+        assert!(app.view.items[0].text.contains("rowid"));
+        app.handle_key(Key::Back);
+        assert_eq!(app.page_number, 1);
+
+        app.handle_key(Key::Char(']'));
+        assert_eq!(app.page_number, 2);
+        app.handle_key(Key::Char('q'));
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_tui_draw() {
+        let db = Database::open("test-data/Chinook.db.4.analyze").unwrap();
+        let app = App::new(&db).unwrap();
+        assert_eq!(app.hex_rows().len(), 256);
+        assert!(app.hex_rows()[0].starts_with("0000  53 51 4c 69 74 65 20 66"));
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|f| tui::draw(f, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen: String = buffer.content.iter().map(|cell| cell.symbol.clone()).collect();
+        assert!(screen.contains("table sqlite_schema"));
+        assert!(screen.contains("page 1 of 224, sqlite_schema"));
+        assert!(screen.contains("SQLite format 3"));
+    }
+}
@@ -2,7 +2,9 @@
 
 mod tests {
 
-    use sqlite_database_file_dissect::utils::varint::decode_varint_to_usize;
+    use sqlite_database_file_dissect::utils::string::encode_base64;
+    use sqlite_database_file_dissect::utils::error::ErrorKind;
+    use sqlite_database_file_dissect::utils::varint::{decode_varint, decode_varint_i64, decode_varint_to_usize, encode_varint, encode_varint_i64, varint_length};
 
     #[test]
     fn test_varint(){
@@ -10,4 +12,64 @@ mod tests {
         assert_eq!(308, v);
         assert_eq!(2, l)
     }
+
+    #[test]
+    fn test_varint_boundaries() {
+        // the largest value of every length and the smallest of the next one
+        let mut values = vec![0u64, 1, u64::MAX - 1, u64::MAX];
+        for length in 1..=8 {
+            let largest = (1u64 << (7 * length)) - 1;
+            values.extend_from_slice(&[largest - 1, largest, largest + 1]);
+        }
+        for value in values {
+            let bytes = encode_varint(value);
+            assert_eq!(bytes.len(), varint_length(value), "{}", value);
+            let expected_length = if value >= 1 << 56 { 9 } else { (64 - value.leading_zeros() as usize).div_ceil(7).max(1) };
+            assert_eq!(bytes.len(), expected_length, "{}", value);
+            assert_eq!(decode_varint(&bytes).unwrap(), (value, bytes.len()), "{}", value);
+            // the bytes after the varint are left alone
+            let mut followed = bytes.clone();
+            followed.extend_from_slice(&[0xff, 0x00]);
+            assert_eq!(decode_varint(&followed).unwrap(), (value, bytes.len()), "{}", value);
+            // every prefix is cut off
+            for cut in 0..bytes.len() {
+                assert!(matches!(decode_varint(&bytes[..cut]).unwrap_err().kind(), ErrorKind::InvalidVarInt), "{} cut at {}", value, cut);
+            }
+        }
+
+        assert_eq!(encode_varint(0x7f), vec![0x7f]);
+        assert_eq!(encode_varint(0x80), vec![0x81, 0x00]);
+        assert_eq!(encode_varint(0x3fff), vec![0xff, 0x7f]);
+        assert_eq!(encode_varint(0x4000), vec![0x81, 0x80, 0x00]);
+        assert_eq!(encode_varint(0x00ff_ffff_ffff_ffff), vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        assert_eq!(encode_varint(0x0100_0000_0000_0000), vec![0x80, 0xc0, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00]);
+        // the ninth byte holds 8 bits, its high bit doesn't continue the varint
+        assert_eq!(decode_varint(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0xff, 0x01]).unwrap(), (0xff, 9));
+        // leading 0x80 bytes are allowed though never written
+        assert_eq!(decode_varint(&[0x80, 0x80, 0x05]).unwrap(), (5, 3));
+        assert!(matches!(decode_varint(&[]).unwrap_err().kind(), ErrorKind::InvalidVarInt));
+        assert!(decode_varint_to_usize(&[0x81]).is_err());
+    }
+
+    #[test]
+    fn test_signed_varint() {
+        for value in [0, 1, -1, -2, i64::MIN, i64::MAX, i64::MIN + 1, 1 << 56, -(1 << 56), (1 << 56) - 1] {
+            let bytes = encode_varint_i64(value);
+            assert_eq!(decode_varint_i64(&bytes).unwrap(), (value, bytes.len()), "{}", value);
+        }
+        assert_eq!(encode_varint_i64(-1), vec![0xff; 9]);
+        assert_eq!(encode_varint_i64(i64::MIN), vec![0xc0, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00]);
+        assert_eq!(encode_varint_i64(i64::MAX), vec![0xbf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        // negative values never have a shorter form
+        assert_eq!(encode_varint_i64(-2).len(), 9);
+    }
+
+    #[test]
+    fn test_base64(){
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(&[0xfb, 0xff]), "+/8=");
+    }
 }
@@ -0,0 +1,146 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::thread;
+    use std::time::Duration;
+
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::server::watch::{is_watched, subscribe, wal_path, ChangeTracker};
+
+    const SALTS: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    fn wal_frame(page_number: u32, commit_size: u32, salts: &[u8], page_size: usize) -> Vec<u8> {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&page_number.to_be_bytes());
+        frame.extend_from_slice(&commit_size.to_be_bytes());
+        frame.extend_from_slice(salts);
+        frame.extend_from_slice(&[0; 8]);
+        frame.extend_from_slice(&vec![0; page_size]);
+        frame
+    }
+
+    fn wal_header(page_size: usize, salts: &[u8]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&0x377f0682u32.to_be_bytes());
+        header.extend_from_slice(&3007000u32.to_be_bytes());
+        header.extend_from_slice(&(page_size as u32).to_be_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(salts);
+        header.extend_from_slice(&[0; 8]);
+        header
+    }
+
+    fn append(path: &str, bytes: &[u8]) {
+        OpenOptions::new().append(true).create(true).open(path).unwrap().write_all(bytes).unwrap();
+    }
+
+    #[test]
+    fn test_database_changes() {
+        let path = std::env::temp_dir().join("test_database_changes.db");
+        fs::copy("test-data/Chinook.db.4.analyze", &path).unwrap();
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(wal_path(path));
+
+        let mut tracker = ChangeTracker::new(path).unwrap();
+        assert!(tracker.poll().unwrap().is_empty());
+
+        let page_size = Database::open(path).unwrap().page_size();
+        let mut bytes = fs::read(path).unwrap();
+        let file_change_count = u32::from_be_bytes([bytes[24], bytes[25], bytes[26], bytes[27]]) + 1;
+        bytes[24..28].copy_from_slice(&file_change_count.to_be_bytes());
+        bytes[6 * page_size + 100] ^= 0xff;
+        fs::write(path, &bytes).unwrap();
+
+        let notices = tracker.poll().unwrap();
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].source, "database");
+        assert_eq!(notices[0].file_change_count, file_change_count);
+        assert_eq!(notices[0].pages, vec![1, 7]);
+        assert!(tracker.poll().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_wal_changes() {
+        let path = std::env::temp_dir().join("test_wal_changes.db");
+        fs::copy("test-data/Chinook.db.4.analyze", &path).unwrap();
+        let path = path.to_str().unwrap();
+        let wal = wal_path(path);
+        let page_size = Database::open(path).unwrap().page_size();
+
+        // the transaction already in the wal is not reported
+        fs::write(&wal, wal_header(page_size, &SALTS)).unwrap();
+        append(&wal, &wal_frame(2, 224, &SALTS, page_size));
+        let mut tracker = ChangeTracker::new(path).unwrap();
+        assert!(tracker.poll().unwrap().is_empty());
+
+        append(&wal, &wal_frame(5, 0, &SALTS, page_size));
+        append(&wal, &wal_frame(3, 0, &SALTS, page_size));
+        append(&wal, &wal_frame(5, 224, &SALTS, page_size));
+        append(&wal, &wal_frame(9, 224, &SALTS, page_size));
+        // not committed yet
+        append(&wal, &wal_frame(11, 0, &SALTS, page_size));
+        let notices = tracker.poll().unwrap();
+        let pages: Vec<Vec<u32>> = notices.iter().map(|notice| notice.pages.clone()).collect();
+        assert_eq!(pages, vec![vec![3, 5], vec![9]]);
+        assert!(notices.iter().all(|notice| notice.source == "wal"));
+
+        append(&wal, &wal_frame(12, 224, &SALTS, page_size));
+        let notices = tracker.poll().unwrap();
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].pages, vec![11, 12]);
+
+        // a checkpoint restarts the wal with new salts, over the old frames
+        let salts = [8, 7, 6, 5, 4, 3, 2, 1];
+        let mut bytes = fs::read(&wal).unwrap();
+        let restarted: Vec<u8> = wal_header(page_size, &salts).into_iter()
+            .chain(wal_frame(4, 224, &salts, page_size))
+            .collect();
+        bytes[..restarted.len()].copy_from_slice(&restarted);
+        fs::write(&wal, &bytes).unwrap();
+        let notices = tracker.poll().unwrap();
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].pages, vec![4]);
+
+        fs::remove_file(&wal).unwrap();
+        assert!(tracker.poll().unwrap().is_empty());
+    }
+
+    fn bump_file_change_count(path: &str) {
+        let mut bytes = fs::read(path).unwrap();
+        let file_change_count = u32::from_be_bytes([bytes[24], bytes[25], bytes[26], bytes[27]]) + 1;
+        bytes[24..28].copy_from_slice(&file_change_count.to_be_bytes());
+        fs::write(path, &bytes).unwrap();
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        let path = std::env::temp_dir().join("test_unsubscribe.db");
+        fs::copy("test-data/Chinook.db.4.analyze", &path).unwrap();
+        let path = path.to_str().unwrap();
+
+        let mut receiver = subscribe("test_unsubscribe", path).unwrap();
+        assert!(is_watched("test_unsubscribe"));
+        bump_file_change_count(path);
+        let mut event = None;
+        for _ in 0..50 {
+            if let Ok(Some(bytes)) = receiver.try_next() {
+                event = Some(bytes);
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(String::from_utf8(event.unwrap().to_vec()).unwrap().starts_with("event: change\ndata: {\"source\":\"database\""));
+
+        // the next change finds the event stream closed and the watcher stops
+        drop(receiver);
+        bump_file_change_count(path);
+        for _ in 0..50 {
+            if !is_watched("test_unsubscribe") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(!is_watched("test_unsubscribe"));
+    }
+}
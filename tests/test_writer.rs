@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod tests {
+    use sqlite_database_file_dissect::components::database::Database;
+    use sqlite_database_file_dissect::components::database_header::TextEncoding;
+    use sqlite_database_file_dissect::components::record::Column;
+    use sqlite_database_file_dissect::components::writer::{DatabaseWriter, WriterOptions};
+
+    const TRACK_SQL: &str = "CREATE TABLE track (id INTEGER PRIMARY KEY, name TEXT, bytes BLOB, price REAL)";
+
+    fn track_rows(count: usize) -> Vec<(i64, Vec<Column>)> {
+        (1..=count)
+            .map(|row_id| (row_id as i64, vec![
+                Column::NULL,
+                Column::STRING(format!("track {}", count - row_id)),
+                // every tenth row spills into overflow pages
+                Column::BLOB(vec![row_id as u8; if row_id % 10 == 0 { 3000 } else { 20 }].into_boxed_slice()),
+                Column::F64(row_id as f64 / 4.0),
+            ]))
+            .collect()
+    }
+
+    fn write(options: WriterOptions, row_count: usize, name: &str) -> Database {
+        let mut writer = DatabaseWriter::new(options).unwrap();
+        writer.add_table("track", TRACK_SQL, track_rows(row_count)).unwrap();
+        writer.add_index("track_name", "track", "CREATE INDEX track_name ON track (name)").unwrap();
+        writer.add_index("track_price", "track", "CREATE INDEX track_price ON track (price, id)").unwrap();
+        let file_name = std::env::temp_dir().join(name);
+        writer.write_to(file_name.to_str().unwrap()).unwrap();
+        Database::open(file_name.to_str().unwrap()).unwrap()
+    }
+
+    fn depth(db: &Database, page_number: u32) -> usize {
+        let node = db.btree_node(page_number).unwrap();
+        1 + node.children.first().map_or(0, |child| depth(db, child.page_number))
+    }
+
+    #[test]
+    fn test_writer() {
+        let db = write(WriterOptions { page_size: 512, ..Default::default() }, 1000, "test_writer.db");
+        let report = db.check();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(db.header().in_header_database_size, db.page_count());
+
+        let track = db.table("track").unwrap();
+        let rows = db.table_rows(track.entry.root_page).unwrap();
+        assert_eq!(rows.len(), 1000);
+        assert_eq!(rows.iter().map(|row| row.record.columns.clone()).collect::<Vec<Vec<Column>>>(),
+                   track_rows(1000).into_iter().map(|(_, columns)| columns).collect::<Vec<Vec<Column>>>());
+
+        let index = db.find_schema_entry("track_name").unwrap();
+        let entries = db.index_entries(index.root_page).unwrap();
+        assert_eq!(entries.len(), 1000);
+        let names: Vec<&str> = entries.iter().map(|entry| entry.columns[0].as_str().unwrap()).collect();
+        let mut sorted = names.clone();
+        sorted.sort_unstable();
+        assert_eq!(names, sorted);
+        assert_eq!(entries[0].columns[1].as_i64(), Some(1000));
+
+        let index = db.find_schema_entry("track_price").unwrap();
+        let entries = db.index_entries(index.root_page).unwrap();
+        assert_eq!(entries[999].columns, vec![Column::F64(250.0), Column::I16(1000), Column::I16(1000)]);
+    }
+
+    #[test]
+    fn test_writer_shapes() {
+        let options = WriterOptions { page_size: 1024, max_cells_per_page: Some(3), ..Default::default() };
+        let db = write(options, 200, "test_writer_deep.db");
+        assert!(db.check().is_ok());
+        let track = db.table("track").unwrap();
+        assert!(depth(&db, track.entry.root_page) >= 5);
+        assert_eq!(db.count_entries(db.find_schema_entry("track_name").unwrap().root_page).unwrap(), 200);
+
+        // more schema entries than page 1 holds put an interior page there, the names and rows
+        // read back in either UTF-16 byte order
+        for text_encoding in [TextEncoding::UTF16le, TextEncoding::UTF16be].iter() {
+            let mut writer = DatabaseWriter::new(WriterOptions { page_size: 512, reserved_bytes: 32, text_encoding: *text_encoding, ..Default::default() }).unwrap();
+            for idx in 0..40 {
+                writer.add_table(&format!("t{}", idx), &format!("CREATE TABLE t{} (a INTEGER, b TEXT)", idx), vec![(1, vec![Column::I1, Column::STRING(format!("x{} é", idx))])]).unwrap();
+            }
+            let file_name = std::env::temp_dir().join(format!("test_writer_schema_{:?}.db", text_encoding));
+            writer.write_to(file_name.to_str().unwrap()).unwrap();
+            let db = Database::open(file_name.to_str().unwrap()).unwrap();
+            assert!(db.check().is_ok());
+            assert_eq!(db.header().text_encoding, *text_encoding);
+            assert_eq!(db.usable_size(), 480);
+            assert!(db.btree_node(1).unwrap().children.len() > 1);
+
+            let schema = db.schema().unwrap();
+            let names: Vec<&str> = schema.iter().map(|entry| entry.name.as_str()).collect();
+            assert_eq!(names, (0..40).map(|idx| format!("t{}", idx)).collect::<Vec<String>>());
+            assert_eq!(schema[7].sql.as_deref(), Some("CREATE TABLE t7 (a INTEGER, b TEXT)"));
+            for (idx, entry) in schema.iter().enumerate() {
+                let rows = db.table_rows(entry.root_page).unwrap();
+                assert_eq!(rows[0].record.columns, vec![Column::I1, Column::STRING(format!("x{} é", idx))]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_writer_constraints() {
+        let mut writer = DatabaseWriter::new(WriterOptions::default()).unwrap();
+        let rows = (1..=300).map(|row_id| (row_id, vec![Column::NULL, Column::STRING(format!("code {}", row_id)), Column::integer(row_id % 7)])).collect();
+        writer.add_table("item", "CREATE TABLE item (id INTEGER PRIMARY KEY, code TEXT UNIQUE, kind INT, UNIQUE (kind, code))", rows).unwrap();
+        writer.add_schema_entry("view", "item_kind", "item_kind", "CREATE VIEW item_kind AS SELECT kind FROM item").unwrap();
+        // a collation sqlite doesn't have built in leaves the index out, not the table
+        let left_out = writer.add_table("custom", "CREATE TABLE custom (a TEXT COLLATE UNICODE UNIQUE)", Vec::new()).unwrap();
+        assert_eq!(left_out.len(), 1);
+        assert_eq!(left_out[0].0, "sqlite_autoindex_custom_1");
+        let file_name = std::env::temp_dir().join("test_writer_constraints.db");
+        writer.write_to(file_name.to_str().unwrap()).unwrap();
+
+        let db = Database::open(file_name.to_str().unwrap()).unwrap();
+        assert!(db.check().is_ok(), "{:?}", db.check().problems);
+        let schema = db.schema().unwrap();
+        let names: Vec<&str> = schema.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec!["item", "custom", "sqlite_autoindex_item_1", "sqlite_autoindex_item_2", "item_kind"]);
+        assert!(schema[2].sql.is_none());
+        assert_eq!(schema[4].root_page, 0);
+        assert_eq!(db.index_entries(schema[3].root_page).unwrap().len(), 300);
+    }
+
+    #[test]
+    fn test_writer_extreme_row_ids() {
+        let row_ids = [i64::MIN, -(1 << 53) - 1, -1, 0, 1, (1 << 53) - 1, 1 << 53, i64::MAX];
+        let mut writer = DatabaseWriter::new(WriterOptions { max_cells_per_page: Some(2), ..Default::default() }).unwrap();
+        let rows = row_ids.iter().rev().map(|&row_id| (row_id, vec![Column::NULL, Column::STRING(format!("row {}", row_id))])).collect();
+        writer.add_table("t", "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT UNIQUE)", rows).unwrap();
+        let file_name = std::env::temp_dir().join("test_writer_extreme_row_ids.db");
+        writer.write_to(file_name.to_str().unwrap()).unwrap();
+
+        let db = Database::open(file_name.to_str().unwrap()).unwrap();
+        assert!(db.check().is_ok(), "{:?}", db.check().problems);
+        let table = db.table("t").unwrap();
+        let rows = db.table_rows(table.entry.root_page).unwrap();
+        assert_eq!(rows.iter().map(|row| row.row_id).collect::<Vec<i64>>(), row_ids);
+        assert_eq!(table.values(&rows[0])[0], Column::I64(i64::MIN));
+        let index = db.find_schema_entry("sqlite_autoindex_t_1").unwrap();
+        let mut indexed: Vec<i64> = db.index_entries(index.root_page).unwrap().iter().map(|entry| entry.columns[1].as_i64().unwrap()).collect();
+        indexed.sort_unstable();
+        assert_eq!(indexed, row_ids);
+
+        // javascript reads integers below 2^53 exactly, larger ones are written as strings
+        let json: Vec<serde_json::Value> = rows.iter().map(|row| serde_json::to_value(row).unwrap()["row_id"].clone()).collect();
+        assert_eq!(json[0], serde_json::json!("-9223372036854775808"));
+        assert_eq!(json[1], serde_json::json!("-9007199254740993"));
+        assert_eq!(json[2], serde_json::json!(-1));
+        assert_eq!(json[5], serde_json::json!(9007199254740991i64));
+        assert_eq!(json[6], serde_json::json!("9007199254740992"));
+        assert_eq!(serde_json::to_value(&rows[7].record.columns).unwrap(), serde_json::json!(["NULL", {"STRING": "row 9223372036854775807"}]));
+        let mut cells = Vec::new();
+        db.walk_btree(table.entry.root_page, |cell| {
+            cells.push(serde_json::to_value(cell).unwrap());
+            Ok(())
+        }).unwrap();
+        assert_eq!(cells[0]["row_id"], serde_json::json!("-9223372036854775808"));
+        assert_eq!(cells[2]["row_id"], serde_json::json!(-1));
+        assert!(cells[0]["payload_length"].is_u64());
+    }
+
+    #[test]
+    fn test_writer_errors() {
+        assert!(DatabaseWriter::new(WriterOptions { page_size: 1000, ..Default::default() }).is_err());
+        let mut writer = DatabaseWriter::new(WriterOptions::default()).unwrap();
+        let rows = vec![(1, vec![Column::NULL]), (1, vec![Column::NULL])];
+        assert_eq!(writer.add_table("t", "CREATE TABLE t (a)", rows).unwrap_err().to_string(), "The rowid 1 is used by more than one row.");
+        writer.add_table("t", "CREATE TABLE t (a)", Vec::new()).unwrap();
+        assert!(writer.add_index("t_a", "t", "CREATE INDEX t_a ON t (a COLLATE UNICODE)").is_err());
+        assert!(writer.add_index("t_b", "t", "CREATE INDEX t_b ON t (b)").is_err());
+    }
+}